@@ -0,0 +1,447 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::{ecs::query::QueryData, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::factory_game::{
+    BaseLayer, ConveyorSystems,
+    chunks::ChunkedTileStorage,
+    conveyor::{Conveyor, ConveyorUpdated, cell_conveyor},
+    conveyor_belts::ConveyorBelt,
+    footprint::Footprint,
+    generator::Generator,
+    helpers::{ConveyorDirection, conveyor_directions, get_neighboring_positions},
+    operators::OperatorTile,
+    sink::Sink,
+};
+
+pub fn analysis_plugin(app: &mut App) {
+    app.init_resource::<ConveyorAnalysis>().add_systems(
+        Update,
+        analyze_conveyors
+            .after(ConveyorSystems::TileUpdater)
+            .before(ConveyorSystems::TransferPayloadsToHandlers),
+    );
+}
+
+/// Something about how a tile is connected that probably isn't what the
+/// player wants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Warning {
+    /// Payloads can go round and round a loop this tile is part of
+    Cycle,
+    /// The belt points at an empty tile
+    DeadEnd,
+    /// The belt points at a tile that won't take payloads from that side
+    Blocked,
+    /// Nothing the generator makes can reach a sink
+    NoPathToSink,
+    /// Only one of the operator's operands is fed
+    OneOperand,
+}
+
+impl Warning {
+    pub fn text(&self) -> &'static str {
+        match self {
+            Warning::Cycle => "payloads can loop forever",
+            Warning::DeadEnd => "belt points at nothing",
+            Warning::Blocked => "belt points at a tile that won't take its payloads",
+            Warning::NoPathToSink => "generator has no path to a sink",
+            Warning::OneOperand => "operator only has one operand fed",
+        }
+    }
+}
+
+/// The warnings for every tile on the base layer.  Those that only depend
+/// on a tile and its neighbours are rechecked for tiles around each change,
+/// as are the edges of the graph.  Loops and paths to sinks are only worked
+/// out again, for the whole map, when a change adds or removes an edge.
+#[derive(Resource, Debug, Default)]
+pub struct ConveyorAnalysis {
+    local: HashMap<TilePos, Warning>,
+    global: Vec<(TilePos, Warning)>,
+    edges: HashMap<Entity, Vec<Entity>>,
+}
+
+impl ConveyorAnalysis {
+    /// Every warning, ordered by tile
+    pub fn warnings(&self) -> Vec<(TilePos, Warning)> {
+        let mut warnings: Vec<_> = self
+            .local
+            .iter()
+            .map(|(pos, warning)| (*pos, *warning))
+            .chain(self.global.iter().copied())
+            .collect();
+        warnings.sort_by_key(|(pos, warning)| (pos.x, pos.y, *warning));
+        warnings
+    }
+
+    /// The tiles each tile can send payloads to, as of the last change
+    pub fn edges(&self) -> &HashMap<Entity, Vec<Entity>> {
+        &self.edges
+    }
+}
+
+#[derive(QueryData)]
+pub struct GraphTile {
+    entity: Entity,
+    tile_pos: &'static TilePos,
+    conveyor: &'static Conveyor,
+    footprint: Option<&'static Footprint>,
+    belt: Has<ConveyorBelt>,
+    sink: Has<Sink>,
+    generator: Has<Generator>,
+    operator: Has<OperatorTile>,
+}
+
+impl GraphTileItem<'_> {
+    /// Whether the tile takes payloads coming in from its `side` at `cell`.
+    /// Belts and sinks take them from anywhere and operators from either side
+    /// of their output, whatever their `Conveyor` inputs say.
+    fn accepts_from(&self, side: ConveyorDirection, cell: &TilePos) -> bool {
+        if self.sink {
+            true
+        } else if self.operator {
+            let output = self.conveyor.output();
+            side.is_left_of(output) || side.is_right_of(output)
+        } else if self.belt {
+            self.conveyor.single_or_no_output() != Some(side)
+        } else {
+            self.conveyor_at(cell).inputs().is_set(side)
+        }
+    }
+
+    fn conveyor_at(&self, cell: &TilePos) -> Conveyor {
+        cell_conveyor((self.conveyor, self.footprint, self.tile_pos), cell)
+    }
+
+    pub fn is_belt(&self) -> bool {
+        self.belt
+    }
+
+    fn cells(&self) -> Vec<TilePos> {
+        match self.footprint {
+            Some(footprint) => footprint.cells(self.tile_pos).collect(),
+            None => vec![*self.tile_pos],
+        }
+    }
+}
+
+enum Destination {
+    Empty,
+    Blocked,
+    Accepts(Entity),
+}
+
+/// The base layer's tiles, linked by where each sends its payloads
+pub struct Graph<'w, 's, 'a> {
+    tile_storage: &'a ChunkedTileStorage,
+    map_size: &'a TilemapSize,
+    map_type: &'a TilemapType,
+    tiles: &'a Query<'w, 's, GraphTile>,
+}
+
+impl<'w, 's, 'a> Graph<'w, 's, 'a> {
+    pub fn new(
+        tile_storage: &'a ChunkedTileStorage,
+        map_size: &'a TilemapSize,
+        map_type: &'a TilemapType,
+        tiles: &'a Query<'w, 's, GraphTile>,
+    ) -> Self {
+        Graph {
+            tile_storage,
+            map_size,
+            map_type,
+            tiles,
+        }
+    }
+
+    /// The tiles each tile can send payloads to
+    pub fn edges(&self) -> HashMap<Entity, Vec<Entity>> {
+        self.tiles
+            .iter()
+            .map(|tile| (tile.entity, self.next(&tile)))
+            .collect()
+    }
+
+    /// The tiles `tile` can send payloads to
+    fn next(&self, tile: &GraphTileItem) -> Vec<Entity> {
+        let mut next = Vec::new();
+        for destination in self.destinations(tile) {
+            if let Destination::Accepts(entity) = destination
+                && !next.contains(&entity)
+            {
+                next.push(entity);
+            }
+        }
+        next
+    }
+
+    /// Where each of a tile's outputs leads.  Machines covering several
+    /// tiles only output from the cells their output ports are on.
+    fn destinations(&self, tile: &GraphTileItem) -> Vec<Destination> {
+        let cells = tile.cells();
+        let mut destinations = Vec::new();
+        for cell in &cells {
+            let outputs = tile.conveyor_at(cell).outputs();
+            for direction in conveyor_directions(self.map_type) {
+                if !outputs.is_set(*direction) {
+                    continue;
+                }
+                let Some(pos) = direction.offset(cell, self.map_size) else {
+                    destinations.push(Destination::Empty);
+                    continue;
+                };
+                if cells.contains(&pos) {
+                    continue;
+                }
+                let destination = self.tile_storage.get(&pos);
+                destinations.push(
+                    match destination.and_then(|entity| self.tiles.get(entity).ok()) {
+                        None => Destination::Empty,
+                        Some(other) if other.accepts_from(direction.opposite(), &pos) => {
+                            Destination::Accepts(other.entity)
+                        }
+                        Some(_) => Destination::Blocked,
+                    },
+                );
+            }
+        }
+        destinations
+    }
+
+    fn local_warning(&self, tile: &GraphTileItem) -> Option<Warning> {
+        if tile.belt {
+            return match self.destinations(tile).first()? {
+                Destination::Empty => Some(Warning::DeadEnd),
+                Destination::Blocked => Some(Warning::Blocked),
+                Destination::Accepts(_) => None,
+            };
+        }
+
+        if tile.operator {
+            let output = tile.conveyor.output();
+            let fed_sides: Vec<_> = conveyor_directions(self.map_type)
+                .iter()
+                .filter(|side| self.is_fed_from(tile, **side))
+                .collect();
+            let left = fed_sides.iter().any(|side| side.is_left_of(output));
+            let right = fed_sides.iter().any(|side| side.is_right_of(output));
+            return (left != right).then_some(Warning::OneOperand);
+        }
+
+        None
+    }
+
+    /// Whether the neighbour on `side` outputs into the tile
+    fn is_fed_from(&self, tile: &GraphTileItem, side: ConveyorDirection) -> bool {
+        side.offset(tile.tile_pos, self.map_size)
+            .is_some_and(|pos| {
+                self.tile_storage
+                    .get(&pos)
+                    .and_then(|entity| self.tiles.get(entity).ok())
+                    .is_some_and(|neighbor| {
+                        neighbor.conveyor_at(&pos).outputs().is_set(side.opposite())
+                    })
+            })
+    }
+}
+
+fn analyze_conveyors(
+    mut analysis: ResMut<ConveyorAnalysis>,
+    changed: Query<&TilePos, Changed<Conveyor>>,
+    mut updated: EventReader<ConveyorUpdated>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
+    tiles: Query<GraphTile>,
+) {
+    let mut to_check: HashSet<TilePos> = changed.iter().copied().collect();
+    to_check.extend(updated.read().map(|updated| updated.0));
+    if to_check.is_empty() {
+        return;
+    }
+
+    let (tile_storage, map_size, map_type) = base.into_inner();
+    let graph = Graph::new(tile_storage, map_size, map_type, &tiles);
+
+    // Machines covering several tiles can be fed through any of their cells
+    let changed: Vec<_> = to_check
+        .iter()
+        .flat_map(|pos| {
+            tile_storage
+                .get(pos)
+                .and_then(|entity| tiles.get(entity).ok())
+                .map_or(vec![*pos], |tile| tile.cells())
+        })
+        .collect();
+    for pos in changed {
+        to_check.insert(pos);
+        to_check.extend(get_neighboring_positions(&pos, map_size, map_type).iter());
+    }
+
+    // Tiles that have been removed or replaced
+    let before = analysis.edges.len();
+    analysis.edges.retain(|entity, _| tiles.contains(*entity));
+    let mut edges_changed = analysis.edges.len() != before;
+    for entity in to_check.iter().filter_map(|pos| tile_storage.get(pos)) {
+        let Ok(tile) = tiles.get(entity) else {
+            continue;
+        };
+        let next = graph.next(&tile);
+        if analysis.edges.get(&entity) != Some(&next) {
+            analysis.edges.insert(entity, next);
+            edges_changed = true;
+        }
+    }
+
+    for pos in to_check {
+        let warning = tile_storage
+            .get(&pos)
+            .and_then(|entity| tiles.get(entity).ok())
+            .filter(|tile| tile.tile_pos == &pos)
+            .and_then(|tile| graph.local_warning(&tile));
+        match warning {
+            Some(warning) => analysis.local.insert(pos, warning),
+            None => analysis.local.remove(&pos),
+        };
+    }
+
+    if !edges_changed {
+        return;
+    }
+    let in_cycles = tiles_in_cycles(&analysis.edges);
+    let reach_sinks = tiles_reaching(
+        &analysis.edges,
+        tiles.iter().filter(|t| t.sink).map(|t| t.entity),
+    );
+
+    analysis.global = tiles
+        .iter()
+        .filter_map(|tile| {
+            if in_cycles.contains(&tile.entity) {
+                Some((*tile.tile_pos, Warning::Cycle))
+            } else if tile.generator && !reach_sinks.contains(&tile.entity) {
+                Some((*tile.tile_pos, Warning::NoPathToSink))
+            } else {
+                None
+            }
+        })
+        .collect();
+}
+
+/// The tiles in a strongly connected component with more than one tile, or
+/// that output to themselves, found with Kosaraju's algorithm.  Searches are
+/// iterative, as belts can be long.
+fn tiles_in_cycles(edges: &HashMap<Entity, Vec<Entity>>) -> HashSet<Entity> {
+    let next = |entity: &Entity| edges.get(entity).map_or(&[][..], Vec::as_slice);
+
+    // Tiles in the order their searches finish
+    let mut finished = Vec::new();
+    let mut visited = HashSet::new();
+    for &start in edges.keys() {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut stack = vec![(start, 0)];
+        while let Some((entity, index)) = stack.pop() {
+            if let Some(&other) = next(&entity).get(index) {
+                stack.push((entity, index + 1));
+                if visited.insert(other) {
+                    stack.push((other, 0));
+                }
+            } else {
+                finished.push(entity);
+            }
+        }
+    }
+
+    let mut reversed: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for (from, tos) in edges {
+        for to in tos {
+            reversed.entry(*to).or_default().push(*from);
+        }
+    }
+
+    let mut in_cycles = HashSet::new();
+    let mut assigned = HashSet::new();
+    for &root in finished.iter().rev() {
+        if !assigned.insert(root) {
+            continue;
+        }
+        let mut component = vec![root];
+        let mut stack = vec![root];
+        while let Some(entity) = stack.pop() {
+            for &other in reversed.get(&entity).into_iter().flatten() {
+                if assigned.insert(other) {
+                    component.push(other);
+                    stack.push(other);
+                }
+            }
+        }
+        if component.len() > 1 || next(&root).contains(&root) {
+            in_cycles.extend(component);
+        }
+    }
+    in_cycles
+}
+
+/// The tiles with a path to any of `targets`, including the targets
+fn tiles_reaching(
+    edges: &HashMap<Entity, Vec<Entity>>,
+    targets: impl Iterator<Item = Entity>,
+) -> HashSet<Entity> {
+    let mut reversed: HashMap<Entity, Vec<Entity>> = HashMap::new();
+    for (from, tos) in edges {
+        for to in tos {
+            reversed.entry(*to).or_default().push(*from);
+        }
+    }
+
+    let mut reaching: HashSet<Entity> = targets.collect();
+    let mut stack: Vec<_> = reaching.iter().copied().collect();
+    while let Some(entity) = stack.pop() {
+        for &other in reversed.get(&entity).into_iter().flatten() {
+            if reaching.insert(other) {
+                stack.push(other);
+            }
+        }
+    }
+    reaching
+}
+
+#[cfg(test)]
+mod analysis_test {
+    use super::*;
+
+    fn edges(pairs: &[(u32, u32)]) -> HashMap<Entity, Vec<Entity>> {
+        let mut edges: HashMap<Entity, Vec<Entity>> = HashMap::new();
+        for (from, to) in pairs {
+            edges
+                .entry(Entity::from_raw(*from))
+                .or_default()
+                .push(Entity::from_raw(*to));
+            edges.entry(Entity::from_raw(*to)).or_default();
+        }
+        edges
+    }
+
+    fn entities(ids: &[u32]) -> HashSet<Entity> {
+        ids.iter().copied().map(Entity::from_raw).collect()
+    }
+
+    #[test]
+    fn finds_loops_but_not_what_feeds_them() {
+        // 1 feeds the loop 2 -> 3 -> 4 -> 2, which leaks into 5; 6 loops on
+        // itself
+        let edges = edges(&[(1, 2), (2, 3), (3, 4), (4, 2), (4, 5), (6, 6)]);
+        assert_eq!(tiles_in_cycles(&edges), entities(&[2, 3, 4, 6]));
+    }
+
+    #[test]
+    fn finds_what_reaches_targets() {
+        let edges = edges(&[(1, 2), (2, 3), (4, 5), (5, 4)]);
+        assert_eq!(
+            tiles_reaching(&edges, [Entity::from_raw(3)].into_iter()),
+            entities(&[1, 2, 3])
+        );
+    }
+}
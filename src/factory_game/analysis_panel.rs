@@ -0,0 +1,104 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        analysis::{ConveyorAnalysis, Warning},
+    },
+    helpers::TilemapQuery,
+};
+
+pub fn analysis_panel_plugin(app: &mut App) {
+    app.init_resource::<AnalysisPanel>()
+        .add_systems(
+            Update,
+            (
+                toggle_analysis_panel
+                    .run_if(input_just_pressed(KeyCode::KeyV))
+                    .run_if(not(egui_wants_any_input)),
+                draw_warning_markers.run_if(|panel: Res<AnalysisPanel>| panel.open),
+            )
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_analysis_panel.run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_analysis_panel);
+}
+
+/// Whether the conveyor checks are shown, both as a list and as markers on
+/// the map
+#[derive(Resource, Default)]
+struct AnalysisPanel {
+    open: bool,
+}
+
+fn toggle_analysis_panel(mut panel: ResMut<AnalysisPanel>) {
+    panel.open = !panel.open;
+}
+
+fn close_analysis_panel(mut panel: ResMut<AnalysisPanel>) {
+    panel.open = false;
+}
+
+fn warning_color(warning: Warning) -> Color {
+    match warning {
+        Warning::Cycle => Color::srgb(0.9, 0.3, 0.9),
+        Warning::DeadEnd | Warning::Blocked => Color::srgb(1.0, 0.3, 0.2),
+        Warning::NoPathToSink => Color::srgb(1.0, 0.7, 0.1),
+        Warning::OneOperand => Color::srgb(0.3, 0.7, 1.0),
+    }
+}
+
+/// Rings the tiles that have warnings, in a colour for each kind
+fn draw_warning_markers(
+    mut gizmos: Gizmos,
+    analysis: Res<ConveyorAnalysis>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    let radius = base.grid_size.x.min(base.grid_size.y) * 0.45;
+    for (tile_pos, warning) in analysis.warnings() {
+        gizmos.circle_2d(
+            Isometry2d::from_translation(base.center_in_world(&tile_pos)),
+            radius,
+            warning_color(warning),
+        );
+    }
+}
+
+fn show_analysis_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<AnalysisPanel>,
+    analysis: Res<ConveyorAnalysis>,
+) -> Result {
+    if !panel.open {
+        return Ok(());
+    }
+
+    let warnings = analysis.warnings();
+
+    egui::Window::new("Conveyor checks (V)")
+        .open(&mut panel.open)
+        .resizable(false)
+        .show(contexts.ctx_mut()?, |ui| {
+            if warnings.is_empty() {
+                ui.label("No problems found");
+                return;
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for (tile_pos, warning) in warnings {
+                    let [r, g, b, _] = warning_color(warning).to_srgba().to_u8_array();
+                    ui.colored_label(
+                        egui::Color32::from_rgb(r, g, b),
+                        format!("{}, {}: {}", tile_pos.x, tile_pos.y, warning.text()),
+                    );
+                }
+            });
+        });
+
+    Ok(())
+}
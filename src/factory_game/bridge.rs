@@ -4,9 +4,11 @@ use bevy_ecs_tilemap::prelude::*;
 use crate::{
     factory_game::{
         BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
         conveyor::{Conveyor, ConveyorUpdated, TilesToCheck},
         conveyor_belts::find_incoming_directions,
-        helpers::{ConveyorDirection, ConveyorDirections},
+        footprint::Footprint,
+        helpers::{CONVEYOR_DIRECTIONS, ConveyorDirection, ConveyorDirections},
         interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
         payload_handler::{AddPayloadHandler, PayloadHandler},
         payloads::{Payload, PayloadTransportLine, RequestPayloadTransferEvent},
@@ -34,6 +36,10 @@ impl Tool for BridgeTool {
         (GameSprite::BridgeBoth, TileFlip::default())
     }
 
+    fn cost(&self) -> u32 {
+        PlaceBridgeEvent(TilePos::default()).cost()
+    }
+
     fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
         commands.trigger(PlaceBridgeEvent(*tile_pos));
     }
@@ -47,23 +53,35 @@ impl PlaceTileEvent for PlaceBridgeEvent {
         self.0
     }
 
+    fn cost(&self) -> u32 {
+        5
+    }
+
     fn configure_new_entity(&self, mut commands: EntityCommands) {
         commands.insert((Bridge::default(), Name::new("Bridge")));
     }
 }
 
+/// Number of lines through a bridge, one for each pair of opposite
+/// directions.  Square maps only use two, and hex maps three.
+const AXES: usize = CONVEYOR_DIRECTIONS.len() / 2;
+
+/// The line through a bridge that payloads going `direction` use.  East to
+/// West goes over the top and the rest underneath.
+fn axis(direction: ConveyorDirection) -> usize {
+    direction.index() % AXES
+}
+
 #[derive(Component, Reflect, Debug)]
 pub struct BridgeConveyor {
-    top: Option<PayloadTransportLine>,
-    bottom: Option<PayloadTransportLine>,
+    lines: [Option<PayloadTransportLine>; AXES],
     capacity: u32,
 }
 
 impl Default for BridgeConveyor {
     fn default() -> Self {
         Self {
-            top: Default::default(),
-            bottom: Default::default(),
+            lines: Default::default(),
             capacity: 5,
         }
     }
@@ -75,31 +93,26 @@ impl PayloadHandler for BridgeConveyor {
         _: &Conveyor,
         request: &RequestPayloadTransferEvent,
     ) -> Option<Entity> {
-        use ConveyorDirection::*;
-
-        let transport = match request.direction {
-            North | South => self.bottom.as_mut(),
-            East | West => self.top.as_mut(),
-        };
-
-        transport.and_then(|transport| {
-            transport.try_transfer_onto(request.direction.opposite(), || request.payload)
-        })
+        self.lines[axis(request.direction)]
+            .as_mut()
+            .and_then(|transport| {
+                transport.try_transfer_onto(request.direction.opposite(), || request.payload)
+            })
     }
 
     fn remove_payload(&mut self, payload: Entity) {
-        if let Some(top) = &mut self.top {
-            top.remove_payload(payload);
-        }
-        if let Some(bottom) = &mut self.bottom {
-            bottom.remove_payload(payload);
-        }
+        self.lines
+            .iter_mut()
+            .flatten()
+            .for_each(|line| line.remove_payload(payload));
     }
 
     fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
-        std::iter::empty()
-            .chain(self.top.iter().flat_map(|t| t.iter_payloads()))
-            .chain(self.bottom.iter().flat_map(|b| b.iter_payloads()))
+        self.lines.iter().flatten().flat_map(|l| l.iter_payloads())
+    }
+
+    fn transport_lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        self.lines.iter().flatten()
     }
 }
 
@@ -110,20 +123,19 @@ impl BridgeConveyor {
         payloads: &mut Query<&mut Transform, With<Payload>>,
         base: &TilemapQueryItem,
     ) {
-        if let Some(top) = &self.top {
-            top.update_payload_transforms(tile_pos, payloads, base);
-        }
-        if let Some(bottom) = &self.bottom {
-            bottom.update_payload_transforms(tile_pos, payloads, base);
+        for line in self.lines.iter().flatten() {
+            line.update_payload_transforms(tile_pos, payloads, base);
         }
     }
 
-    fn current_bottom_output(&self) -> Option<ConveyorDirection> {
-        self.bottom.as_ref().map(|bottom| bottom.output_direction())
+    pub fn lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        self.lines.iter().flatten()
     }
 
-    fn current_top_output(&self) -> Option<ConveyorDirection> {
-        self.top.as_ref().map(|top| top.output_direction())
+    fn current_outputs(&self) -> [Option<ConveyorDirection>; AXES] {
+        self.lines
+            .each_ref()
+            .map(|line| line.as_ref().map(|line| line.output_direction()))
     }
 }
 
@@ -142,11 +154,11 @@ pub struct BridgeTop(Entity);
 fn update_bridge_conveyors(
     to_check: Res<TilesToCheck>,
     mut bridge_conveyors: Query<&mut BridgeConveyor>,
-    mut conveyors: Query<&mut Conveyor>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    mut conveyors: Query<(&mut Conveyor, Option<&Footprint>, &TilePos)>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
     mut conveyor_updated: EventWriter<ConveyorUpdated>,
 ) {
-    let (tile_storage, map_size) = base.into_inner();
+    let (tile_storage, map_size, map_type) = base.into_inner();
 
     for tile_pos in &to_check.0 {
         if let Some(entity) = tile_storage.get(tile_pos)
@@ -156,49 +168,31 @@ fn update_bridge_conveyors(
                 tile_pos,
                 tile_storage,
                 map_size,
+                map_type,
                 &conveyors.as_readonly(),
             );
 
-            if let Ok(mut conveyor) = conveyors.get_mut(entity) {
+            if let Ok((mut conveyor, ..)) = conveyors.get_mut(entity) {
                 let old_value = conveyor.clone();
 
                 conveyor.set_inputs(inputs);
 
-                use ConveyorDirection::*;
-
-                let wanted_bottom_output = if inputs.is_set(North) {
-                    Some(South)
-                } else if inputs.is_set(South) {
-                    Some(North)
-                } else {
-                    None
-                };
-
-                let wanted_top_output = if inputs.is_set(East) {
-                    Some(West)
-                } else if inputs.is_set(West) {
-                    Some(East)
-                } else {
-                    None
-                };
-
-                let mut outputs = ConveyorDirections::default();
-                wanted_bottom_output
-                    .iter()
-                    .chain(wanted_top_output.iter())
-                    .for_each(|output| outputs.add(*output));
-
-                conveyor.set_outputs(outputs);
-
-                let current_bottom_output = bridge.current_bottom_output();
-                if current_bottom_output != wanted_bottom_output {
-                    bridge.bottom = wanted_bottom_output
-                        .map(|output| PayloadTransportLine::new(output, bridge.capacity));
+                // Each line carries on straight from whichever of its inputs
+                // comes first clockwise from North
+                let mut wanted_outputs = [None; AXES];
+                for input in inputs.iter() {
+                    wanted_outputs[axis(input)].get_or_insert(input.opposite());
                 }
-                let current_top_output = bridge.current_top_output();
-                if current_top_output != wanted_top_output {
-                    bridge.top = wanted_top_output
-                        .map(|output| PayloadTransportLine::new(output, bridge.capacity));
+
+                conveyor.set_outputs(wanted_outputs.iter().flatten().copied().into());
+
+                let current_outputs = bridge.current_outputs();
+                let capacity = bridge.capacity;
+                for (i, wanted_output) in wanted_outputs.into_iter().enumerate() {
+                    if current_outputs[i] != wanted_output {
+                        bridge.lines[i] =
+                            wanted_output.map(|output| PayloadTransportLine::new(output, capacity));
+                    }
                 }
 
                 if old_value != *conveyor {
@@ -212,7 +206,7 @@ fn update_bridge_conveyors(
 fn update_bridge_payloads(
     bridges: Query<(Entity, &mut BridgeConveyor, &TilePos)>,
     time: Res<Time>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
     mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
 ) {
     let (tile_storage, map_size) = base.into_inner();
@@ -220,18 +214,8 @@ fn update_bridge_payloads(
     let t = time.delta_secs();
 
     for (source, mut bridge, tile_pos) in bridges {
-        if let Some(top) = &mut bridge.top {
-            top.update(
-                source,
-                tile_pos,
-                t,
-                tile_storage,
-                map_size,
-                &mut send_payloads,
-            );
-        }
-        if let Some(bottom) = &mut bridge.bottom {
-            bottom.update(
+        for line in bridge.lines.iter_mut().flatten() {
+            line.update(
                 source,
                 tile_pos,
                 t,
@@ -0,0 +1,473 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer, MapConfig,
+        chunks::{ChunkedTileStorage, UNBOUNDED_MAP_SIZE},
+        economy::Money,
+        generator::{Generator, PlaceGeneratorEvent, Sequence},
+        goals::{Goal, GoalCompleted, PlaceGoalSinkEvent},
+        interaction::Locked,
+        power::Power,
+        research::Research,
+        tiled_map::TiledMap,
+        value::Value,
+    },
+    helpers::{asset_path, user_data_path},
+};
+
+pub fn campaign_plugin(app: &mut App) {
+    app.insert_resource(Levels::load(asset_path(LEVELS_DIR)))
+        .insert_resource(BestScores::load(user_data_path(BEST_SCORES_PATH)))
+        .add_systems(
+            OnEnter(GameState::FactoryGame),
+            (
+                place_level_tiles.after(super::make_base_layer),
+                create_level_ui,
+            )
+                .run_if(resource_exists::<CurrentLevel>),
+        )
+        .add_systems(
+            Update,
+            (
+                complete_level
+                    .run_if(on_event::<GoalCompleted>)
+                    .run_if(resource_exists::<CurrentLevel>)
+                    .run_if(in_state(GameState::FactoryGame)),
+                save_best_scores.run_if(resource_changed::<BestScores>),
+            )
+                .chain(),
+        );
+}
+
+/// In the assets directory
+const LEVELS_DIR: &str = "levels";
+/// In the player's data directory
+const BEST_SCORES_PATH: &str = "saves/best_scores.json";
+
+/// A puzzle: a map with some tiles already placed that can't be changed, and
+/// only some of the tools to solve it with.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Level {
+    /// The level file's name, which best scores are saved under
+    #[serde(skip)]
+    pub id: String,
+    pub name: String,
+    /// Width and height of the map in tiles
+    pub size: [u32; 2],
+    /// Slots of the tools the player may use
+    pub tools: Vec<u32>,
+    pub tiles: Vec<LevelTile>,
+    pub scoring: Scoring,
+}
+
+/// A tile placed by a level
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LevelTile {
+    /// Emits each of `values` in turn
+    Generator {
+        pos: [u32; 2],
+        values: Vec<Value>,
+        #[serde(default = "default_seconds_between")]
+        seconds_between: f32,
+    },
+    Goal {
+        pos: [u32; 2],
+        target: Value,
+        quantity: u32,
+        #[serde(default)]
+        min_rate: Option<f32>,
+    },
+}
+
+fn default_seconds_between() -> f32 {
+    1.0
+}
+
+/// Completing a level earns a star, and there's one more each for using at
+/// most `max_tiles` tiles and for delivering at least `min_throughput`
+/// payloads a second.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Scoring {
+    pub max_tiles: usize,
+    pub min_throughput: f32,
+}
+
+impl Scoring {
+    pub fn stars(&self, score: &Score) -> u32 {
+        1 + (score.tiles <= self.max_tiles) as u32
+            + (score.throughput >= self.min_throughput) as u32
+    }
+
+    /// More stars is better, then fewer tiles, then more throughput
+    pub fn is_better(&self, score: &Score, than: &Score) -> bool {
+        let (stars, than_stars) = (self.stars(score), self.stars(than));
+        stars > than_stars
+            || (stars == than_stars
+                && (score.tiles < than.tiles
+                    || (score.tiles == than.tiles && score.throughput > than.throughput)))
+    }
+}
+
+impl LevelTile {
+    fn tile_pos(&self) -> TilePos {
+        let (LevelTile::Generator { pos, .. } | LevelTile::Goal { pos, .. }) = self;
+        TilePos {
+            x: pos[0],
+            y: pos[1],
+        }
+    }
+}
+
+impl Level {
+    pub fn parse(id: &str, json: &str) -> serde_json::Result<Level> {
+        let mut level: Level = serde_json::from_str(json)?;
+        level.id = id.to_string();
+        Ok(level)
+    }
+
+    pub fn allows_tool(&self, slot: u32) -> bool {
+        self.tools.contains(&slot)
+    }
+}
+
+/// The levels of the campaign, in the order of their file names
+#[derive(Resource, Default)]
+pub struct Levels(Vec<Level>);
+
+impl Levels {
+    fn load(dir: impl AsRef<Path>) -> Self {
+        let Ok(entries) = fs::read_dir(dir) else {
+            warn!("No levels found");
+            return Levels::default();
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        let levels = paths
+            .iter()
+            .filter_map(|path| {
+                let id = path.file_stem()?.to_string_lossy();
+                let json = fs::read_to_string(path)
+                    .inspect_err(|e| warn!("Can't read level {path:?}: {e}"))
+                    .ok()?;
+                Level::parse(&id, &json)
+                    .inspect_err(|e| warn!("Can't parse level {path:?}: {e}"))
+                    .ok()
+            })
+            .collect();
+
+        Levels(levels)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Level> {
+        self.0.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Level> {
+        self.0.iter()
+    }
+}
+
+/// How well a level was solved
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Score {
+    /// How many tiles the player placed
+    pub tiles: usize,
+    /// Payloads delivered per second to all the goals together
+    pub throughput: f32,
+}
+
+/// The best score for each level, by level id
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct BestScores(HashMap<String, Score>);
+
+impl BestScores {
+    fn load(path: impl AsRef<Path>) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .inspect_err(|e| warn!("Can't parse best scores: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn get(&self, level: &Level) -> Option<&Score> {
+        self.0.get(&level.id)
+    }
+
+    /// Keeps `score` if it's better than the best so far, returning whether
+    /// it was.
+    pub fn record(&mut self, level: &Level, score: Score) -> bool {
+        let better = self
+            .get(level)
+            .is_none_or(|best| level.scoring.is_better(&score, best));
+        if better {
+            self.0.insert(level.id.clone(), score);
+        }
+        better
+    }
+}
+
+/// The level being played.  There's none in the sandbox.
+#[derive(Resource, Debug)]
+pub struct CurrentLevel(pub Level);
+
+impl CurrentLevel {
+    pub fn allows_tool(&self, slot: u32) -> bool {
+        self.0.allows_tool(slot)
+    }
+}
+
+/// Starts the factory game, playing `level` or else in the sandbox.  Only
+/// the sandbox has an economy, research and power, and its map is
+/// effectively unbounded.
+pub fn play_level(commands: &mut Commands, level: Option<&Level>) {
+    let mut config = MapConfig::default();
+    commands.remove_resource::<TiledMap>();
+
+    match level {
+        Some(level) => {
+            config.size = TilemapSize {
+                x: level.size[0],
+                y: level.size[1],
+            };
+            commands.insert_resource(CurrentLevel(level.clone()));
+            commands.remove_resource::<Money>();
+            commands.remove_resource::<Research>();
+            commands.remove_resource::<Power>();
+        }
+        None => {
+            commands.remove_resource::<CurrentLevel>();
+            config.size = UNBOUNDED_MAP_SIZE;
+            config.deposits = true;
+            commands.insert_resource(Money::default());
+            commands.insert_resource(Research::default());
+            commands.insert_resource(Power::default());
+        }
+    }
+
+    commands.insert_resource(config);
+    commands.set_state(GameState::FactoryGame);
+}
+
+/// Starts the sandbox on a hex map, whose rows of tiles are laid like
+/// bricks.  Deposits are only generated for square maps, so generators can
+/// go anywhere.
+pub fn play_hex_sandbox(commands: &mut Commands) {
+    play_level(commands, None);
+
+    let config = MapConfig::default();
+    commands.insert_resource(MapConfig {
+        size: UNBOUNDED_MAP_SIZE,
+        // Hex rows are three quarters of the grid height apart, so this
+        // stacks them without gaps
+        grid_size: TilemapGridSize {
+            x: config.tile_size.x,
+            y: config.tile_size.y * 4.0 / 3.0,
+        },
+        map_type: TilemapType::Hexagon(HexCoordSystem::Row),
+        ..config
+    });
+}
+
+/// Places the current level's tiles and locks them so that the player can't
+/// change them.
+pub fn place_level_tiles(world: &mut World) {
+    let Some(level) = world.get_resource::<CurrentLevel>() else {
+        return;
+    };
+    let tiles = level.0.tiles.clone();
+
+    for tile in tiles {
+        let tile_pos = tile.tile_pos();
+        match &tile {
+            LevelTile::Generator { .. } => world.trigger(PlaceGeneratorEvent(tile_pos)),
+            LevelTile::Goal {
+                target,
+                quantity,
+                min_rate,
+                ..
+            } => world.trigger(PlaceGoalSinkEvent(
+                tile_pos,
+                Goal::new(target.clone(), *quantity, *min_rate),
+            )),
+        }
+        world.flush();
+
+        let Some(entity) = world
+            .query_filtered::<&ChunkedTileStorage, With<BaseLayer>>()
+            .single(world)
+            .ok()
+            .and_then(|storage| storage.get(&tile_pos))
+        else {
+            warn!("Level tile {tile:?} is off the map");
+            continue;
+        };
+
+        let mut entity = world.entity_mut(entity);
+        entity.insert(Locked);
+
+        if let LevelTile::Generator {
+            values,
+            seconds_between,
+            ..
+        } = tile
+            && let Some(mut generator) = entity.get_mut::<Generator>()
+        {
+            generator.sequence = match <[Value; 1]>::try_from(values) {
+                Ok([value]) => Sequence::Constant(value),
+                Err(values) => Sequence::Cycle { values, index: 0 },
+            };
+            generator.time_between_generations = seconds_between;
+        }
+    }
+}
+
+#[derive(Component)]
+struct LevelStatusText;
+
+fn create_level_ui(mut commands: Commands, level: Res<CurrentLevel>) {
+    let scoring = &level.0.scoring;
+
+    commands.spawn((
+        StateScoped(GameState::FactoryGame),
+        LevelStatusText,
+        Text::new(format!(
+            "{}\nPar: {} tiles, {:.1}/s",
+            level.0.name, scoring.max_tiles, scoring.min_throughput
+        )),
+        TextFont::from_font_size(14.0),
+        Node {
+            margin: UiRect::all(Val::Px(10.0)),
+            align_self: AlignSelf::Start,
+            justify_self: JustifySelf::End,
+            ..default()
+        },
+    ));
+}
+
+#[expect(clippy::type_complexity)]
+fn complete_level(
+    level: Res<CurrentLevel>,
+    goals: Query<&Goal>,
+    player_tiles: Query<(), (With<BaseLayer>, With<TilePos>, Without<Locked>)>,
+    mut best_scores: ResMut<BestScores>,
+    texts: Query<&mut Text, With<LevelStatusText>>,
+) {
+    let level = &level.0;
+    let score = Score {
+        tiles: player_tiles.iter().count(),
+        throughput: goals.iter().map(Goal::rate).sum(),
+    };
+    let new_best = best_scores.record(level, score);
+
+    let mut message = format!(
+        "{}\nComplete! {}/3 stars\n{} tiles, {:.1}/s",
+        level.name,
+        level.scoring.stars(&score),
+        score.tiles,
+        score.throughput
+    );
+    if new_best {
+        message += "\nNew best!";
+    }
+    for mut text in texts {
+        text.0 = message.clone();
+    }
+}
+
+fn save_best_scores(best_scores: Res<BestScores>) {
+    if let Err(e) = best_scores.save(user_data_path(BEST_SCORES_PATH)) {
+        warn!("Can't save best scores: {e}");
+    }
+}
+
+#[cfg(test)]
+mod campaign_test {
+    use super::*;
+
+    const LEVEL: &str = r##"{
+        "name": "Test",
+        "size": [8, 4],
+        "tools": [1, 2],
+        "tiles": [
+            { "type": "generator", "pos": [0, 0], "values": ["1", "#ff0000"] },
+            { "type": "goal", "pos": [7, 0], "target": "2", "quantity": 5, "min_rate": 0.5 }
+        ],
+        "scoring": { "max_tiles": 6, "min_throughput": 0.5 }
+    }"##;
+
+    #[test]
+    fn parse_level() {
+        let level = Level::parse("test", LEVEL).unwrap();
+
+        assert_eq!(level.id, "test");
+        assert!(level.allows_tool(2));
+        assert!(!level.allows_tool(3));
+        assert!(matches!(
+            &level.tiles[0],
+            LevelTile::Generator { values, seconds_between: 1.0, .. }
+                if values == &[Value::Int(1), Value::Colour([255, 0, 0])]
+        ));
+        assert_eq!(level.tiles[1].tile_pos(), TilePos { x: 7, y: 0 });
+
+        assert!(Level::parse("bad", &LEVEL.replace("\"2\"", "\"much too long\"")).is_err());
+    }
+
+    #[test]
+    fn campaign_levels_parse() {
+        let levels = Levels::load(asset_path(LEVELS_DIR));
+        let files = fs::read_dir(asset_path(LEVELS_DIR)).unwrap().count();
+
+        assert!(files > 0);
+        assert_eq!(levels.iter().count(), files);
+    }
+
+    #[test]
+    fn best_score() {
+        let level = Level::parse("test", LEVEL).unwrap();
+        let mut best_scores = BestScores::default();
+
+        let slow = Score {
+            tiles: 5,
+            throughput: 0.2,
+        };
+        let fast = Score {
+            tiles: 7,
+            throughput: 1.0,
+        };
+        let fast_and_small = Score {
+            tiles: 6,
+            throughput: 0.8,
+        };
+
+        assert_eq!(level.scoring.stars(&slow), 2);
+        assert_eq!(level.scoring.stars(&fast_and_small), 3);
+
+        assert!(best_scores.record(&level, slow));
+        // Same stars but more tiles
+        assert!(!best_scores.record(&level, fast));
+        assert!(best_scores.record(&level, fast_and_small));
+        assert_eq!(best_scores.get(&level), Some(&fast_and_small));
+    }
+}
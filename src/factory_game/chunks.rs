@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+/// Width and height, in tiles, of each chunk of a layer
+pub const CHUNK_SIZE: u32 = 32;
+
+/// Size of the sandbox map.  Tiles are only stored and drawn where there's
+/// something, so this can be far bigger than anyone will build.
+pub const UNBOUNDED_MAP_SIZE: TilemapSize = TilemapSize {
+    x: 1 << 16,
+    y: 1 << 16,
+};
+
+/// Like [`TileStorage`], but split into chunks that are only created when
+/// something is placed in them, so that the map can be huge.
+#[derive(Component, Debug, Default)]
+pub struct ChunkedTileStorage {
+    chunks: HashMap<UVec2, Box<[Option<Entity>]>>,
+}
+
+impl ChunkedTileStorage {
+    fn chunk_and_index(tile_pos: &TilePos) -> (UVec2, usize) {
+        let pos = UVec2::new(tile_pos.x, tile_pos.y);
+        let within = pos % CHUNK_SIZE;
+        (
+            pos / CHUNK_SIZE,
+            (within.y * CHUNK_SIZE + within.x) as usize,
+        )
+    }
+
+    pub fn get(&self, tile_pos: &TilePos) -> Option<Entity> {
+        let (chunk, index) = Self::chunk_and_index(tile_pos);
+        self.chunks.get(&chunk)?[index]
+    }
+
+    pub fn set(&mut self, tile_pos: &TilePos, entity: Entity) {
+        let (chunk, index) = Self::chunk_and_index(tile_pos);
+        self.chunks
+            .entry(chunk)
+            .or_insert_with(|| vec![None; (CHUNK_SIZE * CHUNK_SIZE) as usize].into())[index] =
+            Some(entity);
+    }
+
+    /// Removes and returns the entity at `tile_pos`.  Chunks are dropped
+    /// once they're empty.
+    pub fn remove(&mut self, tile_pos: &TilePos) -> Option<Entity> {
+        let (chunk, index) = Self::chunk_and_index(tile_pos);
+        let tiles = self.chunks.get_mut(&chunk)?;
+        let entity = tiles[index].take();
+        if tiles.iter().all(Option::is_none) {
+            self.chunks.remove(&chunk);
+        }
+        entity
+    }
+
+    /// Positions of the chunks that have been created, in chunks rather
+    /// than tiles
+    pub fn chunks(&self) -> impl Iterator<Item = UVec2> {
+        self.chunks.keys().copied()
+    }
+}
+
+#[cfg(test)]
+mod chunks_test {
+    use bevy::ecs::world::World;
+    use bevy_ecs_tilemap::helpers::square_grid::neighbors::{Neighbors, SquareDirection};
+
+    use super::*;
+
+    #[test]
+    fn chunks_are_made_and_dropped_as_needed() {
+        let mut world = World::new();
+        let (a, b) = (world.spawn_empty().id(), world.spawn_empty().id());
+        let mut storage = ChunkedTileStorage::default();
+        assert_eq!(storage.chunks().count(), 0);
+
+        storage.set(&TilePos { x: 5, y: 5 }, a);
+        storage.set(&TilePos { x: 40_000, y: 5 }, b);
+        assert_eq!(storage.chunks().count(), 2);
+        assert_eq!(storage.get(&TilePos { x: 5, y: 5 }), Some(a));
+        assert_eq!(storage.get(&TilePos { x: 40_000, y: 5 }), Some(b));
+        assert_eq!(storage.get(&TilePos { x: 6, y: 5 }), None);
+
+        assert_eq!(storage.remove(&TilePos { x: 5, y: 5 }), Some(a));
+        assert_eq!(storage.remove(&TilePos { x: 5, y: 5 }), None);
+        assert_eq!(storage.chunks().count(), 1);
+    }
+
+    #[test]
+    fn neighbors_cross_chunk_boundaries() {
+        let mut world = World::new();
+        let mut storage = ChunkedTileStorage::default();
+        let pos = TilePos {
+            x: CHUNK_SIZE - 1,
+            y: CHUNK_SIZE,
+        };
+        let (east, south) = (world.spawn_empty().id(), world.spawn_empty().id());
+        storage.set(
+            &TilePos {
+                x: CHUNK_SIZE,
+                ..pos
+            },
+            east,
+        );
+        storage.set(
+            &pos.square_offset(&SquareDirection::South, &UNBOUNDED_MAP_SIZE)
+                .unwrap(),
+            south,
+        );
+
+        let neighbors =
+            Neighbors::get_square_neighboring_positions(&pos, &UNBOUNDED_MAP_SIZE, false)
+                .and_then_ref(|pos| storage.get(pos));
+        assert_eq!(neighbors.east, Some(east));
+        assert_eq!(neighbors.south, Some(south));
+        assert_eq!(neighbors.north, None);
+        assert_eq!(storage.chunks().count(), 2);
+    }
+}
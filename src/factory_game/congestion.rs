@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::factory_game::payload_handler::PayloadHandler;
+
+/// Seconds over which a tile's heat follows its congestion, so it reflects
+/// what the tile has been doing recently
+pub const DECAY_WINDOW: f32 = 5.0;
+
+/// How congested a tile's payload handler has been.  Every handler has one.
+#[derive(Component, Debug, Default, Reflect)]
+pub struct Congestion {
+    /// The payload at the end of one of the tile's lines waiting to be
+    /// transferred, if any
+    stalled_payload: Option<Entity>,
+    /// Seconds `stalled_payload` has been waiting
+    stalled_for: f32,
+    /// How full the tile's lines are, from 0 to 1
+    fill: f32,
+    /// Recent congestion, from 0 to 1
+    heat: f32,
+}
+
+impl Congestion {
+    pub fn stalled_for(&self) -> f32 {
+        self.stalled_for
+    }
+
+    pub fn fill(&self) -> f32 {
+        self.fill
+    }
+
+    pub fn heat(&self) -> f32 {
+        self.heat
+    }
+
+    /// A tile is hottest when its lines are full and its front payload has
+    /// been stuck for the whole decay window.  Its heat moves towards that
+    /// exponentially, so older behaviour fades out.
+    fn update(&mut self, stalled_payload: Option<Entity>, fill: f32, t: f32) {
+        if stalled_payload.is_some() && stalled_payload == self.stalled_payload {
+            self.stalled_for += t;
+        } else {
+            self.stalled_for = 0.0;
+        }
+        self.stalled_payload = stalled_payload;
+        self.fill = fill;
+
+        let congestion = ((self.stalled_for / DECAY_WINDOW).min(1.0) + fill) / 2.0;
+        let decay = 1.0 - (-t / DECAY_WINDOW).exp();
+        self.heat += (congestion - self.heat) * decay;
+    }
+}
+
+pub fn track_congestion<T: PayloadHandler>(
+    handlers: Query<(&T, &mut Congestion)>,
+    time: Res<Time>,
+) {
+    let t = time.delta_secs();
+
+    for (handler, mut congestion) in handlers {
+        let (mut lines, mut fill) = (0, 0.0);
+        let mut stalled_payload = None;
+        for line in handler.transport_lines() {
+            lines += 1;
+            fill += line.fill_ratio();
+            stalled_payload = stalled_payload.or(line.get_payload_to_transfer());
+        }
+
+        let fill = if lines == 0 { 0.0 } else { fill / lines as f32 };
+        congestion.update(stalled_payload, fill, t);
+    }
+}
+
+#[cfg(test)]
+mod congestion_test {
+    use super::*;
+
+    #[test]
+    fn stall_time_restarts_with_each_payload() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut congestion = Congestion::default();
+
+        congestion.update(Some(a), 0.5, 1.0);
+        assert_eq!(congestion.stalled_for(), 0.0);
+        congestion.update(Some(a), 0.5, 1.0);
+        congestion.update(Some(a), 0.5, 1.0);
+        assert_eq!(congestion.stalled_for(), 2.0);
+
+        congestion.update(Some(b), 0.5, 1.0);
+        assert_eq!(congestion.stalled_for(), 0.0);
+        congestion.update(None, 0.0, 1.0);
+        assert_eq!(congestion.stalled_for(), 0.0);
+    }
+
+    #[test]
+    fn heat_decays_once_congestion_clears() {
+        let payload = Entity::from_raw(1);
+        let mut congestion = Congestion::default();
+
+        for _ in 0..100 {
+            congestion.update(Some(payload), 1.0, 0.5);
+        }
+        assert!(congestion.heat() > 0.95);
+
+        congestion.update(None, 0.0, DECAY_WINDOW);
+        assert!((congestion.heat() - (-1.0f32).exp()).abs() < 0.05);
+        for _ in 0..10 {
+            congestion.update(None, 0.0, DECAY_WINDOW);
+        }
+        assert!(congestion.heat() < 0.01);
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::input::egui_wants_any_input;
+
+use crate::{
+    GameState,
+    factory_game::{BaseLayer, MapConfig, chunks::ChunkedTileStorage, congestion::Congestion},
+    sprite_sheet::{GameSprite, SpriteSheet},
+};
+
+pub fn congestion_overlay_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            toggle_congestion_overlay
+                .run_if(input_just_pressed(KeyCode::KeyH))
+                .run_if(not(egui_wants_any_input)),
+            update_congestion_overlay.run_if(congestion_overlay_visible),
+        )
+            .run_if(in_state(GameState::FactoryGame)),
+    );
+}
+
+/// Tints each tile from green to red by how congested it has been recently.
+/// Sits above the base layer, under the payloads, and is hidden until
+/// toggled.
+#[derive(Component)]
+pub struct CongestionLayer;
+
+/// A tile on the congestion layer showing the heat of a base layer tile
+#[derive(Component)]
+struct HeatTile(Entity);
+
+pub fn make_congestion_layer(
+    mut commands: Commands,
+    sprite_sheet: Res<SpriteSheet>,
+    config: Res<MapConfig>,
+) {
+    commands
+        .spawn((
+            CongestionLayer,
+            ChunkedTileStorage::default(),
+            super::make_layer(&config, sprite_sheet.image(), 0.5, "CongestionLayer"),
+        ))
+        .insert(Visibility::Hidden);
+}
+
+fn congestion_overlay_visible(layer: Single<&Visibility, With<CongestionLayer>>) -> bool {
+    **layer != Visibility::Hidden
+}
+
+fn toggle_congestion_overlay(mut layer: Single<&mut Visibility, With<CongestionLayer>>) {
+    layer.toggle_visible_hidden();
+}
+
+fn update_congestion_overlay(
+    mut commands: Commands,
+    layer: Single<(Entity, &mut ChunkedTileStorage), With<CongestionLayer>>,
+    tiles: Query<(Entity, &TilePos, &Congestion), With<BaseLayer>>,
+    mut heat_tiles: Query<(Entity, &TilePos, &HeatTile, &mut TileColor)>,
+) {
+    let (layer_entity, mut storage) = layer.into_inner();
+
+    // Heat tiles are dropped when what they show is cleared away or replaced
+    for (entity, tile_pos, heat_tile, mut color) in &mut heat_tiles {
+        match tiles.get(heat_tile.0) {
+            Ok((_, _, congestion)) => {
+                // Only touch the colour when it changes, so the tilemap isn't
+                // told to redraw every heat tile every frame
+                let heat_color = heat_color(congestion.heat());
+                if color.0 != heat_color {
+                    color.0 = heat_color;
+                }
+            }
+            Err(_) => {
+                storage.remove(tile_pos);
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+
+    for (entity, tile_pos, congestion) in tiles {
+        if storage.get(tile_pos).is_some() {
+            continue;
+        }
+
+        let heat_tile = commands
+            .spawn((
+                StateScoped(GameState::FactoryGame),
+                Name::new("HeatTile"),
+                HeatTile(entity),
+                TileBundle {
+                    position: *tile_pos,
+                    texture_index: GameSprite::BlankSquare.tile_texture_index(),
+                    tilemap_id: TilemapId(layer_entity),
+                    color: heat_color(congestion.heat()).into(),
+                    ..default()
+                },
+            ))
+            .id();
+        storage.set(tile_pos, heat_tile);
+    }
+}
+
+fn heat_color(heat: f32) -> Color {
+    Color::hsla(120.0 * (1.0 - heat), 0.9, 0.5, 0.5)
+}
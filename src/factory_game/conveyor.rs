@@ -1,13 +1,18 @@
 use std::collections::HashSet;
 
 use bevy::prelude::*;
-use bevy_ecs_tilemap::{helpers::square_grid::neighbors::Neighbors, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
 
 use crate::{
     GameState,
     factory_game::{
         BaseLayer,
-        helpers::{ConveyorDirection, ConveyorDirections, get_neighbors_from_query},
+        chunks::ChunkedTileStorage,
+        footprint::Footprint,
+        helpers::{
+            ConveyorDirection, ConveyorDirections, get_neighbor_conveyors,
+            get_neighboring_positions,
+        },
     },
 };
 
@@ -74,12 +79,14 @@ impl Conveyor {
     pub fn get_available_destination(
         &self,
         starting_direction: ConveyorDirection,
-        tile_storage: &TileStorage,
+        tile_storage: &ChunkedTileStorage,
         tile_pos: &TilePos,
         map_size: &TilemapSize,
-        conveyors: &Query<&Conveyor>,
+        map_type: &TilemapType,
+        conveyors: &Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
     ) -> Option<ConveyorDirection> {
-        let neighbors = get_neighbors_from_query(tile_storage, tile_pos, map_size, conveyors);
+        let neighbors =
+            get_neighbor_conveyors(tile_storage, tile_pos, map_size, map_type, conveyors);
 
         self.outputs()
             .iter_from(starting_direction)
@@ -92,6 +99,18 @@ impl Conveyor {
     }
 }
 
+/// The `Conveyor` a neighbour of `cell` sees.  Machines covering several
+/// tiles only connect through the ports on that cell.
+pub fn cell_conveyor(
+    (conveyor, footprint, anchor): (&Conveyor, Option<&Footprint>, &TilePos),
+    cell: &TilePos,
+) -> Conveyor {
+    match footprint {
+        Some(footprint) => footprint.cell_conveyor(anchor, cell),
+        None => conveyor.clone(),
+    }
+}
+
 #[derive(Component, Debug, Reflect, Default)]
 pub struct SimpleConveyor;
 
@@ -103,16 +122,19 @@ pub struct ConveyorUpdated(pub TilePos);
 
 pub fn update_tiles_to_check(
     mut commands: Commands,
-    new: Query<&TilePos, Added<Conveyor>>,
+    new: Query<(&TilePos, Option<&Footprint>), Added<Conveyor>>,
     mut updated: EventReader<ConveyorUpdated>,
-    base: Single<&TilemapSize, With<BaseLayer>>,
+    base: Single<(&TilemapSize, &TilemapType), With<BaseLayer>>,
 ) {
-    let map_size = base.into_inner();
+    let (map_size, map_type) = base.into_inner();
 
     let mut to_check = HashSet::new();
 
-    new.iter().for_each(|pos| {
-        to_check.insert(*pos);
+    new.iter().for_each(|(pos, footprint)| match footprint {
+        Some(footprint) => to_check.extend(footprint.cells(pos)),
+        None => {
+            to_check.insert(*pos);
+        }
     });
 
     updated.read().for_each(|entity| {
@@ -121,9 +143,7 @@ pub fn update_tiles_to_check(
 
     let sources: Vec<_> = to_check.iter().cloned().collect();
     for pos in sources {
-        for neighbor_pos in
-            Neighbors::get_square_neighboring_positions(&pos, map_size, false).iter()
-        {
+        for neighbor_pos in get_neighboring_positions(&pos, map_size, map_type).iter() {
             to_check.insert(*neighbor_pos);
         }
     }
@@ -7,13 +7,15 @@ use bevy_ecs_tilemap::{
 use crate::{
     factory_game::{
         BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
         conveyor::{Conveyor, SimpleConveyor, TilesToCheck},
+        footprint::Footprint,
         helpers::{
-            ConveyorDirection, ConveyorDirections, get_neighbors_from_query, make_east_relative,
-            opposite,
+            ConveyorDirection, ConveyorDirections, get_neighbor_conveyors, make_east_relative,
         },
         interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
         payloads::PayloadTransportLine,
+        power::PowerConsumer,
     },
     sprite_sheet::GameSprite,
 };
@@ -27,16 +29,33 @@ pub fn conveyor_belts_plugin(app: &mut App) {
         );
 }
 
-#[derive(Default)]
 pub struct ConveyorBeltTool(ConveyorDirection);
 
+impl Default for ConveyorBeltTool {
+    fn default() -> Self {
+        ConveyorBeltTool(ConveyorDirection::East)
+    }
+}
+
+/// Power a belt needs to run at full speed
+const POWER: u32 = 1;
+
 impl Tool for ConveyorBeltTool {
     fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
-        (GameSprite::Arrow, self.0.tile_flip())
+        let sprite = if self.0.is_diagonal() {
+            GameSprite::ArrowNorthEast
+        } else {
+            GameSprite::Arrow
+        };
+        (sprite, self.0.tile_flip())
     }
 
-    fn next_variant(&mut self) {
-        self.0 = self.0.next();
+    fn next_variant(&mut self, map_type: &TilemapType) {
+        self.0 = self.0.next(map_type);
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceConveyorBeltEvent(TilePos::default(), self.0).cost()
     }
 
     fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
@@ -52,13 +71,17 @@ impl PlaceTileEvent for PlaceConveyorBeltEvent {
         self.0
     }
 
+    fn cost(&self) -> u32 {
+        1
+    }
+
     fn configure_new_entity(&self, mut commands: EntityCommands) {
         commands.insert((conveyor_belt_bundle(self.1), Name::new("Conveyor Belt")));
     }
 }
 
 #[derive(Component)]
-#[require(SimpleConveyor)]
+#[require(SimpleConveyor, PowerConsumer::new(POWER))]
 pub struct ConveyorBelt;
 
 pub fn conveyor_belt_bundle(output: ConveyorDirection) -> impl Bundle {
@@ -71,11 +94,11 @@ pub fn conveyor_belt_bundle(output: ConveyorDirection) -> impl Bundle {
 
 fn update_conveyor_belt_conveyors(
     to_check: Res<TilesToCheck>,
-    mut conveyors: Query<&mut Conveyor>,
+    mut conveyors: Query<(&mut Conveyor, Option<&Footprint>, &TilePos)>,
     conveyor_belts: Query<(), With<ConveyorBelt>>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
 ) {
-    let (tile_storage, map_size) = base.into_inner();
+    let (tile_storage, map_size, map_type) = base.into_inner();
 
     for tile_pos in &to_check.0 {
         if let Some(entity) = tile_storage.get(tile_pos)
@@ -85,9 +108,10 @@ fn update_conveyor_belt_conveyors(
                 tile_pos,
                 tile_storage,
                 map_size,
+                map_type,
                 &conveyors.as_readonly(),
             );
-            if let Ok(mut conveyor) = conveyors.get_mut(entity) {
+            if let Ok((mut conveyor, ..)) = conveyors.get_mut(entity) {
                 conveyor.set_inputs(directions);
             }
         }
@@ -96,11 +120,13 @@ fn update_conveyor_belt_conveyors(
 
 pub fn find_incoming_directions(
     tile_pos: &TilePos,
-    tile_storage: &TileStorage,
+    tile_storage: &ChunkedTileStorage,
     map_size: &TilemapSize,
-    conveyors: &Query<&Conveyor>,
+    map_type: &TilemapType,
+    conveyors: &Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
 ) -> ConveyorDirections {
-    let neighbors = find_conveyors_outputting_to(tile_pos, tile_storage, map_size, conveyors);
+    let neighbors =
+        find_conveyors_outputting_to(tile_pos, tile_storage, map_size, map_type, conveyors);
     let directions = neighbors
         .iter_with_direction()
         .map(|(d, _)| ConveyorDirection::from(d));
@@ -108,21 +134,23 @@ pub fn find_incoming_directions(
     directions.into()
 }
 
-pub fn find_conveyors_outputting_to<'a>(
+pub fn find_conveyors_outputting_to(
     tile_pos: &TilePos,
-    tile_storage: &TileStorage,
+    tile_storage: &ChunkedTileStorage,
     map_size: &TilemapSize,
-    conveyors: &'a Query<&Conveyor>,
-) -> Neighbors<&'a Conveyor> {
+    map_type: &TilemapType,
+    conveyors: &Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
+) -> Neighbors<Conveyor> {
     // Find the neighbors that have conveyors on them
-    let neighbor_conveyors = get_neighbors_from_query(tile_storage, tile_pos, map_size, conveyors);
+    let neighbor_conveyors =
+        get_neighbor_conveyors(tile_storage, tile_pos, map_size, map_type, conveyors);
 
     // And just the conveyors pointing towards this one
 
     Neighbors::from_directional_closure(|dir| {
         neighbor_conveyors.get(dir).and_then(|c| {
-            if c.outputs().is_set(opposite(dir).into()) {
-                Some(*c)
+            if c.outputs().is_set(ConveyorDirection::from(dir).opposite()) {
+                Some(c.clone())
             } else {
                 None
             }
@@ -134,14 +162,14 @@ pub fn find_conveyors_outputting_to<'a>(
 fn update_conveyor_belt_tiles(
     to_check: Res<TilesToCheck>,
     mut commands: Commands,
-    conveyors: Query<&Conveyor>,
+    conveyors: Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
     conveyor_belts: Query<
         (&Conveyor, Option<&TileTextureIndex>, Option<&TileFlip>),
         With<ConveyorBelt>,
     >,
-    base: Single<(Entity, &TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(Entity, &ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
 ) {
-    let (tilemap_entity, tile_storage, map_size) = base.into_inner();
+    let (tilemap_entity, tile_storage, map_size, map_type) = base.into_inner();
 
     for pos in &to_check.0 {
         if let Some(entity) = tile_storage.get(pos)
@@ -152,15 +180,19 @@ fn update_conveyor_belt_tiles(
                 ..default()
             });
 
-            update_conveyor_belt_tile(
-                commands.reborrow(),
-                entity,
-                conveyor_belt,
-                pos,
-                tile_storage,
-                map_size,
-                &conveyors,
-            );
+            if let TilemapType::Hexagon(_) = map_type {
+                update_hex_conveyor_belt_tile(commands.reborrow(), entity, conveyor_belt);
+            } else {
+                update_conveyor_belt_tile(
+                    commands.reborrow(),
+                    entity,
+                    conveyor_belt,
+                    pos,
+                    tile_storage,
+                    map_size,
+                    &conveyors,
+                );
+            }
         }
     }
 }
@@ -170,16 +202,21 @@ fn update_conveyor_belt_tile(
     entity: Entity,
     conveyor_belt: (&Conveyor, Option<&TileTextureIndex>, Option<&TileFlip>),
     tile_pos: &TilePos,
-    tile_storage: &TileStorage,
+    tile_storage: &ChunkedTileStorage,
     map_size: &TilemapSize,
-    conveyors: &Query<&Conveyor>,
+    conveyors: &Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
 ) {
     let (conveyor, texture_index, flip) = conveyor_belt;
 
     let out_dir: SquareDirection = conveyor.output().into();
 
-    let neighbor_conveyors =
-        find_conveyors_outputting_to(tile_pos, tile_storage, map_size, conveyors);
+    let neighbor_conveyors = find_conveyors_outputting_to(
+        tile_pos,
+        tile_storage,
+        map_size,
+        &TilemapType::Square,
+        conveyors,
+    );
 
     // Rotate all of this so that east is always the "out" direction
     let neighbor_conveyors = make_east_relative(neighbor_conveyors, out_dir);
@@ -245,7 +282,6 @@ fn update_conveyor_belt_tile(
             north: None,
             east: _,
             south: None,
-            west: _,
             ..
         } => (GameSprite::ConveyorInWOutE, false),
     };
@@ -283,3 +319,25 @@ fn update_conveyor_belt_tile(
         commands.entity(entity).insert((new_index, new_flip));
     }
 }
+
+/// There aren't sprites for curves and junctions on hex maps, so belts there
+/// are always drawn straight.
+fn update_hex_conveyor_belt_tile(
+    mut commands: Commands,
+    entity: Entity,
+    conveyor_belt: (&Conveyor, Option<&TileTextureIndex>, Option<&TileFlip>),
+) {
+    let (conveyor, texture_index, flip) = conveyor_belt;
+    let output = conveyor.output();
+
+    let new_sprite = if output.is_diagonal() {
+        GameSprite::ConveyorInSWOutNE
+    } else {
+        GameSprite::ConveyorInWOutE
+    };
+    let new_index = new_sprite.tile_texture_index();
+    let new_flip = output.tile_flip();
+    if Some(&new_index) != texture_index || Some(&new_flip) != flip {
+        commands.entity(entity).insert((new_index, new_flip));
+    }
+}
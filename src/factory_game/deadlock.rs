@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer, ConveyorSystems, analysis::ConveyorAnalysis, chunks::ChunkedTileStorage,
+        congestion::Congestion, conveyor::Conveyor, helpers::conveyor_directions,
+        operators::OperatorTile, sink::Sink,
+    },
+};
+
+pub fn deadlock_plugin(app: &mut App) {
+    app.init_resource::<Deadlocks>()
+        .add_systems(
+            Update,
+            detect_deadlocks
+                .after(ConveyorSystems::PayloadTransforms)
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), clear_deadlocks);
+}
+
+/// Seconds a tile has to be stuck before it's looked at as part of a deadlock
+pub const STALL_THRESHOLD: f32 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlockKind {
+    /// A loop of tiles so full that none of them can pass a payload on
+    Cycle,
+    /// An operator holding one operand while the other never arrives
+    StarvedInput,
+    /// A tile with nowhere to send its payloads, or a jammed sink
+    Blocked,
+}
+
+/// Tiles that can't move again without the player stepping in, and why
+#[derive(Debug, Clone, PartialEq)]
+pub struct Deadlock {
+    pub kind: DeadlockKind,
+    /// Where the explanation belongs: the starved operator, the blocked
+    /// tile, or the first tile of the loop
+    pub cause: TilePos,
+    /// Every tile held up, from the cause back up the chains leading to it
+    pub tiles: Vec<TilePos>,
+    pub explanation: String,
+}
+
+#[derive(Resource, Debug, Default, PartialEq)]
+pub struct Deadlocks(Vec<Deadlock>);
+
+impl Deadlocks {
+    pub fn iter(&self) -> impl Iterator<Item = &Deadlock> {
+        self.0.iter()
+    }
+}
+
+/// Where following a stalled tile downstream ends up
+#[derive(Debug, PartialEq)]
+enum Trace {
+    /// The chain runs back into itself at `chain[start]`
+    Cycle { chain: Vec<Entity>, start: usize },
+    /// The last tile of the chain is stuck for a reason of its own
+    EndsAt(Vec<Entity>),
+    /// The chain runs into a tile that's already been explained
+    Joins(Vec<Entity>, Entity),
+    /// Something downstream is still moving, so the chain is only slow
+    Moving,
+}
+
+/// Follows `start` downstream through stuck tiles until it finds what
+/// they're all waiting on
+fn trace(
+    start: Entity,
+    edges: &HashMap<Entity, Vec<Entity>>,
+    is_cause: impl Fn(Entity) -> bool,
+    is_stuck: impl Fn(Entity) -> bool,
+    explained: &HashMap<Entity, usize>,
+) -> Trace {
+    let mut chain = vec![start];
+    let mut in_chain = HashMap::from([(start, 0)]);
+    loop {
+        let current = *chain.last().unwrap();
+        if is_cause(current) {
+            return Trace::EndsAt(chain);
+        }
+        let next = edges.get(&current).map_or(&[][..], Vec::as_slice);
+        if next.is_empty() {
+            return Trace::EndsAt(chain);
+        }
+
+        if let Some(start) = next.iter().find_map(|entity| in_chain.get(entity)) {
+            return Trace::Cycle {
+                start: *start,
+                chain,
+            };
+        }
+        if let Some(entity) = next.iter().find(|entity| explained.contains_key(entity)) {
+            return Trace::Joins(chain, *entity);
+        }
+        let Some(&entity) = next.iter().find(|entity| is_stuck(**entity)) else {
+            return Trace::Moving;
+        };
+        in_chain.insert(entity, chain.len());
+        chain.push(entity);
+    }
+}
+
+fn detect_deadlocks(
+    mut deadlocks: ResMut<Deadlocks>,
+    analysis: Res<ConveyorAnalysis>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
+    congestion: Query<(Entity, &Congestion)>,
+    operators: Query<(&OperatorTile, &Conveyor)>,
+    sinks: Query<&Sink>,
+    tiles: Query<(&TilePos, Option<&Name>)>,
+) {
+    let starved = |entity: Entity| {
+        operators
+            .get(entity)
+            .is_ok_and(|(operator, _)| operator.waiting_for() > STALL_THRESHOLD)
+    };
+    let jammed = |entity: Entity| sinks.get(entity).is_ok_and(Sink::is_jammed);
+    let stalled = |entity: Entity| {
+        congestion
+            .get(entity)
+            .is_ok_and(|(_, congestion)| congestion.stalled_for() > STALL_THRESHOLD)
+    };
+
+    let mut candidates: Vec<Entity> = congestion
+        .iter()
+        .map(|(entity, _)| entity)
+        .filter(|entity| stalled(*entity) || starved(*entity))
+        .collect();
+    if candidates.is_empty() {
+        deadlocks.set_if_neq(Deadlocks::default());
+        return;
+    }
+
+    let position = |entity: &Entity| tiles.get(*entity).map_or(TilePos::new(0, 0), |t| *t.0);
+    let described = |entity: &Entity| {
+        let tile_pos = position(entity);
+        let name = tiles
+            .get(*entity)
+            .ok()
+            .and_then(|(_, name)| name)
+            .map_or("Tile", |name| name.as_str());
+        format!("{name} at {}, {}", tile_pos.x, tile_pos.y)
+    };
+    candidates.sort_by_key(|entity| {
+        let tile_pos = position(entity);
+        (tile_pos.x, tile_pos.y)
+    });
+
+    let (tile_storage, map_size, map_type) = base.into_inner();
+    // Kept up to date as tiles change, so it needn't be rebuilt every frame
+    let edges = analysis.edges();
+
+    // What feeds the side of a starved operator that isn't held
+    let missing_feeder = |operator: Entity, held_left: bool| {
+        let (_, conveyor) = operators.get(operator).ok()?;
+        let output = conveyor.output();
+        let operator_pos = position(&operator);
+        conveyor_directions(map_type)
+            .iter()
+            .filter(|side| match held_left {
+                true => side.is_right_of(output),
+                false => side.is_left_of(output),
+            })
+            .filter_map(|side| side.offset(&operator_pos, map_size))
+            .filter_map(|pos| tile_storage.get(&pos))
+            .find(|feeder| {
+                edges
+                    .get(feeder)
+                    .is_some_and(|next| next.contains(&operator))
+            })
+    };
+
+    let mut found: Vec<Deadlock> = Vec::new();
+    let mut explained: HashMap<Entity, usize> = HashMap::new();
+    let mut moving: HashSet<Entity> = HashSet::new();
+    for start in candidates {
+        if explained.contains_key(&start) || moving.contains(&start) {
+            continue;
+        }
+
+        let is_cause = |entity: Entity| starved(entity) || jammed(entity);
+        let is_stuck = |entity: Entity| {
+            !moving.contains(&entity) && (stalled(entity) || starved(entity) || jammed(entity))
+        };
+        let (chain, deadlock) = match trace(start, edges, is_cause, is_stuck, &explained) {
+            Trace::Moving => {
+                // Chains running into this tile later are only slow too
+                moving.insert(start);
+                continue;
+            }
+            Trace::Joins(chain, entity) => {
+                let index = explained[&entity];
+                (chain, index)
+            }
+            Trace::Cycle { chain, start } => {
+                let cause = chain[start..]
+                    .iter()
+                    .min_by_key(|entity| {
+                        let tile_pos = position(entity);
+                        (tile_pos.x, tile_pos.y)
+                    })
+                    .unwrap();
+                found.push(Deadlock {
+                    kind: DeadlockKind::Cycle,
+                    cause: position(cause),
+                    tiles: Vec::new(),
+                    explanation: format!(
+                        "The loop of {} tiles through {} is full, so none of them can move",
+                        chain.len() - start,
+                        described(cause)
+                    ),
+                });
+                (chain, found.len() - 1)
+            }
+            Trace::EndsAt(chain) => {
+                let cause = chain.last().unwrap();
+                let (kind, explanation) = if let Ok((operator, _)) = operators.get(*cause)
+                    && starved(*cause)
+                {
+                    let [left, _] = operator.operands();
+                    let (held, missing) = match left {
+                        Some(_) => ("left", "right"),
+                        None => ("right", "left"),
+                    };
+                    let explanation = match missing_feeder(*cause, left.is_some()) {
+                        Some(feeder) => format!(
+                            "{} holds its {held} operand, but the {} feeding its {missing} \
+                             side hasn't delivered anything for over {STALL_THRESHOLD:.0}s",
+                            described(cause),
+                            described(&feeder)
+                        ),
+                        None => format!(
+                            "{} holds its {held} operand, but nothing feeds its {missing} side",
+                            described(cause)
+                        ),
+                    };
+                    (DeadlockKind::StarvedInput, explanation)
+                } else if jammed(*cause) {
+                    (
+                        DeadlockKind::Blocked,
+                        format!(
+                            "{} is jammed by an error payload, so everything behind it is stuck",
+                            described(cause)
+                        ),
+                    )
+                } else {
+                    (
+                        DeadlockKind::Blocked,
+                        format!("{} has nowhere to send its payloads", described(cause)),
+                    )
+                };
+                found.push(Deadlock {
+                    kind,
+                    cause: position(cause),
+                    tiles: Vec::new(),
+                    explanation,
+                });
+                (chain, found.len() - 1)
+            }
+        };
+
+        // Listed from the cause back, so chains that join later follow on
+        for entity in chain.into_iter().rev() {
+            explained.insert(entity, deadlock);
+            found[deadlock].tiles.push(position(&entity));
+        }
+    }
+
+    deadlocks.set_if_neq(Deadlocks(found));
+}
+
+fn clear_deadlocks(mut deadlocks: ResMut<Deadlocks>) {
+    *deadlocks = Deadlocks::default();
+}
+
+#[cfg(test)]
+mod deadlock_test {
+    use super::*;
+
+    fn edges(pairs: &[(u32, u32)]) -> HashMap<Entity, Vec<Entity>> {
+        let mut edges: HashMap<Entity, Vec<Entity>> = HashMap::new();
+        for (from, to) in pairs {
+            edges
+                .entry(Entity::from_raw(*from))
+                .or_default()
+                .push(Entity::from_raw(*to));
+            edges.entry(Entity::from_raw(*to)).or_default();
+        }
+        edges
+    }
+
+    fn entities(ids: &[u32]) -> Vec<Entity> {
+        ids.iter().copied().map(Entity::from_raw).collect()
+    }
+
+    #[test]
+    fn traces_stuck_chains_to_what_holds_them_up() {
+        // 1 feeds the loop 2 -> 3 -> 4 -> 2; 5 -> 6 -> 7 ends at a starved
+        // operator, 7; 8 -> 9 runs into something still moving, 10
+        let edges = edges(&[
+            (1, 2),
+            (2, 3),
+            (3, 4),
+            (4, 2),
+            (5, 6),
+            (6, 7),
+            (8, 9),
+            (9, 10),
+        ]);
+        let entity = Entity::from_raw;
+        let is_cause = |e: Entity| e == entity(7);
+        let is_stuck = |e: Entity| e != entity(10);
+        let mut explained = HashMap::new();
+
+        assert_eq!(
+            trace(entity(1), &edges, is_cause, is_stuck, &explained),
+            Trace::Cycle {
+                chain: entities(&[1, 2, 3, 4]),
+                start: 1
+            }
+        );
+        assert_eq!(
+            trace(entity(5), &edges, is_cause, is_stuck, &explained),
+            Trace::EndsAt(entities(&[5, 6, 7]))
+        );
+        assert_eq!(
+            trace(entity(8), &edges, is_cause, is_stuck, &explained),
+            Trace::Moving
+        );
+
+        explained.insert(entity(6), 0);
+        assert_eq!(
+            trace(entity(5), &edges, is_cause, is_stuck, &explained),
+            Trace::Joins(entities(&[5]), entity(6))
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use bevy::{prelude::*, sprite::Anchor, text::TextBounds};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        deadlock::{DeadlockKind, Deadlocks},
+    },
+    helpers::TilemapQuery,
+};
+
+pub fn deadlock_overlay_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        (
+            update_deadlock_labels.run_if(resource_changed::<Deadlocks>),
+            outline_deadlocked_tiles,
+        )
+            .run_if(in_state(GameState::FactoryGame)),
+    );
+}
+
+/// Explains a deadlock next to the tile that causes it
+#[derive(Component)]
+struct DeadlockLabel;
+
+fn deadlock_color(kind: DeadlockKind) -> Color {
+    match kind {
+        DeadlockKind::Cycle => Color::srgb(0.9, 0.3, 0.9),
+        DeadlockKind::StarvedInput => Color::srgb(0.3, 0.7, 1.0),
+        DeadlockKind::Blocked => Color::srgb(1.0, 0.3, 0.2),
+    }
+}
+
+fn update_deadlock_labels(
+    mut commands: Commands,
+    deadlocks: Res<Deadlocks>,
+    labels: Query<Entity, With<DeadlockLabel>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for label in labels {
+        commands.entity(label).despawn();
+    }
+
+    for deadlock in deadlocks.iter() {
+        let tile_center = base.center_in_world(&deadlock.cause);
+        commands.spawn((
+            Name::new("Deadlock Label"),
+            DeadlockLabel,
+            Text2d::new(&deadlock.explanation),
+            TextFont::from_font_size(10.0),
+            TextColor(deadlock_color(deadlock.kind)),
+            TextLayout::new_with_justify(JustifyText::Center),
+            TextBounds::from(Vec2::new(base.tile_size.x * 6.0, f32::INFINITY)),
+            Anchor::BottomCenter,
+            Transform::from_translation(
+                (tile_center + Vec2::new(0.0, base.tile_size.y / 2.0)).extend(5.0),
+            ),
+            StateScoped(GameState::FactoryGame),
+        ));
+    }
+}
+
+/// Outlines every tile held up by a deadlock, in a colour for each kind
+fn outline_deadlocked_tiles(
+    mut gizmos: Gizmos,
+    deadlocks: Res<Deadlocks>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    let size = Vec2::new(base.grid_size.x, base.grid_size.y) * 0.9;
+    for deadlock in deadlocks.iter() {
+        for tile_pos in &deadlock.tiles {
+            gizmos.rect_2d(
+                Isometry2d::from_translation(base.center_in_world(tile_pos)),
+                size,
+                deadlock_color(deadlock.kind),
+            );
+        }
+    }
+}
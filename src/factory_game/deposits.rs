@@ -0,0 +1,232 @@
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite::future},
+};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer, ConveyorSystems, MapConfig,
+        generator::{Generator, Sequence},
+        value::Value,
+    },
+    terrain::mapgen,
+};
+
+pub fn deposits_plugin(app: &mut App) {
+    app.register_type::<Deposit>().add_systems(
+        Update,
+        (
+            finish_deposit_layer.in_set(ConveyorSystems::TileGenerator),
+            extract_deposits.in_set(ConveyorSystems::TileUpdater),
+        ),
+    );
+}
+
+const TERRAIN_TEXTURE: &str = "kentangpixel/SummerFloor.png";
+
+/// Terrain is only generated for this much of the map, around its middle.
+/// The sandbox goes on far beyond it, but generators need a deposit, so they
+/// can only be placed in here.  Terrain isn't generated for chunks further
+/// out as they're reached.
+const DEPOSIT_AREA: TilemapSize = TilemapSize { x: 100, y: 100 };
+
+/// Resources under the base layer.  Generators can only be placed on them,
+/// and emit whatever they hold.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Deposit {
+    /// Random digits
+    Sand,
+    /// Ones
+    Water,
+}
+
+impl Deposit {
+    /// The deposit under terrain with the given corner colours, if any of
+    /// them isn't grass
+    fn from_colors(colors: &[&str]) -> Option<Deposit> {
+        colors.iter().find_map(|color| match *color {
+            "Sand" => Some(Deposit::Sand),
+            "Water" => Some(Deposit::Water),
+            _ => None,
+        })
+    }
+
+    pub fn sequence(&self) -> Sequence {
+        match self {
+            Deposit::Sand => Sequence::Random { min: 0, max: 9 },
+            Deposit::Water => Sequence::Constant(Value::Int(1)),
+        }
+    }
+}
+
+/// A generator placed on a deposit, which emits what the deposit holds
+#[derive(Component, Debug)]
+pub struct Extracting(pub Deposit);
+
+/// The terrain under the base layer, where the deposits are.  Only the
+/// sandbox has one.
+#[derive(Component)]
+pub struct DepositLayer {
+    /// Where this layer's first tile is on the base layer
+    pub origin: TilePos,
+}
+
+/// Terrain generation is slow, so it happens in the background
+#[derive(Component)]
+struct GeneratingTerrain(Task<mapgen::Generator>);
+
+pub fn make_deposit_layer(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<MapConfig>,
+) {
+    if !config.deposits {
+        return;
+    }
+
+    let size = TilemapSize {
+        x: DEPOSIT_AREA.x.min(config.size.x),
+        y: DEPOSIT_AREA.y.min(config.size.y),
+    };
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        let mut generator = mapgen::Generator::new(&size);
+        generator.generate();
+        generator
+    });
+
+    // Line the layer's tiles up with the base layer's
+    let origin = TilePos {
+        x: (config.size.x - size.x) / 2,
+        y: (config.size.y - size.y) / 2,
+    };
+    let center_in_world = |tile_pos: &TilePos, map_size| {
+        tile_pos.center_in_world(
+            map_size,
+            &config.grid_size,
+            &config.tile_size,
+            &config.map_type,
+            &TilemapAnchor::Center,
+        )
+    };
+    let offset =
+        center_in_world(&origin, &config.size) - center_in_world(&TilePos { x: 0, y: 0 }, &size);
+
+    let layer_config = MapConfig { size, ..default() };
+    commands
+        .spawn((
+            DepositLayer { origin },
+            GeneratingTerrain(task),
+            super::make_layer(
+                &layer_config,
+                asset_server.load(TERRAIN_TEXTURE),
+                -1.0,
+                "DepositLayer",
+            ),
+        ))
+        .insert((
+            Transform::from_translation(offset.extend(-1.0)),
+            TileStorage::empty(size),
+        ));
+}
+
+fn finish_deposit_layer(
+    mut commands: Commands,
+    layers: Query<(
+        Entity,
+        &mut GeneratingTerrain,
+        &mut TileStorage,
+        &TilemapSize,
+    )>,
+) {
+    for (layer, mut generating, mut storage, size) in layers {
+        let Some(generator) = block_on(future::poll_once(&mut generating.0)) else {
+            continue;
+        };
+
+        for tile in generator.get() {
+            let mapgen::TileState::Collapsed(index) = tile.state else {
+                continue;
+            };
+            if tile.pos.x >= size.x || tile.pos.y >= size.y {
+                continue;
+            }
+
+            let mut entity = commands.spawn((
+                StateScoped(GameState::FactoryGame),
+                TileBundle {
+                    position: tile.pos,
+                    tilemap_id: TilemapId(layer),
+                    texture_index: TileTextureIndex(index),
+                    ..default()
+                },
+            ));
+            if let Some(deposit) = generator
+                .corner_colors(&tile.pos)
+                .and_then(|colors| Deposit::from_colors(&colors))
+            {
+                entity.insert(deposit);
+            }
+            storage.set(&tile.pos, entity.id());
+        }
+
+        commands.entity(layer).remove::<GeneratingTerrain>();
+    }
+}
+
+#[derive(SystemParam)]
+pub struct Deposits<'w, 's> {
+    layer: Query<'w, 's, (&'static DepositLayer, &'static TileStorage), Without<BaseLayer>>,
+    deposits: Query<'w, 's, &'static Deposit>,
+}
+
+impl Deposits<'_, '_> {
+    pub fn get(&self, tile_pos: &TilePos) -> Option<Deposit> {
+        let (layer, storage) = self.layer.single().ok()?;
+        let pos = TilePos {
+            x: tile_pos.x.checked_sub(layer.origin.x)?,
+            y: tile_pos.y.checked_sub(layer.origin.y)?,
+        };
+        let entity = storage.checked_get(&pos)?;
+        self.deposits.get(entity).ok().copied()
+    }
+
+    /// Whether something that needs a deposit can go at `tile_pos`.  Without
+    /// a deposit layer, as in levels, it can go anywhere.
+    pub fn allows(&self, tile_pos: &TilePos) -> bool {
+        self.layer.is_empty() || self.get(tile_pos).is_some()
+    }
+}
+
+fn extract_deposits(
+    mut commands: Commands,
+    mut new_generators: Query<(Entity, &TilePos, &mut Generator), Added<Generator>>,
+    deposits: Deposits,
+) {
+    for (entity, tile_pos, mut generator) in &mut new_generators {
+        if let Some(deposit) = deposits.get(tile_pos) {
+            generator.sequence = deposit.sequence();
+            commands.entity(entity).insert(Extracting(deposit));
+        }
+    }
+}
+
+#[cfg(test)]
+mod deposit_test {
+    use super::*;
+
+    #[test]
+    fn deposits_come_from_any_corner() {
+        assert_eq!(Deposit::from_colors(&["Grass"; 4]), None);
+        assert_eq!(
+            Deposit::from_colors(&["Grass", "Grass", "Water", "Grass"]),
+            Some(Deposit::Water)
+        );
+        assert_eq!(
+            Deposit::from_colors(&["Sand", "Sand", "Sand", "Sand"]),
+            Some(Deposit::Sand)
+        );
+    }
+}
@@ -8,7 +8,13 @@ use bevy_egui::input::{egui_wants_any_keyboard_input, egui_wants_any_pointer_inp
 use crate::{
     GameState,
     factory_game::{
-        BaseLayer, ConveyorDirection, conveyor::Conveyor, conveyor_belts::conveyor_belt_bundle,
+        BaseLayer,
+        chunks::{CHUNK_SIZE, ChunkedTileStorage},
+        conveyor::Conveyor,
+        conveyor_belts::conveyor_belt_bundle,
+        goals::{Goal, PlaceGoalSinkEvent},
+        interaction::HoveredTile,
+        value::Value,
     },
     helpers::TilemapQuery,
     sprite_sheet::{GameSprite, SpriteSheet},
@@ -17,24 +23,35 @@ use crate::{
 pub fn dev_plugin(app: &mut App) {
     app.add_systems(
         Update,
-        ((
-            on_toggle_show_conveyors.run_if(input_just_pressed(KeyCode::Tab)),
-            on_test_data.run_if(input_just_pressed(KeyCode::KeyT)),
+        (
+            (
+                on_toggle_show_conveyors.run_if(input_just_pressed(KeyCode::Tab)),
+                on_test_data.run_if(input_just_pressed(KeyCode::KeyT)),
+                on_test_goal.run_if(input_just_pressed(KeyCode::KeyG)),
+                on_toggle_show_chunks.run_if(input_just_pressed(KeyCode::KeyC)),
+            )
+                .run_if(not(egui_wants_any_keyboard_input))
+                .run_if(not(egui_wants_any_pointer_input)),
+            draw_chunks.run_if(|show: Res<ShowChunks>| show.0),
         )
-            .run_if(not(egui_wants_any_keyboard_input))
-            .run_if(not(egui_wants_any_pointer_input)),)
             .chain()
             .run_if(in_state(GameState::FactoryGame)),
-    );
+    )
+    .init_resource::<ShowChunks>();
 }
 
 fn on_test_data(
     mut commands: Commands,
-    base: Single<(&mut TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(&mut ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
 ) {
     let (mut storage, map_size) = base.into_inner();
 
-    let mut pos = SquarePos { x: 32, y: 58 };
+    // Laid out for a 100x100 map, so moved to the middle of bigger ones
+    let (left, bottom) = (map_size.x as i32 / 2 - 50, map_size.y as i32 / 2 - 50);
+    let mut pos = SquarePos {
+        x: left + 32,
+        y: bottom + 58,
+    };
 
     let mut spawn = |pos: SquarePos, direction| {
         let pos = pos.as_tile_pos(map_size).unwrap();
@@ -59,14 +76,22 @@ fn on_test_data(
 
             pos.x += 4;
 
-            if pos.x > 68 {
-                pos.x = 32;
+            if pos.x > left + 68 {
+                pos.x = left + 32;
                 pos.y -= 4;
             }
         }
     }
 }
 
+/// Put a goal sink wanting the default generator's output under the mouse
+fn on_test_goal(mut commands: Commands, hovered_tile: Single<&TilePos, With<HoveredTile>>) {
+    commands.trigger(PlaceGoalSinkEvent(
+        **hovered_tile,
+        Goal::new(Value::Int(1), 20, Some(0.5)),
+    ));
+}
+
 #[derive(Component)]
 struct DirectionArrow;
 
@@ -83,12 +108,7 @@ fn on_toggle_show_conveyors(
     if *enabled {
         for (conveyor, tile_pos) in conveyors {
             for output in conveyor.outputs().iter() {
-                let angle = match output {
-                    ConveyorDirection::North => std::f32::consts::FRAC_PI_2,
-                    ConveyorDirection::South => -std::f32::consts::FRAC_PI_2,
-                    ConveyorDirection::East => 0.0,
-                    ConveyorDirection::West => std::f32::consts::PI,
-                };
+                let angle = output.angle();
                 let tile_center = base.center_in_world(tile_pos);
 
                 commands.spawn((
@@ -107,3 +127,33 @@ fn on_toggle_show_conveyors(
             .for_each(|arrow| commands.entity(arrow).despawn());
     }
 }
+
+/// Whether the outlines of the base layer's chunks are shown
+#[derive(Resource, Default)]
+struct ShowChunks(bool);
+
+fn on_toggle_show_chunks(mut show: ResMut<ShowChunks>) {
+    show.0 = !show.0;
+}
+
+fn draw_chunks(
+    mut gizmos: Gizmos,
+    base: Single<(&ChunkedTileStorage, TilemapQuery), With<BaseLayer>>,
+) {
+    let (storage, tilemap) = base.into_inner();
+    let grid_size = Vec2::new(tilemap.grid_size.x, tilemap.grid_size.y);
+    let chunk_size = grid_size * CHUNK_SIZE as f32;
+
+    for chunk in storage.chunks() {
+        let first_tile = TilePos {
+            x: chunk.x * CHUNK_SIZE,
+            y: chunk.y * CHUNK_SIZE,
+        };
+        let center = tilemap.center_in_world(&first_tile) - grid_size / 2.0 + chunk_size / 2.0;
+        gizmos.rect_2d(
+            Isometry2d::from_translation(center),
+            chunk_size,
+            Color::srgba(0.2, 0.6, 1.0, 0.6),
+        );
+    }
+}
@@ -5,11 +5,14 @@ use smallvec::SmallVec;
 use crate::{
     factory_game::{
         BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
         conveyor::Conveyor,
+        footprint::Footprint,
         helpers::{ConveyorDirection, ConveyorDirections},
         interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
         payload_handler::{AddPayloadHandler, PayloadHandler},
         payloads::{Payload, PayloadTransportLine, RequestPayloadTransferEvent},
+        signals::Disabled,
     },
     helpers::TilemapQuery,
     sprite_sheet::GameSprite,
@@ -40,8 +43,12 @@ impl Tool for DistributorTool {
         (GameSprite::ToolDistributor, self.0.tile_flip())
     }
 
-    fn next_variant(&mut self) {
-        self.0 = self.0.next();
+    fn next_variant(&mut self, map_type: &TilemapType) {
+        self.0 = self.0.next(map_type);
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceDistributorEvent(TilePos::default(), self.0).cost()
     }
 
     fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
@@ -57,15 +64,19 @@ impl PlaceTileEvent for PlaceDistributorEvent {
         self.0
     }
 
-    fn configure_new_entity(&self, mut commands: EntityCommands) {
+    fn cost(&self) -> u32 {
+        5
+    }
+
+    fn configure_for_map(&self, mut commands: EntityCommands, map_type: &TilemapType) {
         let input_direction = self.1.opposite();
         let mut conveyor = Conveyor::default();
         let inputs = ConveyorDirections::new(input_direction);
         conveyor.set_inputs(inputs);
-        conveyor.set_outputs(ConveyorDirections::all_except(inputs));
+        conveyor.set_outputs(ConveyorDirections::all_except(map_type, inputs));
 
         commands.insert((
-            Distributor::new(input_direction, 5),
+            Distributor::new(input_direction, 5, map_type),
             conveyor,
             Name::new("Distributor"),
         ));
@@ -76,7 +87,7 @@ impl PlaceTileEvent for PlaceDistributorEvent {
 pub struct Distributor {
     next_output: ConveyorDirection,
     input: PayloadTransportLine,
-    outputs: SmallVec<[(ConveyorDirection, PayloadTransportLine); 3]>,
+    outputs: SmallVec<[(ConveyorDirection, PayloadTransportLine); 7]>,
     capacity: u32,
 }
 
@@ -106,11 +117,15 @@ impl PayloadHandler for Distributor {
                 .flat_map(|(_, line)| line.iter_payloads()),
         )
     }
+
+    fn transport_lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        std::iter::once(&self.input).chain(self.outputs.iter().map(|(_, line)| line))
+    }
 }
 
 impl Distributor {
-    pub fn new(input: ConveyorDirection, capacity: u32) -> Self {
-        let outputs = ConveyorDirections::all_except(ConveyorDirections::new(input));
+    pub fn new(input: ConveyorDirection, capacity: u32, map_type: &TilemapType) -> Self {
+        let outputs = ConveyorDirections::all_except(map_type, ConveyorDirections::new(input));
         let outputs: SmallVec<_> = outputs
             .iter()
             .map(|dir| (dir, PayloadTransportLine::new(dir, capacity)))
@@ -124,6 +139,18 @@ impl Distributor {
         }
     }
 
+    pub fn next_output(&self) -> ConveyorDirection {
+        self.next_output
+    }
+
+    pub fn input(&self) -> &PayloadTransportLine {
+        &self.input
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = &(ConveyorDirection, PayloadTransportLine)> {
+        self.outputs.iter()
+    }
+
     fn count(&self) -> usize {
         self.input.count()
             + self
@@ -141,13 +168,15 @@ impl Distributor {
             .for_each(|(_, ptl)| ptl.update_payloads(t));
     }
 
+    #[expect(clippy::too_many_arguments)]
     pub fn distribute<F>(
         &mut self,
         self_conveyor: &Conveyor,
-        tile_storage: &TileStorage,
+        tile_storage: &ChunkedTileStorage,
         tile_pos: &TilePos,
         map_size: &TilemapSize,
-        conveyors: &Query<&Conveyor>,
+        map_type: &TilemapType,
+        conveyors: &Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
         get_payload: F,
     ) -> Option<Entity>
     where
@@ -158,6 +187,7 @@ impl Distributor {
             tile_storage,
             tile_pos,
             map_size,
+            map_type,
             conveyors,
         ) {
             let payload = self
@@ -172,7 +202,7 @@ impl Distributor {
                 self.input.remove_payload(payload);
             }
 
-            self.next_output = destination.next();
+            self.next_output = destination.next(map_type);
 
             return payload;
         }
@@ -191,38 +221,41 @@ impl Distributor {
 }
 
 fn update_distributor_payloads(
-    distributors: Query<(Entity, &mut Distributor, &TilePos)>,
-    conveyors: Query<&Conveyor>,
+    distributors: Query<(Entity, &mut Distributor, &TilePos), Without<Disabled>>,
+    conveyors: Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
     time: Res<Time>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
     mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
 ) {
-    let (tile_storage, map_size) = base.into_inner();
+    let (tile_storage, map_size, map_type) = base.into_inner();
     let t = time.delta_secs();
 
     for (source, mut distributor, tile_pos) in distributors {
         distributor.update_payloads(t);
 
-        if let Ok(conveyor) = conveyors.get(source) {
+        if let Ok((conveyor, ..)) = conveyors.get(source) {
             if let Some(payload) = distributor.input.get_payload_to_transfer() {
                 distributor.distribute(
                     conveyor,
                     tile_storage,
                     tile_pos,
                     map_size,
+                    map_type,
                     &conveyors,
                     || payload,
                 );
             }
 
             if let Some((dir, payload)) = distributor.get_payload_to_transfer() {
-                let destination_pos = tile_pos.square_offset(&dir.into(), map_size);
-                let destination_entity = destination_pos.and_then(|pos| tile_storage.get(&pos));
-                if let Some(destination) = destination_entity {
+                let destination_pos = dir.offset(tile_pos, map_size);
+                if let Some(destination_pos) = destination_pos
+                    && let Some(destination) = tile_storage.get(&destination_pos)
+                {
                     let e = RequestPayloadTransferEvent {
                         payload,
                         source,
                         destination,
+                        destination_pos,
                         direction: dir,
                     };
                     send_payloads.write(e);
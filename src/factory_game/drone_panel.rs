@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        drones::{Drone, DronePort, DroneQueue, WaitingFor, tile_to_world},
+        storage::Storage,
+        value::Value,
+    },
+    helpers::TilemapQuery,
+};
+
+pub fn drone_panel_plugin(app: &mut App) {
+    app.init_resource::<DronePanel>()
+        .add_systems(
+            Update,
+            (
+                toggle_drone_panel
+                    .run_if(input_just_pressed(KeyCode::KeyL))
+                    .run_if(not(egui_wants_any_input)),
+                draw_flight_paths,
+            )
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_drone_panel.run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_drone_panel);
+}
+
+/// Whether the logistics panel is open, and the request values being edited
+/// for each requester port, which are only applied once they parse.
+#[derive(Resource, Default)]
+struct DronePanel {
+    open: bool,
+    request_texts: HashMap<Entity, String>,
+}
+
+fn toggle_drone_panel(mut panel: ResMut<DronePanel>) {
+    panel.open = !panel.open;
+}
+
+fn close_drone_panel(mut panel: ResMut<DronePanel>) {
+    panel.open = false;
+    panel.request_texts.clear();
+}
+
+fn draw_flight_paths(
+    mut gizmos: Gizmos,
+    drones: Query<&Drone>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for drone in drones {
+        if let Some((from, to)) = drone.flight() {
+            gizmos.line_2d(
+                tile_to_world(&base, from),
+                tile_to_world(&base, to),
+                Color::srgba(1.0, 0.8, 0.2, 0.6),
+            );
+        }
+    }
+}
+
+fn show_drone_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<DronePanel>,
+    drones: Query<&Drone>,
+    mut ports: Query<(Entity, &TilePos, &Storage, &mut DronePort)>,
+    queue: Res<DroneQueue>,
+) -> Result {
+    if !panel.open {
+        return Ok(());
+    }
+
+    let panel = &mut *panel;
+    let idle = drones.iter().filter(|drone| drone.is_idle()).count();
+    let flying = drones.iter().count() - idle;
+
+    egui::Window::new("Logistics (L)")
+        .open(&mut panel.open)
+        .resizable(false)
+        .show(contexts.ctx_mut()?, |ui| {
+            ui.label(format!("Drones: {flying} flying, {idle} idle"));
+
+            ui.separator();
+            ui.heading("Requesters");
+            let mut requesters: Vec<_> = ports
+                .iter_mut()
+                .filter(|(.., port)| matches!(**port, DronePort::Requester(_)))
+                .collect();
+            if requesters.is_empty() {
+                ui.label("Place a requester port to have drones fetch payloads");
+            }
+            requesters.sort_by_key(|(_, tile_pos, ..)| (tile_pos.x, tile_pos.y));
+
+            for (entity, tile_pos, storage, mut port) in requesters {
+                let DronePort::Requester(value) = &*port else {
+                    continue;
+                };
+                let text = panel
+                    .request_texts
+                    .entry(entity)
+                    .or_insert_with(|| value.source_text());
+
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{}, {} ({}/{})",
+                        tile_pos.x,
+                        tile_pos.y,
+                        storage.count(),
+                        storage.capacity()
+                    ));
+                    if ui.text_edit_singleline(text).changed()
+                        && let Some(new_value) = Value::parse(text)
+                    {
+                        *port = DronePort::Requester(new_value);
+                    }
+                });
+            }
+
+            ui.separator();
+            ui.heading("Queue");
+            if queue.requests().is_empty() {
+                ui.label("Nothing waiting");
+            }
+            for request in queue.requests() {
+                let waiting_for = match request.waiting_for {
+                    WaitingFor::Drone => "waiting for a drone",
+                    WaitingFor::Provider => "no provider has any",
+                };
+                ui.label(format!(
+                    "{} × {} to {}, {}: {waiting_for}",
+                    request.wanted,
+                    request.value.source_text(),
+                    request.tile_pos.x,
+                    request.tile_pos.y,
+                ));
+            }
+        });
+
+    Ok(())
+}
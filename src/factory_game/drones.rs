@@ -0,0 +1,445 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use smallvec::SmallVec;
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer, ConveyorSystems,
+        footprint::Footprint,
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
+        operators::Operand,
+        payloads::Payload,
+        storage::{STORAGE_FOOTPRINT, Storage, storage_bundle},
+        value::Value,
+    },
+    helpers::{TilemapQuery, TilemapQueryItem},
+    sprite_sheet::{GameSprite, SpriteSheet},
+};
+
+pub fn drones_plugin(app: &mut App) {
+    app.register_place_tile_event::<PlaceDronePortEvent>()
+        .register_type::<DronePort>()
+        .register_type::<Drone>()
+        .init_resource::<DroneQueue>()
+        .add_observer(despawn_drone_cargo)
+        .add_systems(
+            Update,
+            (
+                (spawn_drones, update_drone_sprites).in_set(ConveyorSystems::TileUpdater),
+                update_drones.in_set(ConveyorSystems::TransportLogic),
+                update_drone_transforms.in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+/// How many drones each requester port has
+const DRONES_PER_PORT: usize = 2;
+
+/// In tiles per second
+const DRONE_SPEED: f32 = 4.0;
+
+/// How many payloads a drone can carry at once
+const DRONE_CAPACITY: usize = 3;
+
+const PORT_CAPACITY: usize = 20;
+
+/// Storage that drones collect payloads from, or that drones bring the
+/// payloads it requests to.
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+pub enum DronePort {
+    Provider,
+    Requester(Value),
+}
+
+impl DronePort {
+    pub fn sprite(&self) -> GameSprite {
+        match self {
+            DronePort::Provider => GameSprite::ProviderPort,
+            DronePort::Requester(_) => GameSprite::RequesterPort,
+        }
+    }
+}
+
+pub struct DronePortTool(DronePort);
+
+impl DronePortTool {
+    pub fn provider() -> Self {
+        DronePortTool(DronePort::Provider)
+    }
+
+    pub fn requester() -> Self {
+        DronePortTool(DronePort::Requester(Value::Int(1)))
+    }
+}
+
+impl Tool for DronePortTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (self.0.sprite(), TileFlip::default())
+    }
+
+    fn footprint_size(&self) -> UVec2 {
+        STORAGE_FOOTPRINT.size()
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceDronePortEvent(TilePos::default(), self.0.clone()).cost()
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(PlaceDronePortEvent(*tile_pos, self.0.clone()));
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct PlaceDronePortEvent(pub TilePos, pub DronePort);
+
+impl PlaceTileEvent for PlaceDronePortEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    fn cost(&self) -> u32 {
+        30
+    }
+
+    fn footprint_size(&self) -> UVec2 {
+        STORAGE_FOOTPRINT.size()
+    }
+
+    fn configure_new_entity(&self, mut commands: EntityCommands) {
+        let name = match self.1 {
+            DronePort::Provider => "Provider Port",
+            DronePort::Requester(_) => "Requester Port",
+        };
+        commands.insert((
+            storage_bundle(self.0, PORT_CAPACITY),
+            self.1.clone(),
+            Name::new(name),
+        ));
+    }
+}
+
+#[derive(Component, Default)]
+#[relationship_target(relationship = DroneOf, linked_spawn)]
+pub struct Drones(Vec<Entity>);
+
+/// The requester port a drone belongs to, and is despawned along with
+#[derive(Component)]
+#[relationship(relationship_target = Drones)]
+pub struct DroneOf(Entity);
+
+/// Flies payloads from provider ports to the requester port it belongs to.
+/// Positions are in tiles, so that flying doesn't depend on how the tilemap
+/// is drawn.
+#[derive(Component, Debug, Reflect)]
+pub struct Drone {
+    position: Vec2,
+    target: Vec2,
+    /// In tiles per second
+    speed: f32,
+    capacity: usize,
+    cargo: SmallVec<[Entity; 4]>,
+    task: DroneTask,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum DroneTask {
+    /// Waiting at its port for something to fetch
+    Idle,
+    /// Flying to a provider to collect `amount` payloads
+    Fetch { provider: Entity, amount: usize },
+    /// Flying home with whatever it collected
+    Deliver,
+}
+
+impl Drone {
+    fn new(position: Vec2) -> Self {
+        Drone {
+            position,
+            target: position,
+            speed: DRONE_SPEED,
+            capacity: DRONE_CAPACITY,
+            cargo: SmallVec::default(),
+            task: DroneTask::Idle,
+        }
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.task == DroneTask::Idle
+    }
+
+    /// Where the drone is and where it is flying to, in tiles, unless it is
+    /// idle
+    pub fn flight(&self) -> Option<(Vec2, Vec2)> {
+        (!self.is_idle()).then_some((self.position, self.target))
+    }
+
+    /// Payloads on board or on their way to being collected
+    fn incoming(&self) -> usize {
+        match self.task {
+            DroneTask::Fetch { amount, .. } => amount,
+            _ => self.cargo.len(),
+        }
+    }
+
+    /// Flies towards the target for `t` seconds, returning whether it has
+    /// arrived
+    fn fly(&mut self, t: f32) -> bool {
+        self.position = self.position.move_towards(self.target, self.speed * t);
+        self.position == self.target
+    }
+}
+
+/// The middle of a port, in tiles
+fn port_center(anchor: &TilePos, footprint: &Footprint) -> Vec2 {
+    Vec2::new(anchor.x as f32, anchor.y as f32) + (footprint.size().as_vec2() - Vec2::ONE) / 2.0
+}
+
+/// Converts a position in tiles, as drones use, to the world
+pub fn tile_to_world(base: &TilemapQueryItem, position: Vec2) -> Vec2 {
+    let origin = base.center_in_world(&TilePos { x: 0, y: 0 });
+    origin + position * Vec2::new(base.grid_size.x, base.grid_size.y)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitingFor {
+    Drone,
+    Provider,
+}
+
+/// Payloads a requester port wants that no drone is fetching yet
+#[derive(Debug, Clone)]
+pub struct DroneRequest {
+    pub tile_pos: TilePos,
+    pub value: Value,
+    pub wanted: usize,
+    pub waiting_for: WaitingFor,
+}
+
+/// The requests still waiting after drones have been sent out, rebuilt
+/// every tick
+#[derive(Resource, Default, Debug)]
+pub struct DroneQueue(Vec<DroneRequest>);
+
+impl DroneQueue {
+    pub fn requests(&self) -> &[DroneRequest] {
+        &self.0
+    }
+}
+
+fn spawn_drones(
+    mut commands: Commands,
+    new_ports: Query<(Entity, &TilePos, &Footprint, &DronePort), Added<DronePort>>,
+) {
+    for (entity, anchor, footprint, port) in new_ports {
+        if let DronePort::Requester(_) = port {
+            for _ in 0..DRONES_PER_PORT {
+                commands.spawn((
+                    StateScoped(GameState::FactoryGame),
+                    Name::new("Drone"),
+                    Drone::new(port_center(anchor, footprint)),
+                    DroneOf(entity),
+                ));
+            }
+        }
+    }
+}
+
+fn despawn_drone_cargo(
+    trigger: Trigger<OnRemove, Drone>,
+    drones: Query<&Drone>,
+    mut commands: Commands,
+) {
+    if let Ok(drone) = drones.get(trigger.target()) {
+        for payload in &drone.cargo {
+            commands.entity(*payload).despawn();
+        }
+    }
+}
+
+/// Flies drones, then sends idle drones to the nearest provider with
+/// payloads of the value their port requests.
+fn update_drones(
+    time: Res<Time>,
+    mut drones: Query<(Entity, &mut Drone, &DroneOf)>,
+    mut ports: Query<(Entity, &mut Storage, &TilePos, &Footprint, &DronePort)>,
+    operands: Query<&Operand>,
+    mut queue: ResMut<DroneQueue>,
+) {
+    let t = time.delta_secs();
+    let matches = |value: &Value| {
+        let value = value.clone();
+        move |payload: Entity| {
+            operands
+                .get(payload)
+                .is_ok_and(|operand| operand.0 == value)
+        }
+    };
+
+    for (_, mut drone, home) in &mut drones {
+        let Ok((_, _, anchor, footprint, DronePort::Requester(value))) = ports.get(home.0) else {
+            continue;
+        };
+        let (home_center, value) = (port_center(anchor, footprint), value.clone());
+
+        match drone.task {
+            DroneTask::Idle => {}
+            DroneTask::Fetch { provider, amount } => {
+                let Ok((_, _, anchor, footprint, _)) = ports.get(provider) else {
+                    // The provider has been cleared away
+                    drone.task = DroneTask::Deliver;
+                    continue;
+                };
+                drone.target = port_center(anchor, footprint);
+
+                if drone.fly(t) {
+                    let (_, mut storage, ..) = ports.get_mut(provider).unwrap();
+                    while drone.cargo.len() < amount
+                        && let Some(payload) = storage.take(matches(&value))
+                    {
+                        drone.cargo.push(payload);
+                    }
+                    drone.task = DroneTask::Deliver;
+                }
+            }
+            DroneTask::Deliver => {
+                drone.target = home_center;
+
+                if drone.fly(t) {
+                    let (_, mut storage, ..) = ports.get_mut(home.0).unwrap();
+                    while let Some(&payload) = drone.cargo.first()
+                        && storage.push(payload)
+                    {
+                        drone.cargo.remove(0);
+                    }
+                    if drone.cargo.is_empty() {
+                        drone.task = DroneTask::Idle;
+                    }
+                }
+            }
+        }
+    }
+
+    // What each requester wants, less what is already on its way
+    let mut requesters: Vec<_> = ports
+        .iter()
+        .filter_map(|(entity, storage, tile_pos, footprint, port)| match port {
+            DronePort::Requester(value) => Some((
+                entity,
+                *tile_pos,
+                port_center(tile_pos, footprint),
+                value.clone(),
+                storage.capacity() - storage.count(),
+            )),
+            DronePort::Provider => None,
+        })
+        .collect();
+    requesters.sort_by_key(|(entity, ..)| *entity);
+
+    // Payloads in providers that drones are already on their way to collect
+    let mut reserved: Vec<(Entity, Value, usize)> = vec![];
+    for (_, drone, home) in &drones {
+        let Some((.., value, wanted)) = requesters.iter_mut().find(|r| r.0 == home.0) else {
+            continue;
+        };
+        *wanted = wanted.saturating_sub(drone.incoming());
+        if let DroneTask::Fetch { provider, amount } = drone.task {
+            reserved.push((provider, value.clone(), amount));
+        }
+    }
+
+    queue.0.clear();
+    for (requester, tile_pos, center, value, mut wanted) in requesters {
+        if wanted == 0 {
+            continue;
+        }
+
+        let mut idle_drones: Vec<_> = drones
+            .iter()
+            .filter(|(_, drone, home)| home.0 == requester && drone.is_idle())
+            .map(|(entity, ..)| entity)
+            .collect();
+        idle_drones.sort();
+
+        let mut waiting_for = WaitingFor::Drone;
+        for drone_entity in idle_drones {
+            let available = |provider: Entity, storage: &Storage| {
+                let taken: usize = reserved
+                    .iter()
+                    .filter(|(p, v, _)| *p == provider && *v == value)
+                    .map(|(.., amount)| amount)
+                    .sum();
+                storage
+                    .count_matching(matches(&value))
+                    .saturating_sub(taken)
+            };
+            let nearest = ports
+                .iter()
+                .filter(|(.., port)| **port == DronePort::Provider)
+                .map(|(provider, storage, anchor, footprint, _)| {
+                    let distance = port_center(anchor, footprint).distance_squared(center);
+                    (provider, available(provider, storage), distance)
+                })
+                .filter(|(_, available, _)| *available > 0)
+                .min_by(|a, b| a.2.total_cmp(&b.2).then(a.0.cmp(&b.0)));
+
+            let Some((provider, available, _)) = nearest else {
+                waiting_for = WaitingFor::Provider;
+                break;
+            };
+
+            let (_, mut drone, _) = drones.get_mut(drone_entity).unwrap();
+            let amount = drone.capacity.min(wanted).min(available);
+            drone.task = DroneTask::Fetch { provider, amount };
+            reserved.push((provider, value.clone(), amount));
+
+            wanted -= amount;
+            if wanted == 0 {
+                break;
+            }
+        }
+
+        if wanted > 0 {
+            queue.0.push(DroneRequest {
+                tile_pos,
+                value,
+                wanted,
+                waiting_for,
+            });
+        }
+    }
+}
+
+fn update_drone_sprites(
+    mut commands: Commands,
+    new_drones: Query<(Entity, &Drone), Added<Drone>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+    sprite_sheet: Res<SpriteSheet>,
+) {
+    for (entity, drone) in new_drones {
+        commands.entity(entity).insert((
+            sprite_sheet.sprite(GameSprite::Drone),
+            Transform::from_translation(tile_to_world(&base, drone.position).extend(5.0)),
+        ));
+    }
+}
+
+/// Cargo hangs below the drone carrying it
+fn update_drone_transforms(
+    drones: Query<(&Drone, &mut Transform), Without<Payload>>,
+    mut payloads: Query<&mut Transform, With<Payload>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (drone, mut transform) in drones {
+        let center = tile_to_world(&base, drone.position);
+        transform.translation = center.extend(5.0);
+
+        for (i, payload) in drone.cargo.iter().enumerate() {
+            if let Ok(mut transform) = payloads.get_mut(*payload) {
+                let offset = Vec2::new(0.0, -8.0 - 6.0 * i as f32);
+                *transform = Transform::from_translation((center + offset).extend(4.0));
+            }
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use std::any::TypeId;
+
+use bevy::prelude::*;
+
+use crate::factory_game::{ConveyorSystems, sink::PayloadSunkEvent, value::Value};
+
+pub fn economy_plugin(app: &mut App) {
+    app.register_type::<Money>().add_systems(
+        Update,
+        earn_from_sinks
+            .run_if(resource_exists::<Money>)
+            .after(ConveyorSystems::TransportLogic)
+            .before(ConveyorSystems::PayloadTransforms),
+    );
+}
+
+/// What the player starts the sandbox with
+pub const STARTING_MONEY: u64 = 200;
+
+/// The most a single payload can earn
+const MAX_PAYOUT: u64 = 1000;
+
+/// The player's balance.  Without this resource, as in levels, placing tiles
+/// is free.
+#[derive(Resource, Debug, Reflect)]
+#[reflect(Resource)]
+pub struct Money(u64);
+
+impl Default for Money {
+    fn default() -> Self {
+        Money(STARTING_MONEY)
+    }
+}
+
+impl Money {
+    pub fn balance(&self) -> u64 {
+        self.0
+    }
+
+    pub fn can_afford(&self, cost: u32) -> bool {
+        self.0 >= cost as u64
+    }
+
+    /// Adds `refund` and then takes `cost`, unless that would leave the
+    /// balance negative, in which case nothing changes.
+    pub fn try_pay(&mut self, cost: u32, refund: u32) -> bool {
+        match (self.0 + refund as u64).checked_sub(cost as u64) {
+            Some(balance) => {
+                self.0 = balance;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// What was paid for a tile, part of which is refunded when it is cleared
+#[derive(Component, Debug, Reflect)]
+pub struct Cost {
+    paid: u32,
+    /// The type of the event that placed the tile
+    placed_by: TypeId,
+}
+
+impl Cost {
+    pub fn new<T: 'static>(paid: u32) -> Self {
+        Cost {
+            paid,
+            placed_by: TypeId::of::<T>(),
+        }
+    }
+
+    /// What's given back when the tile is replaced by an event of type `T`.
+    /// Replacing a tile with the same kind of tile, such as turning a belt,
+    /// refunds all of it, so only the difference in cost is paid.
+    /// Anything else, including clearing the tile, refunds half.
+    pub fn refund<T: 'static>(&self) -> u32 {
+        if self.placed_by == TypeId::of::<T>() {
+            self.paid
+        } else {
+            self.paid / 2
+        }
+    }
+}
+
+/// What a sink pays for receiving `value`.  Error payloads are worth nothing.
+pub fn payout(value: Option<&Value>) -> u64 {
+    let payout = match value {
+        None => 0,
+        Some(Value::Int(value)) => value.unsigned_abs(),
+        Some(Value::Bool(_)) => 1,
+        Some(Value::Colour(channels)) => channels.iter().filter(|c| **c > 0).count() as u64,
+        Some(Value::Text(text)) => text.chars().count() as u64,
+    };
+    payout.min(MAX_PAYOUT)
+}
+
+fn earn_from_sinks(mut sunk: EventReader<PayloadSunkEvent>, mut money: ResMut<Money>) {
+    let earned: u64 = sunk.read().map(|event| payout(event.value.as_ref())).sum();
+    if earned > 0 {
+        money.0 += earned;
+    }
+}
+
+#[cfg(test)]
+mod economy_test {
+    use super::*;
+
+    #[test]
+    fn pay_with_refund() {
+        let mut money = Money(10);
+
+        assert!(!money.try_pay(15, 0));
+        assert_eq!(money.balance(), 10);
+
+        assert!(money.try_pay(15, 5));
+        assert_eq!(money.balance(), 0);
+    }
+
+    #[test]
+    fn payouts() {
+        assert_eq!(payout(None), 0);
+        assert_eq!(payout(Some(&Value::Int(-7))), 7);
+        assert_eq!(payout(Some(&Value::Int(i64::MAX))), MAX_PAYOUT);
+        assert_eq!(payout(Some(&Value::Colour([255, 0, 8]))), 2);
+        assert_eq!(payout(Some(&Value::Text("abc".to_string()))), 3);
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::factory_game::{
+    conveyor::Conveyor,
+    helpers::{ConveyorDirection, ConveyorDirections},
+};
+
+/// Machines that cover more than one tile have a `Footprint`.  The machine's
+/// entity is registered in the base layer's `ChunkedTileStorage` for every
+/// tile it covers, and its `TilePos` is the bottom-left (anchor) tile.
+#[derive(Component, Clone, Copy, Debug, Reflect)]
+pub struct Footprint {
+    size: UVec2,
+    #[reflect(ignore)]
+    ports: &'static [Port],
+}
+
+/// A named place on the edge of a footprint where payloads can enter or
+/// leave the machine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Port {
+    pub name: &'static str,
+    /// The tile within the footprint, relative to the anchor
+    pub cell: UVec2,
+    /// The edge of the tile that the port is on
+    pub side: ConveyorDirection,
+    pub kind: PortKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortKind {
+    Input,
+    Output,
+}
+
+impl Footprint {
+    pub const fn new(size: UVec2, ports: &'static [Port]) -> Self {
+        Footprint { size, ports }
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn ports(&self) -> impl Iterator<Item = &Port> {
+        self.ports.iter()
+    }
+
+    pub fn port(&self, name: &str) -> Option<&Port> {
+        self.ports.iter().find(|port| port.name == name)
+    }
+
+    pub fn cells(&self, anchor: &TilePos) -> impl Iterator<Item = TilePos> + use<> {
+        footprint_cells(anchor, self.size)
+    }
+
+    pub fn port_pos(&self, anchor: &TilePos, port: &Port) -> TilePos {
+        TilePos {
+            x: anchor.x + port.cell.x,
+            y: anchor.y + port.cell.y,
+        }
+    }
+
+    /// Find the port of the given kind on the `side` edge of the tile at
+    /// `tile_pos`.
+    pub fn port_at(
+        &self,
+        anchor: &TilePos,
+        tile_pos: &TilePos,
+        side: ConveyorDirection,
+        kind: PortKind,
+    ) -> Option<&Port> {
+        self.ports.iter().find(|port| {
+            port.kind == kind && port.side == side && self.port_pos(anchor, port) == *tile_pos
+        })
+    }
+
+    /// The sides that have a port of the given kind, for use in the machine's
+    /// `Conveyor`.
+    pub fn port_sides(&self, kind: PortKind) -> ConveyorDirections {
+        self.ports
+            .iter()
+            .filter(|port| port.kind == kind)
+            .map(|port| port.side)
+            .into()
+    }
+
+    /// The machine's `Conveyor` as seen at the tile at `cell`, with only the
+    /// ports on that tile
+    pub fn cell_conveyor(&self, anchor: &TilePos, cell: &TilePos) -> Conveyor {
+        let sides = |kind| {
+            self.ports
+                .iter()
+                .filter(|port| port.kind == kind && self.port_pos(anchor, port) == *cell)
+                .map(|port| port.side)
+                .into()
+        };
+
+        let mut conveyor = Conveyor::new(sides(PortKind::Output));
+        conveyor.set_inputs(sides(PortKind::Input));
+        conveyor
+    }
+}
+
+pub fn footprint_cells(anchor: &TilePos, size: UVec2) -> impl Iterator<Item = TilePos> + use<> {
+    let anchor = *anchor;
+    (0..size.y).flat_map(move |y| {
+        (0..size.x).map(move |x| TilePos {
+            x: anchor.x + x,
+            y: anchor.y + y,
+        })
+    })
+}
+
+/// Returns the tiles covered by a footprint, or None if any of them are off
+/// the map.
+pub fn footprint_cells_on_map(
+    anchor: &TilePos,
+    size: UVec2,
+    map_size: &TilemapSize,
+) -> Option<Vec<TilePos>> {
+    let cells: Vec<_> = footprint_cells(anchor, size).collect();
+    cells
+        .iter()
+        .all(|pos| pos.within_map_bounds(map_size))
+        .then_some(cells)
+}
+
+#[cfg(test)]
+mod footprint_test {
+    use super::*;
+    use ConveyorDirection::*;
+
+    const PORTS: &[Port] = &[
+        Port {
+            name: "in",
+            cell: UVec2::new(0, 1),
+            side: West,
+            kind: PortKind::Input,
+        },
+        Port {
+            name: "out",
+            cell: UVec2::new(2, 0),
+            side: East,
+            kind: PortKind::Output,
+        },
+    ];
+
+    #[test]
+    fn cells() {
+        let footprint = Footprint::new(UVec2::new(3, 2), PORTS);
+        let cells: Vec<_> = footprint.cells(&TilePos { x: 5, y: 7 }).collect();
+
+        assert_eq!(cells.len(), 6);
+        assert_eq!(cells.first(), Some(&TilePos { x: 5, y: 7 }));
+        assert_eq!(cells.last(), Some(&TilePos { x: 7, y: 8 }));
+    }
+
+    #[test]
+    fn cells_off_map() {
+        let map_size = TilemapSize { x: 10, y: 10 };
+        let size = UVec2::new(2, 2);
+
+        assert!(footprint_cells_on_map(&TilePos { x: 8, y: 8 }, size, &map_size).is_some());
+        assert!(footprint_cells_on_map(&TilePos { x: 9, y: 8 }, size, &map_size).is_none());
+    }
+
+    #[test]
+    fn ports() {
+        let footprint = Footprint::new(UVec2::new(3, 2), PORTS);
+        let anchor = TilePos { x: 5, y: 7 };
+
+        let input = footprint.port_at(&anchor, &TilePos { x: 5, y: 8 }, West, PortKind::Input);
+        assert_eq!(input.map(|p| p.name), Some("in"));
+
+        assert!(
+            footprint
+                .port_at(&anchor, &TilePos { x: 5, y: 7 }, West, PortKind::Input)
+                .is_none()
+        );
+        assert!(
+            footprint
+                .port_at(&anchor, &TilePos { x: 5, y: 8 }, West, PortKind::Output)
+                .is_none()
+        );
+
+        let output = footprint.port("out").unwrap();
+        assert_eq!(footprint.port_pos(&anchor, output), TilePos { x: 7, y: 7 });
+        assert_eq!(
+            footprint.port_sides(PortKind::Output),
+            ConveyorDirections::new(East)
+        );
+    }
+}
@@ -1,16 +1,24 @@
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
+use bevy_rand::{global::GlobalEntropy, prelude::WyRand};
+use rand::{Rng, RngCore};
 use smallvec::SmallVec;
 
 use crate::{
     factory_game::{
         BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
         conveyor::{Conveyor, ConveyorUpdated, TilesToCheck},
-        helpers::{ConveyorDirection, ConveyorDirections, get_neighbors_from_query},
+        footprint::Footprint,
+        helpers::{ConveyorDirection, ConveyorDirections, get_neighbor_conveyors},
         interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
         operators::{Operand, operand_bundle},
         payload_handler::{AddPayloadHandler, PayloadHandler},
         payloads::{Payload, PayloadTransportLine, RequestPayloadTransferEvent},
+        power::PowerConsumer,
+        research::{Research, Upgrade},
+        signals::Disabled,
+        value::Value,
     },
     helpers::TilemapQuery,
     sprite_sheet::GameSprite,
@@ -19,6 +27,8 @@ use crate::{
 pub fn generator_plugin(app: &mut App) {
     app.register_place_tile_event::<PlaceGeneratorEvent>()
         .add_payload_handler::<Generator>()
+        .register_type::<Sequence>()
+        .add_event::<PayloadGeneratedEvent>()
         .add_systems(
             Update,
             (
@@ -32,11 +42,18 @@ pub fn generator_plugin(app: &mut App) {
 
 pub struct GeneratorTool;
 
+/// Power a generator needs to run at full speed
+const POWER: u32 = 5;
+
 impl Tool for GeneratorTool {
     fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
         (GameSprite::Generator, TileFlip::default())
     }
 
+    fn cost(&self) -> u32 {
+        PlaceGeneratorEvent(TilePos::default()).cost()
+    }
+
     fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
         commands.trigger(PlaceGeneratorEvent(*tile_pos));
     }
@@ -50,16 +67,136 @@ impl PlaceTileEvent for PlaceGeneratorEvent {
         self.0
     }
 
-    fn configure_new_entity(&self, mut commands: EntityCommands) {
-        commands.insert((Generator::default(), Name::new("Generator")));
+    fn cost(&self) -> u32 {
+        20
+    }
+
+    fn needs_deposit(&self) -> bool {
+        true
     }
+
+    fn configure_for_map(&self, mut commands: EntityCommands, map_type: &TilemapType) {
+        commands.insert((
+            Generator::default(),
+            Conveyor::new(ConveyorDirections::all(map_type)),
+            Name::new("Generator"),
+        ));
+    }
+}
+
+/// The values a generator emits, one per payload.
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub enum Sequence {
+    Constant(Value),
+    /// Counts up from `next` in steps of `step`, wrapping on overflow
+    Counter {
+        next: i64,
+        step: i64,
+    },
+    /// Starts again from 0, 1 when the numbers get too big
+    Fibonacci {
+        current: i64,
+        next: i64,
+    },
+    /// Uniformly distributed between `min` and `max` inclusive
+    Random {
+        min: i64,
+        max: i64,
+    },
+    /// Each of `values` in turn, starting at `index`
+    Cycle {
+        values: Vec<Value>,
+        index: usize,
+    },
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Sequence::Constant(Value::Int(1))
+    }
+}
+
+impl Sequence {
+    /// One of each kind of sequence, for choosing from
+    pub fn kinds() -> [Sequence; 5] {
+        [
+            Sequence::default(),
+            Sequence::counter(),
+            Sequence::fibonacci(),
+            Sequence::Random { min: 0, max: 9 },
+            Sequence::Cycle {
+                values: vec![Value::Int(1), Value::Int(2), Value::Int(3)],
+                index: 0,
+            },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sequence::Constant(_) => "Constant",
+            Sequence::Counter { .. } => "Counter",
+            Sequence::Fibonacci { .. } => "Fibonacci",
+            Sequence::Random { .. } => "Random",
+            Sequence::Cycle { .. } => "Cycle",
+        }
+    }
+
+    pub fn counter() -> Self {
+        Sequence::Counter { next: 0, step: 1 }
+    }
+
+    pub fn fibonacci() -> Self {
+        Sequence::Fibonacci {
+            current: 0,
+            next: 1,
+        }
+    }
+
+    /// Returns the next value in the sequence, or None if there are no
+    /// values to emit.
+    pub fn next_value(&mut self, rng: &mut impl RngCore) -> Option<Value> {
+        match self {
+            Sequence::Constant(value) => Some(value.clone()),
+            Sequence::Counter { next, step } => {
+                let value = *next;
+                *next = next.wrapping_add(*step);
+                Some(Value::Int(value))
+            }
+            Sequence::Fibonacci { current, next } => {
+                let value = *current;
+                (*current, *next) = match current.checked_add(*next) {
+                    Some(after_next) => (*next, after_next),
+                    None => (0, 1),
+                };
+                Some(Value::Int(value))
+            }
+            Sequence::Random { min, max } => {
+                let (low, high) = (*min.min(max), *min.max(max));
+                Some(Value::Int(rng.random_range(low..=high)))
+            }
+            Sequence::Cycle { values, index } => {
+                let value = values.get(*index % values.len().max(1)).cloned();
+                *index = (*index + 1) % values.len().max(1);
+                value
+            }
+        }
+    }
+}
+
+/// Sent when a generator has put a new payload on one of its outputs
+#[derive(Event, Debug)]
+pub struct PayloadGeneratedEvent {
+    pub generator: Entity,
+    pub value: Value,
 }
 
 #[derive(Component, Debug, Reflect)]
-#[require(Conveyor::new(ConveyorDirections::all()))]
-struct Generator {
+#[require(Conveyor, PowerConsumer::new(POWER))]
+pub struct Generator {
+    pub sequence: Sequence,
+    /// Seconds between payloads
+    pub time_between_generations: f32,
     next_generate_time: f32,
-    time_between_generations: f32,
     outputs: SmallVec<[(ConveyorDirection, PayloadTransportLine); 4]>,
     next_output: ConveyorDirection,
 }
@@ -67,8 +204,9 @@ struct Generator {
 impl Default for Generator {
     fn default() -> Self {
         Generator {
-            next_generate_time: 0.0,
+            sequence: Sequence::default(),
             time_between_generations: 1.0,
+            next_generate_time: 0.0,
             outputs: SmallVec::default(),
             next_output: ConveyorDirection::default(),
         }
@@ -93,9 +231,26 @@ impl PayloadHandler for Generator {
     fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
         std::iter::empty().chain(self.outputs.iter().flat_map(|(_, ptl)| ptl.iter_payloads()))
     }
+
+    fn transport_lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        self.outputs.iter().map(|(_, ptl)| ptl)
+    }
 }
 
 impl Generator {
+    pub fn next_output(&self) -> ConveyorDirection {
+        self.next_output
+    }
+
+    /// The elapsed time at which the next payload is due
+    pub fn next_generate_time(&self) -> f32 {
+        self.next_generate_time
+    }
+
+    pub fn outputs(&self) -> impl Iterator<Item = &(ConveyorDirection, PayloadTransportLine)> {
+        self.outputs.iter()
+    }
+
     fn update_payloads(&mut self, t: f32) {
         self.outputs
             .iter_mut()
@@ -117,17 +272,23 @@ fn update_generators(
     mut commands: Commands,
     to_check: Res<TilesToCheck>,
     mut generators: Query<&mut Generator>,
-    mut conveyors: Query<&mut Conveyor>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    mut conveyors: Query<(&mut Conveyor, Option<&Footprint>, &TilePos)>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
     mut conveyor_updated: EventWriter<ConveyorUpdated>,
 ) {
-    let (tile_storage, map_size) = base.into_inner();
+    let (tile_storage, map_size, map_type) = base.into_inner();
 
     for tile_pos in &to_check.0 {
         if let Some(entity) = tile_storage.get(tile_pos)
             && let Ok(mut generator) = generators.get_mut(entity)
         {
-            let neighbors = get_neighbors_from_query(tile_storage, tile_pos, map_size, &conveyors);
+            let neighbors = get_neighbor_conveyors(
+                tile_storage,
+                tile_pos,
+                map_size,
+                map_type,
+                &conveyors.as_readonly(),
+            );
 
             let output_directions =
                 ConveyorDirections::from(neighbors.iter_with_direction().filter_map(
@@ -141,7 +302,7 @@ fn update_generators(
                     },
                 ));
 
-            if let Ok(mut conveyor) = conveyors.get_mut(entity) {
+            if let Ok((mut conveyor, ..)) = conveyors.get_mut(entity) {
                 let old_outputs = conveyor.outputs();
                 conveyor.set_outputs(output_directions);
 
@@ -173,7 +334,7 @@ fn update_generators(
 fn update_generator_tiles(
     mut commands: Commands,
     new_generators: Query<Entity, (With<Generator>, Without<TileTextureIndex>)>,
-    tilemap_entity: Single<Entity, (With<BaseLayer>, With<TileStorage>)>,
+    tilemap_entity: Single<Entity, (With<BaseLayer>, With<ChunkedTileStorage>)>,
 ) {
     for new_generator in new_generators {
         commands.entity(new_generator).insert_if_new((TileBundle {
@@ -184,22 +345,39 @@ fn update_generator_tiles(
     }
 }
 
+#[expect(clippy::too_many_arguments)]
 fn generate_payloads(
     mut commands: Commands,
     time: Res<Time>,
-    generators: Query<(&TilePos, &Conveyor, &mut Generator)>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
-    conveyors: Query<&Conveyor>,
+    generators: Query<
+        (Entity, &TilePos, &Conveyor, &mut Generator, &PowerConsumer),
+        Without<Disabled>,
+    >,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
+    conveyors: Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
+    mut rng: GlobalEntropy<WyRand>,
+    research: Option<Res<Research>>,
+    mut generated: EventWriter<PayloadGeneratedEvent>,
 ) {
-    let (tile_storage, map_size) = base.into_inner();
+    let (tile_storage, map_size, map_type) = base.into_inner();
+    let speed_up = if research.is_some_and(|research| research.has_upgrade(Upgrade::FastGenerators))
+    {
+        2.0
+    } else {
+        1.0
+    };
 
-    for (tile_pos, conveyor, mut generator) in generators {
-        if time.elapsed_secs() > generator.next_generate_time
+    for (entity, tile_pos, conveyor, mut generator, power) in generators {
+        let generator = &mut *generator;
+        let speed = speed_up * power.speed();
+        if speed > 0.0
+            && time.elapsed_secs() > generator.next_generate_time
             && let Some(destination) = conveyor.get_available_destination(
                 generator.next_output,
                 tile_storage,
                 tile_pos,
                 map_size,
+                map_type,
                 &conveyors,
             )
         {
@@ -208,41 +386,53 @@ fn generate_payloads(
                 .iter_mut()
                 .find(|(dir, _)| *dir == destination)
             {
-                let payload =
+                // Only take a value from the sequence if it can be emitted
+                let payload = if ptl.has_room_for_one_more_with_mu(0.5)
+                    && let Some(value) = generator.sequence.next_value(rng.as_mut())
+                {
                     ptl.try_transfer_onto_with_mu(ConveyorDirection::default(), 0.5, || {
-                        commands.spawn(operand_bundle(Operand(1))).id()
-                    });
+                        generated.write(PayloadGeneratedEvent {
+                            generator: entity,
+                            value: value.clone(),
+                        });
+                        commands.spawn(operand_bundle(Operand(value))).id()
+                    })
+                } else {
+                    None
+                };
 
                 if payload.is_some() {
                     generator.next_generate_time =
-                        time.elapsed_secs() + generator.time_between_generations;
+                        time.elapsed_secs() + generator.time_between_generations / speed;
                 }
             }
-            generator.next_output = generator.next_output.next();
+            generator.next_output = generator.next_output.next(map_type);
         }
     }
 }
 
 fn update_generator_payloads(
-    generators: Query<(Entity, &mut Generator, &TilePos)>,
+    generators: Query<(Entity, &mut Generator, &TilePos, &PowerConsumer)>,
     time: Res<Time>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
     mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
 ) {
     let (tile_storage, map_size) = base.into_inner();
     let t = time.delta_secs();
 
-    for (source, mut generator, tile_pos) in generators {
-        generator.update_payloads(t);
+    for (source, mut generator, tile_pos, power) in generators {
+        generator.update_payloads(t * power.speed());
 
         if let Some((dir, payload)) = generator.get_payload_to_transfer() {
-            let destination_pos = tile_pos.square_offset(&dir.into(), map_size);
-            let destination_entity = destination_pos.and_then(|pos| tile_storage.get(&pos));
-            if let Some(destination) = destination_entity {
+            let destination_pos = dir.offset(tile_pos, map_size);
+            if let Some(destination_pos) = destination_pos
+                && let Some(destination) = tile_storage.get(&destination_pos)
+            {
                 let e = RequestPayloadTransferEvent {
                     payload,
                     source,
                     destination,
+                    destination_pos,
                     direction: dir,
                 };
                 send_payloads.write(e);
@@ -262,3 +452,66 @@ fn update_generator_payload_transforms(
         }
     }
 }
+
+#[cfg(test)]
+mod sequence_test {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn take(sequence: &mut Sequence, n: usize) -> Vec<Value> {
+        let mut rng = WyRand::seed_from_u64(42);
+        (0..n)
+            .filter_map(|_| sequence.next_value(&mut rng))
+            .collect()
+    }
+
+    fn ints(values: &[i64]) -> Vec<Value> {
+        values.iter().map(|v| Value::Int(*v)).collect()
+    }
+
+    #[test]
+    fn counter_and_fibonacci() {
+        assert_eq!(take(&mut Sequence::counter(), 4), ints(&[0, 1, 2, 3]));
+        assert_eq!(
+            take(&mut Sequence::fibonacci(), 7),
+            ints(&[0, 1, 1, 2, 3, 5, 8])
+        );
+    }
+
+    #[test]
+    fn fibonacci_restarts_instead_of_overflowing() {
+        let mut sequence = Sequence::Fibonacci {
+            current: i64::MAX - 1,
+            next: i64::MAX,
+        };
+        assert_eq!(take(&mut sequence, 3), ints(&[i64::MAX - 1, 0, 1]));
+    }
+
+    #[test]
+    fn random_stays_in_range() {
+        let mut sequence = Sequence::Random { min: 5, max: -5 };
+        assert!(
+            take(&mut sequence, 100)
+                .iter()
+                .all(|v| matches!(v, Value::Int(-5..=5)))
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let mut sequence = Sequence::Cycle {
+            values: vec![Value::Bool(true), Value::Bool(false)],
+            index: 0,
+        };
+        assert_eq!(
+            take(&mut sequence, 3),
+            vec![Value::Bool(true), Value::Bool(false), Value::Bool(true)]
+        );
+
+        let mut empty = Sequence::Cycle {
+            values: vec![],
+            index: 0,
+        };
+        assert_eq!(take(&mut empty, 3), vec![]);
+    }
+}
@@ -0,0 +1,167 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        chunks::ChunkedTileStorage,
+        deposits::Extracting,
+        generator::{Generator, Sequence},
+        interaction::{HoveredTile, Locked, Tools},
+        value::Value,
+    },
+};
+
+pub fn generator_panel_plugin(app: &mut App) {
+    app.init_resource::<GeneratorPanel>()
+        .add_systems(
+            Update,
+            select_generator
+                .run_if(input_just_pressed(MouseButton::Left))
+                .run_if(not(egui_wants_any_input))
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_generator_panel.run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_generator_panel);
+}
+
+/// Which generator is being configured, and the text being edited for its
+/// values, which is only applied once it parses.
+#[derive(Resource, Default)]
+struct GeneratorPanel {
+    generator: Option<Entity>,
+    values_text: String,
+}
+
+impl GeneratorPanel {
+    fn reset_values_text(&mut self, sequence: &Sequence) {
+        self.values_text = match sequence {
+            Sequence::Constant(value) => value.source_text(),
+            Sequence::Cycle { values, .. } => values
+                .iter()
+                .map(Value::source_text)
+                .collect::<Vec<_>>()
+                .join(", "),
+            _ => String::new(),
+        };
+    }
+}
+
+/// Clicking on a generator with no tool selected opens its panel, unless it
+/// was placed by a level
+fn select_generator(
+    tools: Res<Tools>,
+    hovered_tile: Single<&TilePos, With<HoveredTile>>,
+    tile_storage: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    generators: Query<&Generator, Without<Locked>>,
+    mut panel: ResMut<GeneratorPanel>,
+) {
+    if tools.current_tool().is_some() {
+        return;
+    }
+
+    let selected = tile_storage
+        .get(*hovered_tile)
+        .and_then(|entity| Some((entity, generators.get(entity).ok()?)));
+
+    panel.generator = selected.map(|(entity, _)| entity);
+    if let Some((_, generator)) = selected {
+        panel.reset_values_text(&generator.sequence);
+    }
+}
+
+fn close_generator_panel(mut panel: ResMut<GeneratorPanel>) {
+    panel.generator = None;
+}
+
+fn show_generator_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<GeneratorPanel>,
+    mut generators: Query<(&mut Generator, &TilePos, Option<&Extracting>)>,
+) -> Result {
+    let Some(entity) = panel.generator else {
+        return Ok(());
+    };
+    let Ok((mut generator, tile_pos, extracting)) = generators.get_mut(entity) else {
+        // The generator has been cleared away
+        panel.generator = None;
+        return Ok(());
+    };
+
+    let mut open = true;
+    egui::Window::new(format!("Generator at {}, {}", tile_pos.x, tile_pos.y))
+        .open(&mut open)
+        .resizable(false)
+        .show(contexts.ctx_mut()?, |ui| {
+            // What a deposit holds can't be changed
+            if let Some(Extracting(deposit)) = extracting {
+                ui.label(format!("Extracting from a {deposit:?} deposit"));
+            } else {
+                egui::ComboBox::from_label("Sequence")
+                    .selected_text(generator.sequence.name())
+                    .show_ui(ui, |ui| {
+                        for kind in Sequence::kinds() {
+                            let selected = kind.name() == generator.sequence.name();
+                            if ui.selectable_label(selected, kind.name()).clicked() && !selected {
+                                panel.reset_values_text(&kind);
+                                generator.sequence = kind;
+                            }
+                        }
+                    });
+
+                match &mut generator.sequence {
+                    Sequence::Constant(value) => {
+                        ui.label("Value: int, true/false, #rrggbb or text");
+                        if ui.text_edit_singleline(&mut panel.values_text).changed()
+                            && let Some(new_value) = Value::parse(&panel.values_text)
+                        {
+                            *value = new_value;
+                        }
+                    }
+                    Sequence::Counter { next, step } => {
+                        ui.add(egui::DragValue::new(next).prefix("Next: "));
+                        ui.add(egui::DragValue::new(step).prefix("Step: "));
+                    }
+                    Sequence::Fibonacci { current, next } => {
+                        ui.label(format!("Next: {current}"));
+                        if ui.button("Restart").clicked() {
+                            (*current, *next) = (0, 1);
+                        }
+                    }
+                    Sequence::Random { min, max } => {
+                        ui.add(egui::DragValue::new(min).prefix("Min: "));
+                        ui.add(egui::DragValue::new(max).prefix("Max: "));
+                    }
+                    Sequence::Cycle { values, index } => {
+                        ui.label("Values, separated by commas");
+                        if ui.text_edit_singleline(&mut panel.values_text).changed()
+                            && let Some(new_values) = panel
+                                .values_text
+                                .split(',')
+                                .map(Value::parse)
+                                .collect::<Option<Vec<_>>>()
+                        {
+                            *values = new_values;
+                            *index = 0;
+                        }
+                    }
+                }
+            }
+
+            ui.add(
+                egui::Slider::new(&mut generator.time_between_generations, 0.1..=5.0)
+                    .text("Seconds between payloads"),
+            );
+        });
+
+    if !open {
+        panel.generator = None;
+    }
+
+    Ok(())
+}
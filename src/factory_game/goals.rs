@@ -0,0 +1,266 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    factory_game::{
+        BaseLayer, ConveyorSystems,
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent},
+        sink::{PayloadSunkEvent, Sink},
+        value::Value,
+    },
+    helpers::TilemapQuery,
+};
+
+pub fn goals_plugin(app: &mut App) {
+    app.register_place_tile_event::<PlaceGoalSinkEvent>()
+        .register_type::<Goal>()
+        .add_event::<GoalCompleted>()
+        .add_systems(
+            Update,
+            (
+                spawn_goal_labels.in_set(ConveyorSystems::TileUpdater),
+                (record_deliveries, check_goals)
+                    .chain()
+                    .after(ConveyorSystems::TransportLogic)
+                    .before(ConveyorSystems::PayloadTransforms),
+                update_goal_labels.in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+/// Deliveries in the last this many seconds count towards a goal's rate
+const RATE_WINDOW: f32 = 10.0;
+
+/// Places a sink with a goal.  Goal sinks are set up by levels rather than
+/// built by the player.
+#[derive(Event, Debug)]
+pub struct PlaceGoalSinkEvent(pub TilePos, pub Goal);
+
+impl PlaceTileEvent for PlaceGoalSinkEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    /// Goal sinks take anything, counting what isn't wanted as rejects, so
+    /// an error payload can't jam them
+    fn configure_new_entity(&self, mut commands: EntityCommands) {
+        commands.insert((Sink::new(true), self.1.clone(), Name::new("Goal Sink")));
+    }
+}
+
+/// Sent once when every goal on the map has been met
+#[derive(Event, Debug)]
+pub struct GoalCompleted;
+
+/// A sink with a goal wants `quantity` payloads of the `target` value,
+/// optionally arriving at least `min_rate` per second.  Anything else it is
+/// given is a reject.
+#[derive(Component, Debug, Clone, Reflect)]
+#[require(GoalLabels)]
+pub struct Goal {
+    target: Value,
+    quantity: u32,
+    min_rate: Option<f32>,
+    delivered: u32,
+    rejects: u32,
+    /// When each of the deliveries in the rate window arrived
+    delivery_times: VecDeque<f32>,
+    completed: bool,
+}
+
+#[derive(Component, Default)]
+#[relationship_target(relationship = GoalLabel, linked_spawn)]
+pub struct GoalLabels(Vec<Entity>);
+
+/// Mark the text showing a goal's progress so it is despawned along with the
+/// sink
+#[derive(Component)]
+#[relationship(relationship_target = GoalLabels)]
+pub struct GoalLabel(Entity);
+
+impl Goal {
+    pub fn new(target: Value, quantity: u32, min_rate: Option<f32>) -> Self {
+        Goal {
+            target,
+            quantity: quantity.max(1),
+            min_rate,
+            delivered: 0,
+            rejects: 0,
+            delivery_times: VecDeque::default(),
+            completed: false,
+        }
+    }
+
+    pub fn delivered(&self) -> u32 {
+        self.delivered
+    }
+
+    pub fn rejects(&self) -> u32 {
+        self.rejects
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    /// Deliveries per second over the last `RATE_WINDOW` seconds
+    pub fn rate(&self) -> f32 {
+        self.delivery_times.len() as f32 / RATE_WINDOW
+    }
+
+    /// Records a payload arriving at `now`, error payloads having no value.
+    /// The goal is completed once enough have been delivered while the rate
+    /// is high enough, and stays completed from then on.
+    pub fn record(&mut self, value: Option<&Value>, now: f32) {
+        if value != Some(&self.target) {
+            self.rejects += 1;
+            return;
+        }
+
+        self.delivered += 1;
+        self.delivery_times.push_back(now);
+        self.forget_old_deliveries(now);
+
+        let rate_met = self.min_rate.is_none_or(|min_rate| self.rate() >= min_rate);
+        if self.delivered >= self.quantity && rate_met {
+            self.completed = true;
+        }
+    }
+
+    fn forget_old_deliveries(&mut self, now: f32) {
+        while self
+            .delivery_times
+            .front()
+            .is_some_and(|time| now - time > RATE_WINDOW)
+        {
+            self.delivery_times.pop_front();
+        }
+    }
+
+    fn progress_text(&self) -> String {
+        let mut text = format!(
+            "{} {}/{}",
+            self.target.source_text(),
+            self.delivered().min(self.quantity),
+            self.quantity
+        );
+        if let Some(min_rate) = self.min_rate {
+            text += &format!("\n{:.1}/s (>= {min_rate:.1})", self.rate());
+        }
+        if self.rejects() > 0 {
+            text += &format!("\n{} rejected", self.rejects());
+        }
+        text
+    }
+}
+
+fn record_deliveries(
+    mut sunk: EventReader<PayloadSunkEvent>,
+    mut goals: Query<&mut Goal>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_secs();
+
+    for event in sunk.read() {
+        if let Ok(mut goal) = goals.get_mut(event.sink) {
+            goal.record(event.value.as_ref(), now);
+        }
+    }
+
+    for mut goal in &mut goals {
+        goal.forget_old_deliveries(now);
+    }
+}
+
+/// Fires `GoalCompleted` when the last goal is met.  Placing another goal
+/// afterwards means it will fire again once that one is met too.
+fn check_goals(
+    goals: Query<&Goal>,
+    mut completed: EventWriter<GoalCompleted>,
+    mut all_met: Local<bool>,
+) {
+    let now_all_met = !goals.is_empty() && goals.iter().all(Goal::is_completed);
+
+    if now_all_met && !*all_met {
+        completed.write(GoalCompleted);
+    }
+    *all_met = now_all_met;
+}
+
+fn spawn_goal_labels(
+    mut commands: Commands,
+    new_goals: Query<(Entity, &TilePos), Added<Goal>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (entity, tile_pos) in new_goals {
+        let tile_center = base.center_in_world(tile_pos);
+
+        commands.spawn((
+            Name::new("Goal Progress"),
+            Text2d::default(),
+            TextFont::from_font_size(10.0),
+            Transform::from_translation(
+                (tile_center + Vec2::new(0.0, base.tile_size.y)).extend(4.0),
+            ),
+            GoalLabel(entity),
+        ));
+    }
+}
+
+fn update_goal_labels(
+    goals: Query<&Goal>,
+    labels: Query<(&GoalLabel, &mut Text2d, &mut TextColor)>,
+) {
+    for (label, mut text, mut color) in labels {
+        if let Ok(goal) = goals.get(label.0) {
+            let new_text = goal.progress_text();
+            if text.0 != new_text {
+                text.0 = new_text;
+            }
+            color.0 = if goal.is_completed() {
+                Color::srgb(0.4, 1.0, 0.4)
+            } else {
+                Color::WHITE
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod goal_test {
+    use super::*;
+
+    #[test]
+    fn counts_deliveries_and_rejects() {
+        let mut goal = Goal::new(Value::Int(42), 2, None);
+
+        goal.record(Some(&Value::Int(42)), 0.0);
+        goal.record(Some(&Value::Int(41)), 1.0);
+        goal.record(None, 2.0);
+        assert_eq!(goal.delivered(), 1);
+        assert_eq!(goal.rejects(), 2);
+        assert!(!goal.is_completed());
+
+        goal.record(Some(&Value::Int(42)), 3.0);
+        assert!(goal.is_completed());
+    }
+
+    #[test]
+    fn needs_sustained_rate() {
+        let mut goal = Goal::new(Value::Int(1), 3, Some(1.0));
+
+        // Slowly delivering enough isn't enough
+        for i in 0..5 {
+            goal.record(Some(&Value::Int(1)), i as f32 * 20.0);
+        }
+        assert!(!goal.is_completed());
+
+        // One a second for ten seconds is
+        for i in 0..10 {
+            goal.record(Some(&Value::Int(1)), 100.0 + i as f32);
+        }
+        assert!(goal.is_completed());
+    }
+}
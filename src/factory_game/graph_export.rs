@@ -0,0 +1,242 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::input::egui_wants_any_input;
+use serde::Serialize;
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        analysis::{Graph, GraphTile},
+        chunks::ChunkedTileStorage,
+    },
+    helpers::user_data_path,
+};
+
+pub fn graph_export_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        build_factory_graph
+            .pipe(save_factory_graph)
+            .run_if(input_just_pressed(KeyCode::KeyX))
+            .run_if(not(egui_wants_any_input))
+            .run_if(in_state(GameState::FactoryGame)),
+    );
+}
+
+/// Where in the player's data directory the graph is exported to, as
+/// `factory.dot` and `factory.json`
+const EXPORT_DIR: &str = "exports";
+
+/// The factory's conveyor topology.  Tiles are nodes, except for straight
+/// runs of belt, ones that neither merge nor split, which become the edges
+/// between them.
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct FactoryGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphNode {
+    pub id: usize,
+    /// The tile's name, such as "Generator" or "Conveyor Belt"
+    pub kind: String,
+    pub x: u32,
+    pub y: u32,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    /// How many belts the edge stands for
+    pub belts: u32,
+}
+
+impl FactoryGraph {
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph factory {\n    node [shape=box];\n");
+        for node in &self.nodes {
+            dot += &format!(
+                "    n{} [label=\"{} ({}, {})\"];\n",
+                node.id,
+                node.kind.replace('"', "\\\""),
+                node.x,
+                node.y
+            );
+        }
+        for edge in &self.edges {
+            dot += &format!("    n{} -> n{}", edge.from, edge.to);
+            match edge.belts {
+                0 => {}
+                1 => dot += " [label=\"1 belt\"]",
+                belts => dot += &format!(" [label=\"{belts} belts\"]"),
+            }
+            dot += ";\n";
+        }
+        dot += "}\n";
+        dot
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    fn save(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join("factory.dot"), self.to_dot())?;
+        fs::write(dir.join("factory.json"), self.to_json()?)
+    }
+}
+
+/// Builds the graph of what's on the base layer.  Run it with
+/// `World::run_system_cached` to export without the game running.
+pub fn build_factory_graph(
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
+    graph_tiles: Query<GraphTile>,
+    tiles: Query<(&TilePos, Option<&Name>)>,
+) -> FactoryGraph {
+    let (tile_storage, map_size, map_type) = base.into_inner();
+    let edges = Graph::new(tile_storage, map_size, map_type, &graph_tiles).edges();
+
+    let mut incoming: HashMap<Entity, usize> = HashMap::new();
+    for next in edges.values().flatten() {
+        *incoming.entry(*next).or_default() += 1;
+    }
+    let in_run = |entity: &Entity| {
+        graph_tiles.get(*entity).is_ok_and(|tile| tile.is_belt())
+            && incoming.get(entity) == Some(&1)
+            && edges.get(entity).is_some_and(|next| next.len() == 1)
+    };
+
+    let position = |entity: &Entity| {
+        tiles
+            .get(*entity)
+            .map_or((0, 0), |(tile_pos, _)| (tile_pos.x, tile_pos.y))
+    };
+    let mut sources: Vec<Entity> = edges.keys().copied().filter(|e| !in_run(e)).collect();
+    sources.sort_by_key(position);
+
+    // Belts going round a loop with nothing else on it have nowhere to start
+    // from, so one of them is kept as a node
+    let mut runs: Vec<Entity> = edges.keys().copied().filter(in_run).collect();
+    runs.sort_by_key(position);
+    let mut reached: HashSet<Entity> = HashSet::new();
+    let mut graph_edges = Vec::new();
+    let mut walk_from = |source: Entity, reached: &mut HashSet<Entity>| {
+        for &first in &edges[&source] {
+            let (mut to, mut belts) = (first, 0);
+            while in_run(&to) && to != source {
+                reached.insert(to);
+                belts += 1;
+                to = edges[&to][0];
+            }
+            graph_edges.push((source, to, belts));
+        }
+    };
+    for &source in &sources {
+        walk_from(source, &mut reached);
+    }
+    for belt in runs {
+        if reached.insert(belt) {
+            sources.push(belt);
+            walk_from(belt, &mut reached);
+        }
+    }
+
+    let ids: HashMap<Entity, usize> = sources
+        .iter()
+        .enumerate()
+        .map(|(id, entity)| (*entity, id))
+        .collect();
+    FactoryGraph {
+        nodes: sources
+            .iter()
+            .map(|entity| {
+                let (x, y) = position(entity);
+                let kind = tiles
+                    .get(*entity)
+                    .ok()
+                    .and_then(|(_, name)| name)
+                    .map_or("Tile".to_string(), |name| name.to_string());
+                GraphNode {
+                    id: ids[entity],
+                    kind,
+                    x,
+                    y,
+                }
+            })
+            .collect(),
+        edges: graph_edges
+            .into_iter()
+            .map(|(from, to, belts)| GraphEdge {
+                from: ids[&from],
+                to: ids[&to],
+                belts,
+            })
+            .collect(),
+    }
+}
+
+fn save_factory_graph(graph: In<FactoryGraph>) {
+    let dir = user_data_path(EXPORT_DIR);
+    match graph.save(&dir) {
+        Ok(()) => info!(
+            "Exported {} nodes and {} edges to {}",
+            graph.nodes.len(),
+            graph.edges.len(),
+            dir.display()
+        ),
+        Err(e) => warn!("Can't export the factory graph: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod graph_export_test {
+    use super::*;
+
+    #[test]
+    fn dot_labels_nodes_and_belt_runs() {
+        let node = |id, kind: &str, x| GraphNode {
+            id,
+            kind: kind.to_string(),
+            x,
+            y: 0,
+        };
+        let graph = FactoryGraph {
+            nodes: vec![node(0, "Generator", 0), node(1, "Sink", 4)],
+            edges: vec![
+                GraphEdge {
+                    from: 0,
+                    to: 1,
+                    belts: 3,
+                },
+                GraphEdge {
+                    from: 1,
+                    to: 0,
+                    belts: 0,
+                },
+            ],
+        };
+
+        assert_eq!(
+            graph.to_dot(),
+            "digraph factory {
+    node [shape=box];
+    n0 [label=\"Generator (0, 0)\"];
+    n1 [label=\"Sink (4, 0)\"];
+    n0 -> n1 [label=\"3 belts\"];
+    n1 -> n0;
+}
+"
+        );
+    }
+}
@@ -1,19 +1,15 @@
-use bevy::ecs::query::{QueryData, QueryFilter, ROQueryItem};
 use bevy::prelude::*;
 use bevy_ecs_tilemap::helpers::square_grid::neighbors::{Neighbors, SquareDirection};
 use bevy_ecs_tilemap::prelude::*;
 
-pub fn opposite(d: SquareDirection) -> SquareDirection {
-    use SquareDirection::*;
-    match d {
-        East => West,
-        North => South,
-        West => East,
-        South => North,
-        _ => panic!(),
-    }
-}
+use crate::factory_game::{
+    chunks::ChunkedTileStorage,
+    conveyor::{Conveyor, cell_conveyor},
+    footprint::Footprint,
+};
 
+/// A way out of a tile.  Square maps use the four sides; hex maps, whose
+/// tiles are laid out in rows, use East, West and the four diagonals.
 #[derive(PartialEq, Eq, Reflect, Clone, Copy, Debug, Default)]
 pub enum ConveyorDirection {
     #[default]
@@ -21,102 +17,135 @@ pub enum ConveyorDirection {
     South,
     East,
     West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
 }
 
 impl From<ConveyorDirection> for u8 {
     fn from(value: ConveyorDirection) -> Self {
-        match value {
-            ConveyorDirection::North => 1,
-            ConveyorDirection::East => 2,
-            ConveyorDirection::South => 4,
-            ConveyorDirection::West => 8,
-        }
+        1 << value.index()
     }
 }
 
 impl From<usize> for ConveyorDirection {
     fn from(value: usize) -> Self {
-        use ConveyorDirection::*;
-        match value {
-            0 => North,
-            1 => East,
-            2 => South,
-            3 => West,
-            _ => panic!(),
-        }
+        CONVEYOR_DIRECTIONS[value]
     }
 }
 
-pub const CONVEYOR_DIRECTIONS: [ConveyorDirection; 4] = [
+/// Every direction, clockwise from North
+pub const CONVEYOR_DIRECTIONS: [ConveyorDirection; 8] = [
+    ConveyorDirection::North,
+    ConveyorDirection::NorthEast,
+    ConveyorDirection::East,
+    ConveyorDirection::SouthEast,
+    ConveyorDirection::South,
+    ConveyorDirection::SouthWest,
+    ConveyorDirection::West,
+    ConveyorDirection::NorthWest,
+];
+
+const SQUARE_DIRECTIONS: [ConveyorDirection; 4] = [
     ConveyorDirection::North,
     ConveyorDirection::East,
     ConveyorDirection::South,
     ConveyorDirection::West,
 ];
 
+const HEX_DIRECTIONS: [ConveyorDirection; 6] = [
+    ConveyorDirection::NorthEast,
+    ConveyorDirection::East,
+    ConveyorDirection::SouthEast,
+    ConveyorDirection::SouthWest,
+    ConveyorDirection::West,
+    ConveyorDirection::NorthWest,
+];
+
+/// The directions out of a tile on a map of type `map_type`, clockwise.
+/// Hex maps must use `HexCoordSystem::Row`.
+pub fn conveyor_directions(map_type: &TilemapType) -> &'static [ConveyorDirection] {
+    match map_type {
+        TilemapType::Hexagon(_) => &HEX_DIRECTIONS,
+        _ => &SQUARE_DIRECTIONS,
+    }
+}
+
 impl ConveyorDirection {
     pub fn opposite(&self) -> Self {
-        use ConveyorDirection::*;
-        match self {
-            East => West,
-            North => South,
-            West => East,
-            South => North,
-        }
+        CONVEYOR_DIRECTIONS[(self.index() + 4) % 8]
     }
 
-    pub fn left(&self) -> Self {
-        use ConveyorDirection::*;
-        match self {
-            North => West,
-            South => East,
-            East => North,
-            West => South,
-        }
+    /// Whether this direction is on the left of something facing `facing`.
+    /// Straight ahead and straight behind are on neither side.
+    pub fn is_left_of(&self, facing: ConveyorDirection) -> bool {
+        (self.index() + 8 - facing.index()) % 8 > 4
     }
 
-    pub fn right(&self) -> Self {
-        use ConveyorDirection::*;
-        match self {
-            North => East,
-            South => West,
-            East => South,
-            West => North,
-        }
+    /// Whether this direction is on the right of something facing `facing`
+    pub fn is_right_of(&self, facing: ConveyorDirection) -> bool {
+        (1..4).contains(&((self.index() + 8 - facing.index()) % 8))
     }
 
+    /// Position in `CONVEYOR_DIRECTIONS`
     pub fn index(&self) -> usize {
         use ConveyorDirection::*;
         match self {
             North => 0,
-            East => 1,
-            South => 2,
-            West => 3,
+            NorthEast => 1,
+            East => 2,
+            SouthEast => 3,
+            South => 4,
+            SouthWest => 5,
+            West => 6,
+            NorthWest => 7,
         }
     }
 
-    pub fn next(&self) -> ConveyorDirection {
+    /// The next direction clockwise on a map of type `map_type`
+    pub fn next(&self, map_type: &TilemapType) -> ConveyorDirection {
+        let directions = conveyor_directions(map_type);
+        directions
+            .iter()
+            .position(|d| d == self)
+            .map_or(directions[0], |i| directions[(i + 1) % directions.len()])
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        self.index() % 2 == 1
+    }
+
+    /// Angle, in radians, of this direction measured counter-clockwise from
+    /// East
+    pub fn angle(&self) -> f32 {
         use ConveyorDirection::*;
+        use std::f32::consts::{FRAC_PI_2, FRAC_PI_3, PI};
         match self {
-            North => East,
-            East => South,
-            South => West,
-            West => North,
+            East => 0.0,
+            NorthEast => FRAC_PI_3,
+            North => FRAC_PI_2,
+            NorthWest => PI - FRAC_PI_3,
+            West => PI,
+            SouthWest => FRAC_PI_3 - PI,
+            South => -FRAC_PI_2,
+            SouthEast => -FRAC_PI_3,
         }
     }
 
     /// Assuming "East" is normal, figure out the flips to rotate in other
-    /// direction
+    /// direction.  Flips can't turn East into a diagonal, so for those
+    /// "North East" is normal instead.
     pub fn tile_flip(&self) -> TileFlip {
         use ConveyorDirection::*;
         match self {
-            East => TileFlip::default(),
+            East | NorthEast => TileFlip::default(),
             North => TileFlip {
                 d: true,
                 y: true,
                 ..default()
             },
-            West => TileFlip {
+            West | NorthWest => TileFlip {
                 x: true,
                 ..default()
             },
@@ -124,30 +153,71 @@ impl ConveyorDirection {
                 d: true,
                 ..default()
             },
+            SouthEast => TileFlip {
+                y: true,
+                ..default()
+            },
+            SouthWest => TileFlip {
+                x: true,
+                y: true,
+                ..default()
+            },
+        }
+    }
+
+    /// The neighbouring tile in this direction, if it's on the map
+    pub fn offset(&self, tile_pos: &TilePos, map_size: &TilemapSize) -> Option<TilePos> {
+        use ConveyorDirection::*;
+        // Hex maps are in axial coordinates, where the diagonals aren't
+        // symmetrical
+        let (x, y) = match self {
+            North | NorthEast => (0, 1),
+            South | SouthWest => (0, -1),
+            East => (1, 0),
+            West => (-1, 0),
+            NorthWest => (-1, 1),
+            SouthEast => (1, -1),
+        };
+        let pos = TilePos {
+            x: tile_pos.x.checked_add_signed(x)?,
+            y: tile_pos.y.checked_add_signed(y)?,
+        };
+        pos.within_map_bounds(map_size).then_some(pos)
+    }
+
+    /// From the centre of a tile to the middle of its edge in this direction
+    pub fn offset_in_world(&self, grid_size: &TilemapGridSize) -> Vec2 {
+        use ConveyorDirection::*;
+        let half = Vec2::new(grid_size.x, grid_size.y) / 2.0;
+        // Hex rows are three quarters of the grid height apart, and each is
+        // shifted half a tile along from the one below
+        let diagonal = Vec2::new(half.x / 2.0, half.y * 0.75);
+        match self {
+            North => Vec2::new(0.0, half.y),
+            South => Vec2::new(0.0, -half.y),
+            East => Vec2::new(half.x, 0.0),
+            West => Vec2::new(-half.x, 0.0),
+            NorthEast => diagonal,
+            NorthWest => Vec2::new(-diagonal.x, diagonal.y),
+            SouthEast => Vec2::new(diagonal.x, -diagonal.y),
+            SouthWest => -diagonal,
         }
     }
 }
 
 impl From<ConveyorDirection> for SquareDirection {
     fn from(value: ConveyorDirection) -> Self {
-        match value {
-            ConveyorDirection::North => SquareDirection::North,
-            ConveyorDirection::South => SquareDirection::South,
-            ConveyorDirection::East => SquareDirection::East,
-            ConveyorDirection::West => SquareDirection::West,
-        }
+        // Square directions go anticlockwise from East
+        SquareDirection::from(2 - value.index() as isize)
     }
 }
 
 impl From<SquareDirection> for ConveyorDirection {
     fn from(value: SquareDirection) -> Self {
-        match value {
-            SquareDirection::East => ConveyorDirection::East,
-            SquareDirection::North => ConveyorDirection::North,
-            SquareDirection::West => ConveyorDirection::West,
-            SquareDirection::South => ConveyorDirection::South,
-            _ => panic!(),
-        }
+        CONVEYOR_DIRECTIONS
+            .into_iter()
+            .find(|d| SquareDirection::from(*d) == value)
+            .unwrap()
     }
 }
 
@@ -160,19 +230,13 @@ impl ConveyorDirections {
         Self(direction.into())
     }
 
-    pub fn all() -> Self {
-        use ConveyorDirection::*;
-
-        let n: u8 = North.into();
-        let e: u8 = East.into();
-        let s: u8 = South.into();
-        let w: u8 = West.into();
-
-        Self(n | e | s | w)
+    /// Every direction out of a tile on a map of type `map_type`
+    pub fn all(map_type: &TilemapType) -> Self {
+        Self::from(conveyor_directions(map_type).iter().copied())
     }
 
-    pub fn all_except(except: ConveyorDirections) -> Self {
-        let all = Self::all().0;
+    pub fn all_except(map_type: &TilemapType, except: ConveyorDirections) -> Self {
+        let all = Self::all(map_type).0;
         let except: u8 = except.0;
 
         Self(all & !except)
@@ -264,14 +328,93 @@ pub fn make_east_relative<T>(neighbors: Neighbors<T>, direction: SquareDirection
     }
 }
 
-pub fn get_neighbors_from_query<'a, D: QueryData, F: QueryFilter>(
-    tile_storage: &TileStorage,
+/// The positions next to `tile_pos`, in the directions a map of type
+/// `map_type` has
+pub fn get_neighboring_positions(
     tile_pos: &TilePos,
     map_size: &TilemapSize,
-    query: &'a Query<D, F>,
-) -> Neighbors<ROQueryItem<'a, D>> {
-    let neighbor_positions = Neighbors::get_square_neighboring_positions(tile_pos, map_size, false);
-    let neighbor_entities = neighbor_positions.entities(tile_storage);
+    map_type: &TilemapType,
+) -> Neighbors<TilePos> {
+    let directions = conveyor_directions(map_type);
+    Neighbors::from_directional_closure(|dir| {
+        let direction = ConveyorDirection::from(dir);
+        directions
+            .contains(&direction)
+            .then(|| direction.offset(tile_pos, map_size))
+            .flatten()
+    })
+}
+
+/// The `Conveyor` next to `tile_pos` in each direction, as seen from it
+pub fn get_neighbor_conveyors(
+    tile_storage: &ChunkedTileStorage,
+    tile_pos: &TilePos,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    conveyors: &Query<(&Conveyor, Option<&Footprint>, &TilePos)>,
+) -> Neighbors<Conveyor> {
+    get_neighboring_positions(tile_pos, map_size, map_type).and_then_ref(|pos| {
+        let neighbor = conveyors.get(tile_storage.get(pos)?).ok()?;
+        Some(cell_conveyor(neighbor, pos))
+    })
+}
 
-    neighbor_entities.and_then_ref(|n| query.get(*n).ok())
+#[cfg(test)]
+mod helpers_test {
+    use super::*;
+    use ConveyorDirection::*;
+
+    #[test]
+    fn hex_directions_turn_and_pair_up() {
+        let hex = TilemapType::Hexagon(HexCoordSystem::Row);
+        let map_size = TilemapSize { x: 10, y: 10 };
+        let pos = TilePos { x: 5, y: 5 };
+
+        let mut direction = East;
+        for expected in [SouthEast, SouthWest, West, NorthWest, NorthEast, East] {
+            direction = direction.next(&hex);
+            assert_eq!(direction, expected);
+
+            let neighbor = direction.offset(&pos, &map_size).unwrap();
+            assert_eq!(direction.opposite().offset(&neighbor, &map_size), Some(pos));
+            assert_eq!(
+                ConveyorDirection::from(SquareDirection::from(direction)),
+                direction
+            );
+        }
+        assert_eq!(East.next(&TilemapType::Square), South);
+    }
+
+    #[test]
+    fn all_directions_depend_on_the_map() {
+        let hex = TilemapType::Hexagon(HexCoordSystem::Row);
+        let square = ConveyorDirections::all(&TilemapType::Square);
+
+        assert_eq!(
+            square.iter().collect::<Vec<_>>(),
+            [North, East, South, West]
+        );
+        assert_eq!(ConveyorDirections::all(&hex).iter().count(), 6);
+        assert!(!ConveyorDirections::all(&hex).is_set(North));
+        assert_eq!(
+            ConveyorDirections::all_except(&TilemapType::Square, ConveyorDirections::new(West))
+                .iter()
+                .collect::<Vec<_>>(),
+            [North, East, South]
+        );
+    }
+
+    #[test]
+    fn operands_come_from_either_side() {
+        assert!(North.is_left_of(East));
+        assert!(South.is_right_of(East));
+        for side in [NorthEast, NorthWest] {
+            assert!(side.is_left_of(East) && !side.is_right_of(East));
+        }
+        for side in [SouthEast, SouthWest] {
+            assert!(side.is_right_of(East) && !side.is_left_of(East));
+        }
+        assert!(!West.is_left_of(East) && !West.is_right_of(East));
+        assert!(!East.is_left_of(East) && !East.is_right_of(East));
+    }
 }
@@ -0,0 +1,311 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    factory_game::{
+        BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
+        conveyor::Conveyor,
+        helpers::{ConveyorDirection, ConveyorDirections},
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
+        payload_handler::{AddPayloadHandler, PayloadHandler},
+        payloads::{Payload, RequestPayloadTransferEvent},
+    },
+    helpers::TilemapQuery,
+    sprite_sheet::{GameSprite, SpriteSheet},
+};
+
+pub fn inserter_plugin(app: &mut App) {
+    app.register_place_tile_event::<PlaceInserterEvent>()
+        .add_payload_handler::<Inserter>()
+        .add_systems(
+            Update,
+            (
+                update_inserter_tiles.in_set(ConveyorSystems::TileUpdater),
+                update_inserters.in_set(ConveyorSystems::TransportLogic),
+                update_inserter_transforms.in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+const DEFAULT_TIME_PER_TRANSFER: f32 = 1.0;
+
+pub struct InserterTool(ConveyorDirection);
+
+impl Default for InserterTool {
+    fn default() -> Self {
+        InserterTool(ConveyorDirection::East)
+    }
+}
+
+impl Tool for InserterTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (GameSprite::InserterArm, self.0.tile_flip())
+    }
+
+    fn next_variant(&mut self, map_type: &TilemapType) {
+        self.0 = self.0.next(map_type);
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceInserterEvent(TilePos::default(), self.0, DEFAULT_TIME_PER_TRANSFER).cost()
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(PlaceInserterEvent(
+            *tile_pos,
+            self.0,
+            DEFAULT_TIME_PER_TRANSFER,
+        ));
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct PlaceInserterEvent(pub TilePos, pub ConveyorDirection, pub f32);
+
+impl PlaceTileEvent for PlaceInserterEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    fn cost(&self) -> u32 {
+        8
+    }
+
+    fn configure_new_entity(&self, mut commands: EntityCommands) {
+        commands.insert((inserter_bundle(self.1, self.2), Name::new("Inserter")));
+    }
+}
+
+/// An inserter picks up payloads from the tile behind it, or takes those
+/// handed to it, and swings them over to the tile in front of it.  It doesn't
+/// care what sort of tile is at either end, so long as that tile is a
+/// `PayloadHandler`.
+#[derive(Component, Debug, Reflect)]
+#[require(InserterArms)]
+pub struct Inserter {
+    direction: ConveyorDirection,
+    /// Seconds for a full swing from pickup to drop and back again
+    pub time_per_transfer: f32,
+    held: Option<Entity>,
+    /// Position of the arm: 0 is at the pickup side, 1 is at the drop side
+    mu: f32,
+}
+
+#[derive(Component, Default)]
+#[relationship_target(relationship = InserterArm, linked_spawn)]
+pub struct InserterArms(Vec<Entity>);
+
+/// Mark the sprite showing the inserter's arm so it is despawned along with
+/// the inserter
+#[derive(Component)]
+#[relationship(relationship_target = InserterArms)]
+pub struct InserterArm(Entity);
+
+impl PayloadHandler for Inserter {
+    fn try_transfer(
+        &mut self,
+        _: &Conveyor,
+        request: &RequestPayloadTransferEvent,
+    ) -> Option<Entity> {
+        if request.direction == self.direction && self.held.is_none() && self.mu == 0.0 {
+            self.held = Some(request.payload);
+            return self.held;
+        }
+        None
+    }
+
+    fn remove_payload(&mut self, payload: Entity) {
+        if self.held == Some(payload) {
+            self.held = None;
+        }
+    }
+
+    fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
+        self.held.into_iter()
+    }
+}
+
+impl Inserter {
+    pub fn new(direction: ConveyorDirection, time_per_transfer: f32) -> Self {
+        Inserter {
+            direction,
+            time_per_transfer,
+            held: None,
+            mu: 0.0,
+        }
+    }
+
+    /// Moves the arm towards the drop side while holding something, and back
+    /// to the pickup side otherwise.  Returns the payload once it is ready to
+    /// be dropped.
+    pub fn update(&mut self, t: f32) -> Option<Entity> {
+        let step = 2.0 * t / self.time_per_transfer.max(f32::EPSILON);
+
+        if self.held.is_some() {
+            self.mu = (self.mu + step).min(1.0);
+        } else {
+            self.mu = (self.mu - step).max(0.0);
+        }
+
+        if self.mu == 1.0 { self.held } else { None }
+    }
+
+    /// Angle of the arm, swinging clockwise from the pickup side to
+    /// the drop side.
+    fn arm_angle(&self) -> f32 {
+        self.direction.opposite().angle() - PI * self.mu
+    }
+}
+
+pub fn inserter_bundle(direction: ConveyorDirection, time_per_transfer: f32) -> impl Bundle {
+    let mut conveyor = Conveyor::from(direction);
+    conveyor.set_inputs(ConveyorDirections::new(direction.opposite()));
+
+    (Inserter::new(direction, time_per_transfer), conveyor)
+}
+
+fn update_inserters(
+    inserters: Query<(Entity, &mut Inserter, &TilePos)>,
+    time: Res<Time>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
+    mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
+) {
+    let (tile_storage, map_size) = base.into_inner();
+    let t = time.delta_secs();
+
+    for (source, mut inserter, tile_pos) in inserters {
+        if let Some(payload) = inserter.update(t) {
+            let destination_pos = inserter.direction.offset(tile_pos, map_size);
+            if let Some(destination_pos) = destination_pos
+                && let Some(destination) = tile_storage.get(&destination_pos)
+            {
+                send_payloads.write(RequestPayloadTransferEvent {
+                    payload,
+                    source,
+                    destination,
+                    destination_pos,
+                    direction: inserter.direction,
+                });
+            }
+        }
+    }
+}
+
+/// Takes the payload offered by the tile behind each empty inserter whose arm
+/// is back at the pickup side.  The tile gives it up straight away, as if it
+/// had been transferred.
+pub fn pick_up_payloads<T: PayloadHandler>(
+    inserters: Query<(&mut Inserter, &TilePos)>,
+    mut handlers: Query<&mut T, Without<Inserter>>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
+) {
+    let (tile_storage, map_size) = base.into_inner();
+
+    for (mut inserter, tile_pos) in inserters {
+        if inserter.held.is_some() || inserter.mu > 0.0 {
+            continue;
+        }
+
+        let Some(mut handler) = inserter
+            .direction
+            .opposite()
+            .offset(tile_pos, map_size)
+            .and_then(|pos| tile_storage.get(&pos))
+            .and_then(|entity| handlers.get_mut(entity).ok())
+        else {
+            continue;
+        };
+
+        if let Some(payload) = handler.payload_to_pick_up() {
+            handler.remove_payload(payload);
+            inserter.held = Some(payload);
+        }
+    }
+}
+
+fn update_inserter_tiles(
+    mut commands: Commands,
+    new_inserters: Query<(Entity, &TilePos, &Inserter), Without<TileTextureIndex>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+    sprite_sheet: Res<SpriteSheet>,
+) {
+    for (e, tile_pos, inserter) in new_inserters {
+        let tile_center = base.center_in_world(tile_pos);
+
+        commands.spawn((
+            Name::new("Inserter Arm"),
+            sprite_sheet.sprite(GameSprite::InserterArm),
+            Transform::from_translation(tile_center.extend(2.0))
+                .with_rotation(Quat::from_rotation_z(inserter.arm_angle())),
+            InserterArm(e),
+        ));
+
+        commands.entity(e).insert_if_new(TileBundle {
+            tilemap_id: TilemapId(base.entity),
+            texture_index: GameSprite::InserterBase.tile_texture_index(),
+            flip: inserter.direction.tile_flip(),
+            ..default()
+        });
+    }
+}
+
+fn update_inserter_transforms(
+    inserters: Query<(&TilePos, &Inserter)>,
+    arms: Query<(&InserterArm, &mut Transform), Without<Payload>>,
+    mut payloads: Query<&mut Transform, With<Payload>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (arm, mut transform) in arms {
+        if let Ok((_, inserter)) = inserters.get(arm.0) {
+            transform.rotation = Quat::from_rotation_z(inserter.arm_angle());
+        }
+    }
+
+    for (tile_pos, inserter) in inserters {
+        let tile_center = base.center_in_world(tile_pos);
+        let rotation = Quat::from_rotation_z(inserter.arm_angle());
+
+        if let Some(held) = inserter.held
+            && let Ok(mut transform) = payloads.get_mut(held)
+        {
+            let reach = rotation * Vec3::new(base.tile_size.x / 2.0, 0.0, 0.0);
+            *transform = Transform::from_translation(tile_center.extend(3.0) + reach);
+        }
+    }
+}
+
+#[cfg(test)]
+mod inserter_test {
+    use super::*;
+    use ConveyorDirection::*;
+
+    #[test]
+    fn swings_over_and_back() {
+        let mut inserter = Inserter::new(East, 1.0);
+        let e = Entity::from_raw(1);
+
+        inserter.held = Some(e);
+        assert_eq!(inserter.update(0.25), None);
+        assert_eq!(inserter.mu, 0.5);
+        assert_eq!(inserter.update(0.25), Some(e));
+
+        inserter.remove_payload(e);
+        inserter.update(0.25);
+        assert_eq!(inserter.mu, 0.5);
+        inserter.update(0.25);
+        assert_eq!(inserter.mu, 0.0);
+    }
+
+    #[test]
+    fn arm_points_at_pickup_then_drop() {
+        let mut inserter = Inserter::new(North, 1.0);
+        assert_eq!(inserter.arm_angle(), South.angle());
+
+        inserter.mu = 1.0;
+        let drop = Quat::from_rotation_z(inserter.arm_angle()) * Vec3::X;
+        assert!(drop.abs_diff_eq(Vec3::Y, 1e-5));
+    }
+}
@@ -0,0 +1,88 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        chunks::ChunkedTileStorage,
+        inserter::Inserter,
+        interaction::{HoveredTile, Tools},
+    },
+};
+
+pub fn inserter_panel_plugin(app: &mut App) {
+    app.init_resource::<InserterPanel>()
+        .add_systems(
+            Update,
+            select_inserter
+                .run_if(input_just_pressed(MouseButton::Left))
+                .run_if(not(egui_wants_any_input))
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_inserter_panel.run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_inserter_panel);
+}
+
+/// Which inserter is being configured
+#[derive(Resource, Default)]
+struct InserterPanel {
+    inserter: Option<Entity>,
+}
+
+/// Clicking on an inserter with no tool selected opens its panel
+fn select_inserter(
+    tools: Res<Tools>,
+    hovered_tile: Single<&TilePos, With<HoveredTile>>,
+    tile_storage: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    inserters: Query<(), With<Inserter>>,
+    mut panel: ResMut<InserterPanel>,
+) {
+    if tools.current_tool().is_some() {
+        return;
+    }
+
+    panel.inserter = tile_storage
+        .get(*hovered_tile)
+        .filter(|entity| inserters.contains(*entity));
+}
+
+fn close_inserter_panel(mut panel: ResMut<InserterPanel>) {
+    panel.inserter = None;
+}
+
+fn show_inserter_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<InserterPanel>,
+    mut inserters: Query<(&mut Inserter, &TilePos)>,
+) -> Result {
+    let Some(entity) = panel.inserter else {
+        return Ok(());
+    };
+    let Ok((mut inserter, tile_pos)) = inserters.get_mut(entity) else {
+        // The inserter has been cleared away
+        panel.inserter = None;
+        return Ok(());
+    };
+
+    let mut open = true;
+    egui::Window::new(format!("Inserter at {}, {}", tile_pos.x, tile_pos.y))
+        .open(&mut open)
+        .resizable(false)
+        .show(contexts.ctx_mut()?, |ui| {
+            ui.add(
+                egui::Slider::new(&mut inserter.time_per_transfer, 0.2..=5.0)
+                    .text("Seconds per transfer"),
+            );
+        });
+
+    if !open {
+        panel.inserter = None;
+    }
+
+    Ok(())
+}
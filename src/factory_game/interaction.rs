@@ -10,9 +10,28 @@ use std::fmt::Debug;
 use crate::{
     GameState,
     factory_game::{
-        BaseLayer, ConveyorSystems, MapConfig, bridge::BridgeTool, conveyor::ConveyorUpdated,
-        conveyor_belts::ConveyorBeltTool, distributor::DistributorTool, generator::GeneratorTool,
-        operators::OperatorsTool, sink::SinkTool,
+        BaseLayer, ConveyorSystems, MapConfig,
+        bridge::BridgeTool,
+        campaign::CurrentLevel,
+        chunks::ChunkedTileStorage,
+        conveyor::ConveyorUpdated,
+        conveyor_belts::ConveyorBeltTool,
+        deposits::Deposits,
+        distributor::DistributorTool,
+        drones::DronePortTool,
+        economy::{Cost, Money},
+        footprint::{Footprint, footprint_cells, footprint_cells_on_map},
+        generator::GeneratorTool,
+        inserter::InserterTool,
+        operators::{CycleOverflowPolicyEvent, OperatorsTool, OverflowPolicy},
+        power::PowerTool,
+        processor::ProcessorTool,
+        research::ResearchSinkTool,
+        signals::SignalTool,
+        sink::SinkTool,
+        storage::StorageTool,
+        tiled_map::TiledMap,
+        unary_operators::UnaryOperatorsTool,
     },
     helpers::TilemapQuery,
     sprite_sheet::{GameSprite, SpriteSheet},
@@ -36,11 +55,14 @@ pub fn interaction_plugin(app: &mut App) {
                     )
                         .chain(),
                     select_tool,
+                    cycle_overflow_policy.run_if(input_just_pressed(KeyCode::KeyO)),
                 )
                     .in_set(ConveyorSystems::TileGenerator)
                     .run_if(not(egui_wants_any_input)),
                 (
-                    update_hovered_tile.run_if(resource_exists_and_changed::<Tools>),
+                    (update_hovered_tile, spawn_hovered_footprint_tiles)
+                        .run_if(resource_exists_and_changed::<Tools>),
+                    update_hovered_footprint_tiles,
                     flash_hovered_tile,
                 )
                     .in_set(ConveyorSystems::TileUpdater),
@@ -106,11 +128,66 @@ fn update_hovered_tile(
     }
 }
 
-fn flash_hovered_tile(q: Option<Single<&mut TileColor, With<HoveredTile>>>, time: Res<Time>) {
-    if let Some(mut color) = q {
-        let bright_pulse = 1.0 + ((time.elapsed_secs() * 5.0).sin() + 1.0) / 2.0;
-        let alpha_pulse = ((time.elapsed_secs() * 10.0).sin() + 1.0) / 2.0;
-        **color = TileColor(Color::hsla(0.0, 0.5, bright_pulse, alpha_pulse));
+/// Tools that place machines bigger than a single tile show the rest of the
+/// machine's footprint with extra tiles that follow the hovered tile around.
+fn spawn_hovered_footprint_tiles(
+    mut commands: Commands,
+    tools: Res<Tools>,
+    existing: Query<Entity, With<HoveredFootprintTile>>,
+    interaction_layer: Single<Entity, With<InteractionLayer>>,
+) {
+    existing.iter().for_each(|e| commands.entity(e).despawn());
+
+    let Some(tool) = tools.current_tool() else {
+        return;
+    };
+
+    for offset in footprint_cells(&TilePos::default(), tool.tool().footprint_size()).skip(1) {
+        commands.spawn((
+            StateScoped(GameState::FactoryGame),
+            Name::new("HoveredFootprintTile"),
+            HoveredFootprintTile(UVec2::new(offset.x, offset.y)),
+            TileBundle {
+                texture_index: GameSprite::BlankSquare.tile_texture_index(),
+                tilemap_id: TilemapId(*interaction_layer),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_hovered_footprint_tiles(
+    hovered_tile: Single<(&TilePos, &TileVisible), With<HoveredTile>>,
+    footprint_tiles: Query<
+        (&mut TilePos, &mut TileVisible, &HoveredFootprintTile),
+        Without<HoveredTile>,
+    >,
+    interaction_layer: Single<&TilemapSize, With<InteractionLayer>>,
+) {
+    let (hovered_pos, hovered_visible) = hovered_tile.into_inner();
+
+    for (mut tile_pos, mut visible, offset) in footprint_tiles {
+        let pos = TilePos {
+            x: hovered_pos.x + offset.0.x,
+            y: hovered_pos.y + offset.0.y,
+        };
+        let on_map = pos.within_map_bounds(*interaction_layer);
+        if on_map {
+            *tile_pos = pos;
+        }
+        visible.0 = hovered_visible.0 && on_map;
+    }
+}
+
+#[expect(clippy::type_complexity)]
+fn flash_hovered_tile(
+    q: Query<&mut TileColor, Or<(With<HoveredTile>, With<HoveredFootprintTile>)>>,
+    time: Res<Time>,
+) {
+    let bright_pulse = 1.0 + ((time.elapsed_secs() * 5.0).sin() + 1.0) / 2.0;
+    let alpha_pulse = ((time.elapsed_secs() * 10.0).sin() + 1.0) / 2.0;
+    for mut color in q {
+        *color = TileColor(Color::hsla(0.0, 0.5, bright_pulse, alpha_pulse));
     }
 }
 
@@ -147,11 +224,17 @@ pub struct Tools {
 pub struct ToolEntry {
     slot: u32,
     tool: Box<dyn Tool>,
+    /// Locked tools are shown but can't be selected until researched
+    locked: bool,
 }
 
 impl Tools {
     pub fn add(&mut self, slot: u32, tool: Box<dyn Tool>) {
-        self.tools.push(ToolEntry { slot, tool });
+        self.tools.push(ToolEntry {
+            slot,
+            tool,
+            locked: false,
+        });
         self.tools.sort_by_key(|t| t.slot);
     }
 
@@ -169,13 +252,24 @@ impl Tools {
             .tools
             .iter()
             .enumerate()
-            .find(|(_, tool)| tool.slot == slot)
+            .find(|(_, tool)| tool.slot == slot && !tool.locked)
             .map(|(index, _)| index);
     }
 
-    pub fn next_variant(&mut self) {
+    /// Locks or unlocks every tool, deselecting the current tool if it
+    /// becomes locked.
+    pub fn set_locked(&mut self, is_locked: impl Fn(u32) -> bool) {
+        for tool in &mut self.tools {
+            tool.locked = is_locked(tool.slot);
+        }
+        if self.current_tool().is_some_and(ToolEntry::is_locked) {
+            self.current_tool = None;
+        }
+    }
+
+    pub fn next_variant(&mut self, map_type: &TilemapType) {
         if let Some(current_tool) = self.current_tool {
-            self.tools[current_tool].tool.next_variant();
+            self.tools[current_tool].tool.next_variant(map_type);
         }
     }
 
@@ -196,48 +290,121 @@ impl ToolEntry {
     pub fn tool(&self) -> &dyn Tool {
         self.tool.as_ref()
     }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
 }
 
 pub trait Tool: Sync + Send {
     fn get_sprite_flip(&self) -> (GameSprite, TileFlip);
-    fn next_variant(&mut self) {}
+    /// Switches to the tool's next variant, such as the next way round on
+    /// a map of type `map_type`
+    fn next_variant(&mut self, _map_type: &TilemapType) {}
+
+    /// Size, in tiles, of what this tool places
+    fn footprint_size(&self) -> UVec2 {
+        UVec2::ONE
+    }
+
+    /// Money it costs to place what this tool places.  Tools ask the event
+    /// they trigger, so each cost is only written down once.
+    fn cost(&self) -> u32 {
+        0
+    }
 
     fn execute(&self, commands: Commands, tile_pos: &TilePos);
 }
 
-fn select_tool(mut tools: ResMut<Tools>, mut key_events: EventReader<KeyboardInput>) {
+fn select_tool(
+    mut tools: ResMut<Tools>,
+    mut key_events: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    map_type: Single<&TilemapType, With<BaseLayer>>,
+) {
+    // Holding shift selects from the second bank of slots, 11 - 20, and
+    // control from the third, 21 - 30
+    let bank = if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        10
+    } else if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        20
+    } else {
+        0
+    };
+
     for e in key_events.read() {
         if e.state == ButtonState::Pressed {
             match e.key_code {
                 KeyCode::Backquote => tools.set_no_tool(),
-                KeyCode::Digit1 => tools.set_tool(1),
-                KeyCode::Digit2 => tools.set_tool(2),
-                KeyCode::Digit3 => tools.set_tool(3),
-                KeyCode::Digit4 => tools.set_tool(4),
-                KeyCode::Digit5 => tools.set_tool(5),
-                KeyCode::Digit6 => tools.set_tool(6),
-                KeyCode::Digit7 => tools.set_tool(7),
-                KeyCode::Digit8 => tools.set_tool(8),
-                KeyCode::Digit9 => tools.set_tool(9),
-                KeyCode::Digit0 => tools.set_tool(10),
-                KeyCode::Space => tools.next_variant(),
+                KeyCode::Digit1 => tools.set_tool(bank + 1),
+                KeyCode::Digit2 => tools.set_tool(bank + 2),
+                KeyCode::Digit3 => tools.set_tool(bank + 3),
+                KeyCode::Digit4 => tools.set_tool(bank + 4),
+                KeyCode::Digit5 => tools.set_tool(bank + 5),
+                KeyCode::Digit6 => tools.set_tool(bank + 6),
+                KeyCode::Digit7 => tools.set_tool(bank + 7),
+                KeyCode::Digit8 => tools.set_tool(bank + 8),
+                KeyCode::Digit9 => tools.set_tool(bank + 9),
+                KeyCode::Digit0 => tools.set_tool(bank + 10),
+                KeyCode::Space => tools.next_variant(&map_type),
                 _ => (),
             }
         }
     }
 }
 
-fn setup_tools(mut commands: Commands) {
+/// O changes the factory's policy, and shift-O the override of the operator
+/// under the cursor
+fn cycle_overflow_policy(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    hovered_tile: Single<&TilePos, With<HoveredTile>>,
+    mut overflow_policy: ResMut<OverflowPolicy>,
+) {
+    if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        commands.trigger(CycleOverflowPolicyEvent(**hovered_tile));
+    } else {
+        *overflow_policy = overflow_policy.next();
+    }
+}
+
+fn setup_tools(mut commands: Commands, config: Res<MapConfig>, level: Option<Res<CurrentLevel>>) {
+    let map_type = &config.map_type;
     let mut tools = Tools::default();
 
     tools.add(1, Box::new(ClearTool));
     tools.add(2, Box::new(ConveyorBeltTool::default()));
     tools.add(3, Box::new(GeneratorTool));
-    tools.add(4, Box::new(SinkTool));
+    tools.add(4, Box::new(SinkTool::default()));
     tools.add(5, Box::new(DistributorTool::default()));
     tools.add(6, Box::new(BridgeTool));
-    tools.add(7, Box::new(OperatorsTool::plus()));
-    tools.add(8, Box::new(OperatorsTool::multiply()));
+    tools.add(7, Box::new(OperatorsTool::plus(map_type)));
+    tools.add(8, Box::new(OperatorsTool::multiply(map_type)));
+    tools.add(9, Box::new(InserterTool::default()));
+    tools.add(10, Box::new(StorageTool));
+    tools.add(11, Box::new(OperatorsTool::subtract(map_type)));
+    tools.add(12, Box::new(OperatorsTool::divide(map_type)));
+    tools.add(13, Box::new(OperatorsTool::modulo(map_type)));
+    tools.add(14, Box::new(OperatorsTool::min(map_type)));
+    tools.add(15, Box::new(OperatorsTool::max(map_type)));
+    tools.add(16, Box::new(OperatorsTool::power(map_type)));
+    tools.add(17, Box::new(UnaryOperatorsTool::unary()));
+    tools.add(18, Box::new(UnaryOperatorsTool::add_constant()));
+    tools.add(19, Box::new(UnaryOperatorsTool::multiply_constant()));
+    tools.add(20, Box::new(ResearchSinkTool));
+    tools.add(21, Box::new(SignalTool::wire()));
+    tools.add(22, Box::new(SignalTool::sensor()));
+    tools.add(23, Box::new(SignalTool::condition()));
+    tools.add(24, Box::new(ProcessorTool));
+    tools.add(25, Box::new(DronePortTool::provider()));
+    tools.add(26, Box::new(DronePortTool::requester()));
+    tools.add(27, Box::new(PowerTool::plant()));
+    tools.add(28, Box::new(PowerTool::pole()));
+
+    // Levels only allow some of the tools
+    if let Some(level) = level {
+        tools.tools.retain(|tool| level.allows_tool(tool.slot));
+    }
 
     commands.insert_resource(tools);
 }
@@ -263,7 +430,27 @@ where
 {
     fn tile_pos(&self) -> TilePos;
 
-    fn make_new_entity(&self, mut commands: Commands, storage: &mut TileStorage) -> Option<Entity> {
+    /// Size, in tiles, of the thing being placed.  `tile_pos` is the
+    /// bottom-left corner.
+    fn footprint_size(&self) -> UVec2 {
+        UVec2::ONE
+    }
+
+    /// Money it costs to place, when there is an economy
+    fn cost(&self) -> u32 {
+        0
+    }
+
+    /// Whether it can only be placed on a deposit, when there are deposits
+    fn needs_deposit(&self) -> bool {
+        false
+    }
+
+    fn make_new_entity(
+        &self,
+        mut commands: Commands,
+        storage: &mut ChunkedTileStorage,
+    ) -> Option<Entity> {
         let entity = commands
             .spawn((
                 StateScoped(GameState::FactoryGame),
@@ -272,30 +459,101 @@ where
             ))
             .id();
 
-        storage.set(&self.tile_pos(), entity);
+        for pos in footprint_cells(&self.tile_pos(), self.footprint_size()) {
+            storage.set(&pos, entity);
+        }
 
         Some(entity)
     }
 
     #[expect(unused_variables)]
     fn configure_new_entity(&self, commands: EntityCommands) {}
+
+    /// Finishes configuring the new entity for a map of type `map_type`,
+    /// for things with a side for every direction out of their tile
+    #[expect(unused_variables)]
+    fn configure_for_map(&self, commands: EntityCommands, map_type: &TilemapType) {}
 }
 
+/// Tiles placed by a level, which the player can't replace or clear
+#[derive(Component, Debug)]
+pub struct Locked;
+
+#[expect(clippy::too_many_arguments)]
 fn handle_place_tile_event<T: PlaceTileEvent + Debug>(
     trigger: Trigger<T>,
     mut commands: Commands,
-    mut storage: Single<&mut TileStorage, With<BaseLayer>>,
+    base: Single<(&mut ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
+    footprints: Query<(&TilePos, &Footprint)>,
+    replaceable: Query<(Has<Locked>, Option<&Cost>)>,
+    money: Option<ResMut<Money>>,
+    deposits: Deposits,
+    tiled_map: Option<Res<TiledMap>>,
     mut despawned_event: EventWriter<ConveyorUpdated>,
 ) {
-    let tile_pos = trigger.tile_pos();
+    let (mut storage, map_size, map_type) = base.into_inner();
+
+    if trigger.needs_deposit() && !deposits.allows(&trigger.tile_pos()) {
+        return;
+    }
 
-    if let Some(entity) = storage.remove(&tile_pos) {
-        commands.entity(entity).despawn();
-        despawned_event.write(ConveyorUpdated(tile_pos));
+    let Some(cells) =
+        footprint_cells_on_map(&trigger.tile_pos(), trigger.footprint_size(), map_size)
+    else {
+        return;
+    };
+
+    if let Some(tiled_map) = tiled_map
+        && cells.iter().any(|pos| tiled_map.is_blocked(pos))
+    {
+        return;
+    }
+
+    let mut replaced: Vec<_> = cells.iter().filter_map(|pos| storage.get(pos)).collect();
+    replaced.sort();
+    replaced.dedup();
+
+    if replaceable.iter_many(&replaced).any(|(locked, _)| locked) {
+        return;
+    }
+
+    // Anything replaced is refunded first, so it can help pay
+    let paying = money.is_some();
+    if let Some(mut money) = money {
+        let refund = replaceable
+            .iter_many(&replaced)
+            .filter_map(|(_, cost)| cost.map(Cost::refund::<T>))
+            .sum();
+        if !money.try_pay(trigger.cost(), refund) {
+            return;
+        }
+    }
+
+    for tile_pos in cells {
+        if let Some(entity) = storage.remove(&tile_pos) {
+            commands.entity(entity).despawn();
+            despawned_event.write(ConveyorUpdated(tile_pos));
+
+            // Machines covering several tiles need removing from all of them
+            if let Ok((anchor, footprint)) = footprints.get(entity) {
+                for pos in footprint.cells(anchor) {
+                    if storage.get(&pos) == Some(entity) {
+                        storage.remove(&pos);
+                        despawned_event.write(ConveyorUpdated(pos));
+                    }
+                }
+            }
+        }
     }
 
     if let Some(entity) = trigger.make_new_entity(commands.reborrow(), &mut storage) {
         trigger.configure_new_entity(commands.entity(entity));
+        trigger.configure_for_map(commands.entity(entity), map_type);
+        if paying {
+            commands
+                .entity(entity)
+                .insert(Cost::new::<T>(trigger.cost()));
+        }
     }
 }
 
@@ -311,19 +569,24 @@ impl RegisterPlaceTileEvent for App {
 }
 
 #[derive(Event, Debug)]
-pub struct ClearTileEvent(TilePos);
+pub struct ClearTileEvent(pub TilePos);
 
 impl PlaceTileEvent for ClearTileEvent {
     fn tile_pos(&self) -> TilePos {
         self.0
     }
-    fn make_new_entity(&self, _: Commands, _: &mut TileStorage) -> Option<Entity> {
+    fn make_new_entity(&self, _: Commands, _: &mut ChunkedTileStorage) -> Option<Entity> {
         None
     }
 }
 
 #[derive(Component)]
-struct HoveredTile;
+pub struct HoveredTile;
+
+/// Offset from the HoveredTile of one of the other tiles in the current
+/// tool's footprint
+#[derive(Component)]
+struct HoveredFootprintTile(UVec2);
 
 #[derive(Component)]
 pub struct InteractionLayer;
@@ -3,36 +3,86 @@ use bevy_ecs_tilemap::prelude::*;
 use bevy_egui::PrimaryEguiContext;
 use bevy_pancam::PanCam;
 
-use crate::{GameState, helpers::set_camera_limits_from_tilemaps, sprite_sheet::SpriteSheet};
+use crate::{
+    GameState, factory_game::chunks::ChunkedTileStorage, helpers::set_camera_limits_from_tilemaps,
+    sprite_sheet::SpriteSheet,
+};
 
+mod analysis;
+mod analysis_panel;
 mod bridge;
+mod campaign;
+mod chunks;
+mod congestion;
+mod congestion_overlay;
 mod conveyor;
 mod conveyor_belts;
+mod deadlock;
+mod deadlock_overlay;
+mod deposits;
 mod dev;
 mod distributor;
+mod drone_panel;
+mod drones;
+mod economy;
+mod footprint;
 mod generator;
+mod generator_panel;
+mod goals;
+mod graph_export;
 mod helpers;
+mod inserter;
+mod inserter_panel;
 mod interaction;
 mod operators;
 mod payload_handler;
 mod payloads;
+mod power;
+mod power_overlay;
+mod processor;
+mod processor_panel;
+mod research;
+mod research_panel;
+mod signals;
 mod sink;
+mod storage;
+mod throughput;
+mod throughput_panel;
+mod tile_inspector;
+mod tiled_map;
 mod ui;
+mod unary_operators;
+mod value;
 
 #[cfg(test)]
 mod test;
 
-use helpers::*;
+pub use campaign::{BestScores, Levels, play_hex_sandbox, play_level};
+pub use tiled_map::{FACTORY_MAP_PATH, play_tiled_map};
 
 pub fn factory_game_logic_plugin(app: &mut App) {
-    app.add_plugins(bridge::bridge_plugin)
+    app.add_plugins(analysis::analysis_plugin)
+        .add_plugins(bridge::bridge_plugin)
         .add_plugins(conveyor_belts::conveyor_belts_plugin)
         .add_plugins(conveyor::conveyor_plugin)
+        .add_plugins(deadlock::deadlock_plugin)
         .add_plugins(payloads::payloads_plugin)
+        .add_plugins(deposits::deposits_plugin)
         .add_plugins(distributor::distributor_plugin)
+        .add_plugins(drones::drones_plugin)
+        .add_plugins(economy::economy_plugin)
         .add_plugins(generator::generator_plugin)
+        .add_plugins(goals::goals_plugin)
+        .add_plugins(inserter::inserter_plugin)
         .add_plugins(operators::operators_plugin)
+        .add_plugins(power::power_plugin)
+        .add_plugins(processor::processor_plugin)
+        .add_plugins(research::research_plugin)
+        .add_plugins(signals::signals_plugin)
         .add_plugins(sink::sink_plugin)
+        .add_plugins(storage::storage_plugin)
+        .add_plugins(throughput::throughput_plugin)
+        .add_plugins(unary_operators::unary_operators_plugin)
         .insert_resource(MapConfig::default())
         .configure_sets(
             Update,
@@ -55,10 +105,27 @@ pub fn factory_game_plugin(app: &mut App) {
         .add_plugins(factory_game_logic_plugin)
         .add_plugins(dev::dev_plugin)
         .add_plugins(ui::ui_plugin)
+        .add_plugins(generator_panel::generator_panel_plugin)
+        .add_plugins(inserter_panel::inserter_panel_plugin)
+        .add_plugins(processor_panel::processor_panel_plugin)
+        .add_plugins(drone_panel::drone_panel_plugin)
+        .add_plugins(power_overlay::power_overlay_plugin)
+        .add_plugins(congestion_overlay::congestion_overlay_plugin)
+        .add_plugins(deadlock_overlay::deadlock_overlay_plugin)
+        .add_plugins(campaign::campaign_plugin)
+        .add_plugins(research_panel::research_panel_plugin)
+        .add_plugins(tile_inspector::tile_inspector_plugin)
+        .add_plugins(throughput_panel::throughput_panel_plugin)
+        .add_plugins(analysis_panel::analysis_panel_plugin)
+        .add_plugins(graph_export::graph_export_plugin)
+        .add_plugins(tiled_map::tiled_map_plugin)
         .add_systems(
             OnEnter(GameState::FactoryGame),
             (
                 make_base_layer,
+                deposits::make_deposit_layer,
+                signals::make_signal_layer,
+                congestion_overlay::make_congestion_layer,
                 setup_camera,
                 set_camera_limits_from_tilemaps,
             )
@@ -106,7 +173,9 @@ fn make_layer(
             map_type: config.map_type,
             anchor: TilemapAnchor::Center,
             texture: TilemapTexture::Single(texture),
-            storage: TileStorage::empty(config.size),
+            // Layers that need to look tiles up keep a ChunkedTileStorage
+            // instead, as maps can be too big for a dense one
+            storage: TileStorage::default(),
             transform: Transform::from_xyz(0.0, 0.0, z),
             ..default()
         },
@@ -119,6 +188,8 @@ struct MapConfig {
     tile_size: TilemapTileSize,
     grid_size: TilemapGridSize,
     map_type: TilemapType,
+    /// Whether there are resource deposits under the base layer
+    deposits: bool,
 }
 
 impl Default for MapConfig {
@@ -132,6 +203,7 @@ impl Default for MapConfig {
             tile_size,
             grid_size,
             map_type: Default::default(),
+            deposits: false,
         }
     }
 }
@@ -142,6 +214,7 @@ pub struct BaseLayer;
 fn make_base_layer(mut commands: Commands, sprite_sheet: Res<SpriteSheet>, config: Res<MapConfig>) {
     commands.spawn((
         BaseLayer,
+        ChunkedTileStorage::default(),
         make_layer(&config, sprite_sheet.image(), 0.0, "BaseLayer"),
     ));
 }
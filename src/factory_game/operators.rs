@@ -5,13 +5,16 @@ use crate::{
     GameState,
     factory_game::{
         BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
         conveyor::Conveyor,
-        helpers::ConveyorDirection,
+        helpers::{ConveyorDirection, conveyor_directions},
         interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
         payload_handler::{AddPayloadHandler, PayloadHandler},
         payloads::{
             Payload, PayloadTransportLine, RequestPayloadTransferEvent, get_payload_transform,
         },
+        power::PowerConsumer,
+        value::{Value, concat_text, truncate_text, zip_channels},
     },
     helpers::{TilemapQuery, TilemapQueryItem},
     sprite_sheet::GameSprite,
@@ -22,21 +25,34 @@ pub fn operators_plugin(app: &mut App) {
         .add_payload_handler::<OperatorTile>()
         .register_type::<Operator>()
         .register_type::<Operand>()
+        .register_type::<OverflowPolicy>()
+        .init_resource::<OverflowPolicy>()
+        .add_observer(cycle_operator_overflow_policy)
         .add_systems(
             Update,
             (
                 update_operator_tiles.in_set(ConveyorSystems::TileUpdater),
                 (generate_new_payloads, update_operator_payloads)
                     .in_set(ConveyorSystems::TransportLogic),
-                update_operator_payload_transforms.in_set(ConveyorSystems::PayloadTransforms),
+                (update_operator_payload_transforms, update_operand_text)
+                    .in_set(ConveyorSystems::PayloadTransforms),
             ),
         );
 }
 
-#[derive(Debug, Clone, Copy, Reflect)]
+/// Binary operators.  The left operand is the one arriving from the left of
+/// the output direction, so for non-commutative operators it is the minuend,
+/// dividend or base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
 enum Operator {
     Plus,
     Multiply,
+    Subtract,
+    Divide,
+    Modulo,
+    Min,
+    Max,
+    Power,
 }
 
 impl Operator {
@@ -44,45 +60,252 @@ impl Operator {
         match self {
             Operator::Plus => GameSprite::OperatorPlus,
             Operator::Multiply => GameSprite::OperatorMultiply,
+            Operator::Subtract => GameSprite::OperatorSubtract,
+            Operator::Divide => GameSprite::OperatorDivide,
+            Operator::Modulo => GameSprite::OperatorModulo,
+            Operator::Min => GameSprite::OperatorMin,
+            Operator::Max => GameSprite::OperatorMax,
+            Operator::Power => GameSprite::OperatorPower,
         }
     }
 
-    fn generate_operand(&self, left: &Operand, right: &Operand) -> Operand {
+    /// Both operands must have the same type, otherwise the result is an
+    /// error.  Returns None for an error.
+    fn generate_operand(
+        &self,
+        left: &Operand,
+        right: &Operand,
+        policy: OverflowPolicy,
+    ) -> Option<Operand> {
+        let result = match (&left.0, &right.0) {
+            (Value::Int(left), Value::Int(right)) => {
+                self.apply_ints(*left, *right, policy).map(Value::Int)
+            }
+            (Value::Bool(left), Value::Bool(right)) => {
+                self.apply_bools(*left, *right).map(Value::Bool)
+            }
+            (Value::Colour(left), Value::Colour(right)) => {
+                self.apply_colours(left, right).map(Value::Colour)
+            }
+            (Value::Text(left), Value::Text(right)) => {
+                self.apply_texts(left, right, policy).map(Value::Text)
+            }
+            _ => None,
+        };
+        result.map(Operand)
+    }
+
+    /// Results that don't fit in an operand are handled by the overflow
+    /// policy.  Dividing by zero and negative powers are always errors.
+    fn apply_ints(&self, left: i64, right: i64, policy: OverflowPolicy) -> Option<i64> {
+        match self {
+            Operator::Plus => policy.resolve(
+                left.checked_add(right),
+                || left.wrapping_add(right),
+                || left.saturating_add(right),
+            ),
+            Operator::Multiply => policy.resolve(
+                left.checked_mul(right),
+                || left.wrapping_mul(right),
+                || left.saturating_mul(right),
+            ),
+            Operator::Subtract => policy.resolve(
+                left.checked_sub(right),
+                || left.wrapping_sub(right),
+                || left.saturating_sub(right),
+            ),
+            Operator::Divide if right != 0 => policy.resolve(
+                left.checked_div(right),
+                || left.overflowing_div(right).0,
+                || left.saturating_div(right),
+            ),
+            // The only remainder that overflows, i64::MIN % -1, is really 0
+            Operator::Modulo if right != 0 => policy.resolve(
+                left.checked_rem(right),
+                || left.overflowing_rem(right).0,
+                || left.overflowing_rem(right).0,
+            ),
+            Operator::Divide | Operator::Modulo => None,
+            Operator::Min => Some(left.min(right)),
+            Operator::Max => Some(left.max(right)),
+            Operator::Power => {
+                let right = u32::try_from(right).ok()?;
+                policy.resolve(
+                    left.checked_pow(right),
+                    || left.wrapping_pow(right),
+                    || left.saturating_pow(right),
+                )
+            }
+        }
+    }
+
+    /// Plus and max are "or", multiply and min are "and".
+    fn apply_bools(&self, left: bool, right: bool) -> Option<bool> {
         match self {
-            Operator::Plus => Operand(left.0.checked_add(right.0).unwrap_or(1)),
-            Operator::Multiply => Operand(left.0.checked_mul(right.0).unwrap_or(1)),
+            Operator::Plus | Operator::Max => Some(left || right),
+            Operator::Multiply | Operator::Min => Some(left && right),
+            _ => None,
+        }
+    }
+
+    /// Colours mix channel by channel, and always saturate.
+    fn apply_colours(&self, left: &[u8; 3], right: &[u8; 3]) -> Option<[u8; 3]> {
+        let f = match self {
+            Operator::Plus => u8::saturating_add,
+            Operator::Subtract => u8::saturating_sub,
+            Operator::Multiply => |l: u8, r: u8| (l as u16 * r as u16 / 255) as u8,
+            Operator::Min => std::cmp::min,
+            Operator::Max => std::cmp::max,
+            _ => return None,
+        };
+        Some(zip_channels(left, right, f))
+    }
+
+    /// Plus joins texts, and text that ends up too long has overflowed.
+    fn apply_texts(&self, left: &str, right: &str, policy: OverflowPolicy) -> Option<String> {
+        match self {
+            Operator::Plus => match policy {
+                OverflowPolicy::Error => concat_text(left, right),
+                _ => Some(truncate_text(left, right)),
+            },
+            Operator::Min => Some(left.min(right).to_string()),
+            Operator::Max => Some(left.max(right).to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// What operators do with results that don't fit in an operand.  This is set
+/// for the whole factory, and can be overridden on individual operators.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource)]
+pub enum OverflowPolicy {
+    Wrapping,
+    Saturating,
+    /// Replace the result with an error payload
+    #[default]
+    Error,
+}
+
+impl OverflowPolicy {
+    pub fn next(&self) -> Self {
+        match self {
+            OverflowPolicy::Wrapping => OverflowPolicy::Saturating,
+            OverflowPolicy::Saturating => OverflowPolicy::Error,
+            OverflowPolicy::Error => OverflowPolicy::Wrapping,
+        }
+    }
+
+    /// Steps an operator's override through each policy and back to
+    /// following the factory's
+    pub fn next_override(policy: Option<Self>) -> Option<Self> {
+        match policy {
+            None => Some(OverflowPolicy::Wrapping),
+            Some(OverflowPolicy::Error) => None,
+            Some(policy) => Some(policy.next()),
+        }
+    }
+
+    /// Pick the result of an operation given its checked result, which is
+    /// None on overflow, and how to work it out wrapping or saturating.
+    pub fn resolve<T>(
+        &self,
+        checked: Option<T>,
+        wrapping: impl FnOnce() -> T,
+        saturating: impl FnOnce() -> T,
+    ) -> Option<T> {
+        match (checked, self) {
+            (Some(result), _) => Some(result),
+            (None, OverflowPolicy::Wrapping) => Some(wrapping()),
+            (None, OverflowPolicy::Saturating) => Some(saturating()),
+            (None, OverflowPolicy::Error) => None,
         }
     }
 }
 
+/// Steps the `OverflowPolicy` override of the operator on a tile
+#[derive(Event, Debug)]
+pub struct CycleOverflowPolicyEvent(pub TilePos);
+
+fn cycle_operator_overflow_policy(
+    trigger: Trigger<CycleOverflowPolicyEvent>,
+    tile_storage: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    mut operators: Query<&mut OperatorTile>,
+) {
+    if let Some(entity) = tile_storage.get(&trigger.event().0)
+        && let Ok(mut operator) = operators.get_mut(entity)
+    {
+        operator.overflow_policy = OverflowPolicy::next_override(operator.overflow_policy);
+    }
+}
+
 pub struct OperatorsTool {
     operator: Operator,
     direction: ConveyorDirection,
 }
 
 impl OperatorsTool {
-    fn new(operator: Operator) -> Self {
+    /// New operators face North, or East on maps without a North, such as
+    /// hex maps
+    fn new(operator: Operator, map_type: &TilemapType) -> Self {
+        let direction = if conveyor_directions(map_type).contains(&ConveyorDirection::North) {
+            ConveyorDirection::North
+        } else {
+            ConveyorDirection::East
+        };
         Self {
             operator,
-            direction: ConveyorDirection::North,
+            direction,
         }
     }
-    pub fn plus() -> Self {
-        Self::new(Operator::Plus)
+
+    pub fn plus(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Plus, map_type)
+    }
+
+    pub fn multiply(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Multiply, map_type)
+    }
+
+    pub fn subtract(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Subtract, map_type)
     }
 
-    pub fn multiply() -> Self {
-        Self::new(Operator::Multiply)
+    pub fn divide(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Divide, map_type)
+    }
+
+    pub fn modulo(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Modulo, map_type)
+    }
+
+    pub fn min(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Min, map_type)
+    }
+
+    pub fn max(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Max, map_type)
+    }
+
+    pub fn power(map_type: &TilemapType) -> Self {
+        Self::new(Operator::Power, map_type)
     }
 }
 
+/// Power an operator needs to run at full speed
+const POWER: u32 = 3;
+
 impl Tool for OperatorsTool {
     fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
         (self.operator.sprite(), self.direction.tile_flip())
     }
 
-    fn next_variant(&mut self) {
-        self.direction = self.direction.next();
+    fn next_variant(&mut self, map_type: &TilemapType) {
+        self.direction = self.direction.next(map_type);
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceOperatorEvent(TilePos::default(), self.operator, self.direction).cost()
     }
 
     fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
@@ -98,6 +321,10 @@ impl PlaceTileEvent for PlaceOperatorEvent {
         self.0
     }
 
+    fn cost(&self) -> u32 {
+        15
+    }
+
     fn configure_new_entity(&self, mut commands: EntityCommands) {
         commands.insert((
             Name::new(format!("{:?}", self.1)),
@@ -106,20 +333,26 @@ impl PlaceTileEvent for PlaceOperatorEvent {
     }
 }
 
-#[derive(Component, Debug, Reflect, Clone, Copy)]
-pub struct Operand(pub u32);
+#[derive(Component, Debug, Reflect, Clone, PartialEq, Eq)]
+pub struct Operand(pub Value);
 
-impl Operand {
-    fn payload_text(&self) -> String {
-        format!("{}", self.0)
-    }
-}
+/// Marks a payload that is the result of a failed operation.  It has no
+/// `Operand`, and any operation on it gives another error.
+#[derive(Component, Debug, Reflect)]
+pub struct ErrorPayload;
 
 #[derive(Component, Debug, Reflect)]
-struct OperatorTile {
+#[require(PowerConsumer::new(POWER))]
+pub struct OperatorTile {
     operator: Operator,
-    left_operand: Option<Entity>,
-    right_operand: Option<Entity>,
+    /// Overrides the factory's `OverflowPolicy`
+    overflow_policy: Option<OverflowPolicy>,
+    /// Operands waiting to be used, with the directions they came in from.
+    /// On hex maps each side has two directions.
+    left_operand: Option<(Entity, ConveyorDirection)>,
+    right_operand: Option<(Entity, ConveyorDirection)>,
+    /// Seconds one operand has been held without the other arriving
+    waiting_for: f32,
     payload_transport_line: PayloadTransportLine,
 }
 
@@ -131,14 +364,14 @@ impl PayloadHandler for OperatorTile {
     ) -> Option<Entity> {
         let incoming_direction = request.direction.opposite();
 
-        if incoming_direction == self_conveyor.output().left() && self.left_operand.is_none() {
-            self.left_operand = Some(request.payload);
-            return self.left_operand;
-        } else if incoming_direction == self_conveyor.output().right()
-            && self.right_operand.is_none()
-        {
-            self.right_operand = Some(request.payload);
-            return self.right_operand;
+        let output = self_conveyor.output();
+
+        if incoming_direction.is_left_of(output) && self.left_operand.is_none() {
+            self.left_operand = Some((request.payload, incoming_direction));
+            return Some(request.payload);
+        } else if incoming_direction.is_right_of(output) && self.right_operand.is_none() {
+            self.right_operand = Some((request.payload, incoming_direction));
+            return Some(request.payload);
         }
         None
     }
@@ -150,17 +383,23 @@ impl PayloadHandler for OperatorTile {
     fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
         self.payload_transport_line
             .iter_payloads()
-            .chain(self.left_operand)
-            .chain(self.right_operand)
+            .chain(self.left_operand.map(|(entity, _)| entity))
+            .chain(self.right_operand.map(|(entity, _)| entity))
+    }
+
+    fn transport_lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        std::iter::once(&self.payload_transport_line)
     }
 }
 
 impl OperatorTile {
-    pub fn new(operator: Operator, direction: ConveyorDirection) -> Self {
+    fn new(operator: Operator, direction: ConveyorDirection) -> Self {
         OperatorTile {
             operator,
+            overflow_policy: None,
             left_operand: None,
             right_operand: None,
+            waiting_for: 0.0,
             payload_transport_line: PayloadTransportLine::new(direction, 2),
         }
     }
@@ -168,22 +407,39 @@ impl OperatorTile {
     pub fn sprite(&self) -> GameSprite {
         self.operator.sprite()
     }
+
+    /// The left and right operands waiting to be used
+    pub fn operands(&self) -> [Option<(Entity, ConveyorDirection)>; 2] {
+        [self.left_operand, self.right_operand]
+    }
+
+    pub fn payload_transport_line(&self) -> &PayloadTransportLine {
+        &self.payload_transport_line
+    }
+
+    pub fn waiting_for(&self) -> f32 {
+        self.waiting_for
+    }
+
+    pub fn overflow_policy(&self) -> Option<OverflowPolicy> {
+        self.overflow_policy
+    }
 }
 
 fn update_operator_payloads(
-    operators: Query<(Entity, &mut OperatorTile, &TilePos)>,
+    operators: Query<(Entity, &mut OperatorTile, &TilePos, &PowerConsumer)>,
     time: Res<Time>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
     mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
 ) {
     let (tile_storage, map_size) = base.into_inner();
     let t = time.delta_secs();
 
-    for (entity, mut operator, tile_pos) in operators {
+    for (entity, mut operator, tile_pos, power) in operators {
         operator.payload_transport_line.update(
             entity,
             tile_pos,
-            t,
+            t * power.speed(),
             tile_storage,
             map_size,
             &mut send_payloads,
@@ -194,23 +450,33 @@ fn update_operator_payloads(
 fn generate_new_payloads(
     mut commands: Commands,
     operators: Query<&mut OperatorTile>,
-    operands: Query<&Operand>,
+    operands: Query<Option<&Operand>, With<Payload>>,
+    overflow_policy: Res<OverflowPolicy>,
+    time: Res<Time>,
 ) {
     for mut operator in operators {
-        if let Some(left_entity) = operator.left_operand
-            && let Some(right_entity) = operator.right_operand
+        if operator.left_operand.is_some() != operator.right_operand.is_some() {
+            operator.waiting_for += time.delta_secs();
+        } else {
+            operator.waiting_for = 0.0;
+        }
+
+        if let Some((left_entity, _)) = operator.left_operand
+            && let Some((right_entity, _)) = operator.right_operand
             && let Ok(left_operand) = operands.get(left_entity)
             && let Ok(right_operand) = operands.get(right_entity)
         {
             let output_direction = operator.payload_transport_line.output_direction();
-            let new_operand = operator
-                .operator
-                .generate_operand(left_operand, right_operand);
+            let policy = operator.overflow_policy.unwrap_or(*overflow_policy);
+            let new_operand = left_operand
+                .zip(right_operand)
+                .and_then(|(left, right)| operator.operator.generate_operand(left, right, policy));
 
             if operator
                 .payload_transport_line
-                .try_transfer_onto_with_mu(output_direction.opposite(), 0.5, || {
-                    commands.spawn(operand_bundle(new_operand)).id()
+                .try_transfer_onto_with_mu(output_direction.opposite(), 0.5, || match new_operand {
+                    Some(new_operand) => commands.spawn(operand_bundle(new_operand)).id(),
+                    None => commands.spawn(error_payload_bundle()).id(),
                 })
                 .is_some()
             {
@@ -224,24 +490,19 @@ fn generate_new_payloads(
 }
 
 fn update_operator_payload_transforms(
-    operators: Query<(&TilePos, &mut OperatorTile, &Conveyor)>,
+    operators: Query<(&TilePos, &mut OperatorTile)>,
     mut payloads: Query<&mut Transform, With<Payload>>,
     base: Single<TilemapQuery, With<BaseLayer>>,
 ) {
-    for (tile_pos, operator, conveyor) in operators {
+    for (tile_pos, operator) in operators {
         operator
             .payload_transport_line
             .update_payload_transforms(tile_pos, &mut payloads, &base);
 
-        if let Some(entity) = operator.left_operand
-            && let Ok(mut transform) = payloads.get_mut(entity)
-        {
-            *transform = get_operand_transform(&base, tile_pos, conveyor.output().left());
-        }
-        if let Some(entity) = operator.right_operand
-            && let Ok(mut transform) = payloads.get_mut(entity)
-        {
-            *transform = get_operand_transform(&base, tile_pos, conveyor.output().right());
+        for (entity, direction) in operator.left_operand.iter().chain(&operator.right_operand) {
+            if let Ok(mut transform) = payloads.get_mut(*entity) {
+                *transform = get_operand_transform(&base, tile_pos, *direction);
+            }
         }
     }
 }
@@ -252,7 +513,7 @@ fn get_operand_transform(
     direction: ConveyorDirection,
 ) -> Transform {
     let tile_center = base.center_in_world(tile_pos);
-    let transform = get_payload_transform(tile_center, base.tile_size, None, Some(direction), 1.0);
+    let transform = get_payload_transform(tile_center, base.grid_size, None, Some(direction), 1.0);
 
     let scale_center = tile_center.extend(0.0) - transform.translation;
 
@@ -272,7 +533,7 @@ fn operator_bundle(operator: Operator, direction: ConveyorDirection) -> impl Bun
 fn update_operator_tiles(
     mut commands: Commands,
     new_operators: Query<(Entity, &OperatorTile, &Conveyor), Without<TileTextureIndex>>,
-    tilemap_entity: Single<Entity, (With<BaseLayer>, With<TileStorage>)>,
+    tilemap_entity: Single<Entity, (With<BaseLayer>, With<ChunkedTileStorage>)>,
 ) {
     for (entity, operator, conveyor) in new_operators {
         commands.entity(entity).insert_if_new(TileBundle {
@@ -284,13 +545,203 @@ fn update_operator_tiles(
     }
 }
 
+/// Keep the look of a payload in step with its value when something changes
+/// it in place.
+fn update_operand_text(
+    operands: Query<(&Operand, &mut Text2d, &mut TextColor, &mut Sprite), Changed<Operand>>,
+) {
+    for (operand, mut text, mut color, mut sprite) in operands {
+        text.0 = operand.0.payload_text();
+        color.0 = operand.0.text_color();
+        *sprite = operand.0.swatch();
+    }
+}
+
 pub fn operand_bundle(operand: Operand) -> impl Bundle {
     (
         StateScoped(GameState::FactoryGame),
-        Name::new(format!("Payload {}", operand.payload_text())),
+        Name::new(format!("Payload {}", operand.0.type_name())),
+        Payload,
+        Text2d::new(operand.0.payload_text()),
+        TextColor(operand.0.text_color()),
+        operand.0.swatch(),
         operand,
+    )
+}
+
+pub fn error_payload_bundle() -> impl Bundle {
+    (
+        StateScoped(GameState::FactoryGame),
+        Name::new("Payload Error"),
+        ErrorPayload,
         Payload,
-        Text2d::new(operand.payload_text()),
-        TextColor(Color::srgb(1.0, 0.4, 0.4)),
+        Text2d::new("ERR"),
+        TextColor(Color::srgb(1.0, 0.9, 0.0)),
     )
 }
+
+#[cfg(test)]
+mod operator_test {
+    use super::*;
+    use Operator::*;
+
+    fn apply_with(
+        operator: Operator,
+        left: Value,
+        right: Value,
+        policy: OverflowPolicy,
+    ) -> Option<Value> {
+        operator
+            .generate_operand(&Operand(left), &Operand(right), policy)
+            .map(|o| o.0)
+    }
+
+    fn apply_ints(
+        operator: Operator,
+        left: i64,
+        right: i64,
+        policy: OverflowPolicy,
+    ) -> Option<i64> {
+        match apply_with(operator, Value::Int(left), Value::Int(right), policy) {
+            Some(Value::Int(result)) => Some(result),
+            None => None,
+            other => panic!("Expected an int, got {other:?}"),
+        }
+    }
+
+    fn apply(operator: Operator, left: i64, right: i64) -> i64 {
+        apply_ints(operator, left, right, OverflowPolicy::Error).unwrap()
+    }
+
+    #[test]
+    fn left_operand_comes_first() {
+        assert_eq!(apply(Subtract, 7, 3), 4);
+        assert_eq!(apply(Subtract, 3, 7), -4);
+        assert_eq!(apply(Divide, 7, 2), 3);
+        assert_eq!(apply(Modulo, 7, 3), 1);
+        assert_eq!(apply(Power, 2, 5), 32);
+    }
+
+    #[test]
+    fn min_and_max() {
+        assert_eq!(apply(Min, 7, -3), -3);
+        assert_eq!(apply(Max, 7, -3), 7);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        for policy in [
+            OverflowPolicy::Wrapping,
+            OverflowPolicy::Saturating,
+            OverflowPolicy::Error,
+        ] {
+            assert_eq!(apply_ints(Divide, 7, 0, policy), None);
+            assert_eq!(apply_ints(Modulo, 7, 0, policy), None);
+            assert_eq!(apply_ints(Power, 7, -1, policy), None);
+        }
+    }
+
+    #[test]
+    fn out_of_range_results() {
+        use OverflowPolicy::*;
+
+        assert_eq!(apply_ints(Power, 2, 64, Error), None);
+        assert_eq!(apply_ints(Plus, i64::MAX, 1, Error), None);
+
+        assert_eq!(apply_ints(Plus, i64::MAX, 1, Wrapping), Some(i64::MIN));
+        assert_eq!(apply_ints(Subtract, i64::MIN, 1, Wrapping), Some(i64::MAX));
+
+        assert_eq!(
+            apply_ints(Subtract, i64::MIN, 1, Saturating),
+            Some(i64::MIN)
+        );
+        assert_eq!(apply_ints(Power, 2, 64, Saturating), Some(i64::MAX));
+    }
+
+    #[test]
+    fn dividing_the_smallest_int_by_minus_one() {
+        use OverflowPolicy::*;
+
+        assert_eq!(apply_ints(Divide, i64::MIN, -1, Error), None);
+        assert_eq!(apply_ints(Divide, i64::MIN, -1, Wrapping), Some(i64::MIN));
+        assert_eq!(apply_ints(Divide, i64::MIN, -1, Saturating), Some(i64::MAX));
+
+        assert_eq!(apply_ints(Modulo, i64::MIN, -1, Error), None);
+        assert_eq!(apply_ints(Modulo, i64::MIN, -1, Wrapping), Some(0));
+        assert_eq!(apply_ints(Modulo, i64::MIN, -1, Saturating), Some(0));
+    }
+
+    #[test]
+    fn mixed_types_are_errors() {
+        let policy = OverflowPolicy::Wrapping;
+
+        assert_eq!(
+            apply_with(Plus, Value::Int(1), Value::Colour([1, 2, 3]), policy),
+            None
+        );
+        assert_eq!(
+            apply_with(Max, Value::Bool(true), Value::Int(1), policy),
+            None
+        );
+    }
+
+    #[test]
+    fn bools() {
+        let policy = OverflowPolicy::Error;
+        let (t, f) = (Value::Bool(true), Value::Bool(false));
+
+        assert_eq!(
+            apply_with(Plus, t.clone(), f.clone(), policy),
+            Some(t.clone())
+        );
+        assert_eq!(
+            apply_with(Multiply, t.clone(), f.clone(), policy),
+            Some(f.clone())
+        );
+        assert_eq!(apply_with(Divide, t, f, policy), None);
+    }
+
+    #[test]
+    fn colours_mix_by_channel() {
+        let policy = OverflowPolicy::Error;
+        let red = Value::Colour([200, 0, 0]);
+        let blue = Value::Colour([100, 0, 255]);
+
+        assert_eq!(
+            apply_with(Plus, red.clone(), blue.clone(), policy),
+            Some(Value::Colour([255, 0, 255]))
+        );
+        assert_eq!(
+            apply_with(Multiply, red.clone(), blue.clone(), policy),
+            Some(Value::Colour([78, 0, 0]))
+        );
+        assert_eq!(apply_with(Power, red, blue, policy), None);
+    }
+
+    #[test]
+    fn texts_join() {
+        let text = |t: &str| Value::Text(t.to_string());
+
+        assert_eq!(
+            apply_with(Plus, text("ab"), text("cd"), OverflowPolicy::Error),
+            Some(text("abcd"))
+        );
+        assert_eq!(
+            apply_with(Plus, text("abcde"), text("fghij"), OverflowPolicy::Error),
+            None
+        );
+        assert_eq!(
+            apply_with(
+                Plus,
+                text("abcde"),
+                text("fghij"),
+                OverflowPolicy::Saturating
+            ),
+            Some(text("abcdefgh"))
+        );
+        assert_eq!(
+            apply_with(Min, text("b"), text("a"), OverflowPolicy::Error),
+            Some(text("a"))
+        );
+    }
+}
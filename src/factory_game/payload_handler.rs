@@ -3,8 +3,10 @@ use smallvec::SmallVec;
 
 use crate::factory_game::{
     ConveyorSystems,
+    congestion::{Congestion, track_congestion},
     conveyor::Conveyor,
-    payloads::{PayloadTransferredEvent, RequestPayloadTransferEvent},
+    inserter::pick_up_payloads,
+    payloads::{PayloadTransferredEvent, PayloadTransportLine, RequestPayloadTransferEvent},
 };
 
 pub trait PayloadHandler: GetTypeRegistration + Component<Mutability = Mutable> {
@@ -25,6 +27,18 @@ pub trait PayloadHandler: GetTypeRegistration + Component<Mutability = Mutable>
     }
 
     fn iter_payloads(&self) -> impl Iterator<Item = Entity>;
+
+    /// The lines payloads move along through the handler, which show how
+    /// congested its tile is
+    fn transport_lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        std::iter::empty()
+    }
+
+    /// The payload an inserter behind the tile would take, if any
+    fn payload_to_pick_up(&self) -> Option<Entity> {
+        self.transport_lines()
+            .find_map(PayloadTransportLine::get_payload_to_transfer)
+    }
 }
 
 pub trait AddPayloadHandler {
@@ -34,13 +48,19 @@ pub trait AddPayloadHandler {
 impl AddPayloadHandler for App {
     fn add_payload_handler<T: PayloadHandler>(&mut self) -> &mut Self {
         self.register_type::<T>()
+            .register_type::<Congestion>()
+            .register_required_components::<T, Congestion>()
             .add_systems(
                 Update,
                 (
                     transfer_payloads_to_handlers::<T>
                         .in_set(ConveyorSystems::TransferPayloadsToHandlers),
-                    transfer_payloads_from_handlers::<T>
+                    // Inserters only pick up what's still left once payloads
+                    // that have been transferred are gone
+                    (transfer_payloads_from_handlers::<T>, pick_up_payloads::<T>)
+                        .chain()
                         .in_set(ConveyorSystems::TransferPayloadsFromHandlers),
+                    track_congestion::<T>.in_set(ConveyorSystems::PayloadTransforms),
                 ),
             )
             .add_observer(on_remove_handler::<T>)
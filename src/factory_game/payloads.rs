@@ -5,9 +5,12 @@ use smallvec::SmallVec;
 use crate::{
     factory_game::{
         BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
         conveyor::Conveyor,
         helpers::ConveyorDirection,
         payload_handler::{AddPayloadHandler, PayloadHandler},
+        power::PowerConsumer,
+        signals::Disabled,
     },
     helpers::{TilemapQuery, TilemapQueryItem},
 };
@@ -62,6 +65,10 @@ impl PayloadHandler for PayloadTransportLine {
     fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
         self.payloads.iter().map(|p| p.entity)
     }
+
+    fn transport_lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        std::iter::once(self)
+    }
 }
 
 impl PayloadTransportLine {
@@ -114,7 +121,7 @@ impl PayloadTransportLine {
         None
     }
 
-    fn has_room_for_one_more_with_mu(&self, mu: f32) -> bool {
+    pub fn has_room_for_one_more_with_mu(&self, mu: f32) -> bool {
         self.payloads
             .last()
             .map(|p| p.mu >= self.spacing() + mu)
@@ -130,19 +137,21 @@ impl PayloadTransportLine {
         this_entity: Entity,
         tile_pos: &TilePos,
         t: f32,
-        tile_storage: &TileStorage,
+        tile_storage: &ChunkedTileStorage,
         map_size: &TilemapSize,
         send_payloads: &mut EventWriter<RequestPayloadTransferEvent>,
     ) {
         self.update_payloads(t);
         if let Some(payload) = self.get_payload_to_transfer() {
-            let destination_pos = tile_pos.square_offset(&self.output_direction().into(), map_size);
-            let destination_entity = destination_pos.and_then(|pos| tile_storage.get(&pos));
-            if let Some(destination) = destination_entity {
+            let destination_pos = self.output_direction().offset(tile_pos, map_size);
+            if let Some(destination_pos) = destination_pos
+                && let Some(destination) = tile_storage.get(&destination_pos)
+            {
                 let e = RequestPayloadTransferEvent {
                     payload,
                     source: this_entity,
                     destination,
+                    destination_pos,
                     direction: self.output_direction(),
                 };
                 send_payloads.write(e);
@@ -190,7 +199,7 @@ impl PayloadTransportLine {
             if let Ok(mut transform) = payloads.get_mut(p.entity) {
                 *transform = get_payload_transform(
                     tile_center,
-                    base.tile_size,
+                    base.grid_size,
                     Some(p.from),
                     self.output_direction,
                     p.mu,
@@ -202,6 +211,20 @@ impl PayloadTransportLine {
     pub fn count(&self) -> usize {
         self.payloads.len()
     }
+
+    /// How full the line is, from 0 to 1
+    pub fn fill_ratio(&self) -> f32 {
+        self.payloads.len() as f32 / self.capacity as f32
+    }
+
+    pub fn destination(&self) -> Option<ConveyorDirection> {
+        self.output_direction
+    }
+
+    /// The payloads on the line and how far along it they are, front first
+    pub fn payloads(&self) -> impl Iterator<Item = (Entity, f32)> {
+        self.payloads.iter().map(|p| (p.entity, p.mu))
+    }
 }
 
 #[cfg(test)]
@@ -365,22 +388,47 @@ mod payload_transport_line_test {
             &[tp(e[0], West, 1.0), tp(e[2], West, 0.8)]
         );
     }
+
+    #[test]
+    fn lists_payloads_front_first() {
+        let mut ptl = PayloadTransportLine::new(East, 2);
+        let e: Vec<Entity> = (1..3).map(Entity::from_raw).collect();
+
+        ptl.try_transfer_onto(West, || e[0]);
+        ptl.update_payloads(0.75);
+        ptl.try_transfer_onto(West, || e[1]);
+
+        assert_eq!(ptl.destination(), Some(East));
+        assert_eq!(
+            ptl.payloads().collect::<Vec<_>>(),
+            vec![(e[0], 0.75), (e[1], 0.0)]
+        );
+        assert_eq!(PayloadTransportLine::new_no_output(2).destination(), None);
+    }
 }
 
 fn update_payload_transport_lines(
-    transport_lines: Query<(Entity, &mut PayloadTransportLine, &TilePos)>,
+    transport_lines: Query<
+        (
+            Entity,
+            &mut PayloadTransportLine,
+            &TilePos,
+            Option<&PowerConsumer>,
+        ),
+        Without<Disabled>,
+    >,
     time: Res<Time>,
-    base: Single<(&TileStorage, &TilemapSize), With<BaseLayer>>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
     mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
 ) {
     let (tile_storage, map_size) = base.into_inner();
 
     let t = time.delta_secs();
-    for (source, mut transport_line, tile_pos) in transport_lines {
+    for (source, mut transport_line, tile_pos, power) in transport_lines {
         transport_line.update(
             source,
             tile_pos,
-            t,
+            t * power.map_or(1.0, PowerConsumer::speed),
             tile_storage,
             map_size,
             &mut send_payloads,
@@ -403,13 +451,16 @@ pub struct Payload;
 
 pub fn get_payload_transform(
     tile_center: Vec2,
-    tile_size: &TilemapTileSize,
+    grid_size: &TilemapGridSize,
     input_direction: Option<ConveyorDirection>,
     output_direction: Option<ConveyorDirection>,
     mu: f32,
 ) -> Transform {
-    let start = tile_center + get_direction_offset(tile_size, input_direction);
-    let end = tile_center + get_direction_offset(tile_size, output_direction);
+    let offset = |direction: Option<ConveyorDirection>| {
+        direction.map_or(Vec2::ZERO, |d| d.offset_in_world(grid_size))
+    };
+    let start = tile_center + offset(input_direction);
+    let end = tile_center + offset(output_direction);
 
     let pos = if mu < 0.5 {
         start.lerp(tile_center, mu / 0.5)
@@ -417,34 +468,26 @@ pub fn get_payload_transform(
         tile_center.lerp(end, (mu - 0.5) / 0.5)
     };
 
+    // Only payloads going East or West pass over bridges
     let z = output_direction.map(|d| {
-        if d == ConveyorDirection::North || d == ConveyorDirection::South {
-            1.0
-        } else {
+        if d == ConveyorDirection::East || d == ConveyorDirection::West {
             3.0
+        } else {
+            1.0
         }
     });
 
     Transform::from_translation(pos.extend(z.unwrap_or(3.0)))
 }
 
-fn get_direction_offset(tile_size: &TilemapTileSize, direction: Option<ConveyorDirection>) -> Vec2 {
-    let half_size = Vec2::new(tile_size.x / 2.0, tile_size.y / 2.0);
-
-    match direction {
-        Some(ConveyorDirection::North) => Vec2::new(0.0, half_size.y),
-        Some(ConveyorDirection::South) => Vec2::new(0.0, -half_size.y),
-        Some(ConveyorDirection::East) => Vec2::new(half_size.x, 0.0),
-        Some(ConveyorDirection::West) => Vec2::new(-half_size.x, 0.0),
-        None => Vec2::default(),
-    }
-}
-
 #[derive(Event, Debug)]
 pub struct RequestPayloadTransferEvent {
     pub payload: Entity,
     pub source: Entity,
     pub destination: Entity,
+    /// The tile the payload is being transferred onto; for machines that
+    /// cover several tiles this says which one.
+    pub destination_pos: TilePos,
     pub direction: ConveyorDirection,
 }
 
@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    factory_game::{
+        BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
+    },
+    helpers::TilemapQuery,
+    sprite_sheet::GameSprite,
+};
+
+pub fn power_plugin(app: &mut App) {
+    app.register_place_tile_event::<PlacePowerNodeEvent>()
+        .register_type::<PowerNode>()
+        .register_type::<PowerConsumer>()
+        .add_systems(
+            Update,
+            (
+                (update_power_node_tiles, spawn_power_labels).in_set(ConveyorSystems::TileUpdater),
+                update_power
+                    .run_if(resource_exists::<Power>)
+                    .in_set(ConveyorSystems::TileUpdater)
+                    .after(update_power_node_tiles),
+                update_power_labels
+                    .run_if(resource_exists::<Power>)
+                    .in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+/// What a power plant produces
+pub const PLANT_OUTPUT: u32 = 50;
+
+/// How far apart, in tiles, power nodes can be and still be connected
+pub const WIRE_REACH: f32 = 6.0;
+
+/// Power nodes supply machines up to this many tiles away in each direction
+pub const SUPPLY_RADIUS: u32 = 2;
+
+const PLANT_COST: u32 = 40;
+const POLE_COST: u32 = 5;
+
+/// Plants produce power, and poles carry it further.  Both supply machines
+/// around them.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[require(PowerLabels)]
+pub enum PowerNode {
+    Plant,
+    Pole,
+}
+
+impl PowerNode {
+    fn sprite(&self) -> GameSprite {
+        match self {
+            PowerNode::Plant => GameSprite::PowerPlant,
+            PowerNode::Pole => GameSprite::PowerPole,
+        }
+    }
+
+    fn cost(&self) -> u32 {
+        match self {
+            PowerNode::Plant => PLANT_COST,
+            PowerNode::Pole => POLE_COST,
+        }
+    }
+}
+
+pub struct PowerTool(PowerNode);
+
+impl PowerTool {
+    pub fn plant() -> Self {
+        PowerTool(PowerNode::Plant)
+    }
+
+    pub fn pole() -> Self {
+        PowerTool(PowerNode::Pole)
+    }
+}
+
+impl Tool for PowerTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (self.0.sprite(), TileFlip::default())
+    }
+
+    fn cost(&self) -> u32 {
+        PlacePowerNodeEvent(TilePos::default(), self.0).cost()
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(PlacePowerNodeEvent(*tile_pos, self.0));
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct PlacePowerNodeEvent(pub TilePos, pub PowerNode);
+
+impl PlaceTileEvent for PlacePowerNodeEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    fn cost(&self) -> u32 {
+        self.1.cost()
+    }
+
+    fn configure_new_entity(&self, mut commands: EntityCommands) {
+        commands.insert((self.1, Name::new(format!("Power {:?}", self.1))));
+    }
+}
+
+/// How much power a machine needs to run at full speed, and the fraction of
+/// that it is getting.  Machines run slower, in proportion, when they get
+/// less.
+#[derive(Component, Debug, Clone, Copy, Reflect)]
+pub struct PowerConsumer {
+    demand: u32,
+    satisfaction: f32,
+}
+
+impl PowerConsumer {
+    pub const fn new(demand: u32) -> Self {
+        PowerConsumer {
+            demand,
+            satisfaction: 1.0,
+        }
+    }
+
+    /// How fast the machine runs, from 0 to 1
+    pub fn speed(&self) -> f32 {
+        self.satisfaction
+    }
+}
+
+/// Power nodes connected to each other, and the machines they supply
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerNetwork {
+    pub nodes: Vec<Entity>,
+    pub supply: u32,
+    pub demand: u32,
+}
+
+impl PowerNetwork {
+    /// The fraction of the demand that is met
+    pub fn satisfaction(&self) -> f32 {
+        if self.demand == 0 {
+            1.0
+        } else {
+            (self.supply as f32 / self.demand as f32).min(1.0)
+        }
+    }
+}
+
+/// The power networks, rebuilt every tick.  Without this resource, as in
+/// levels, machines don't need power.  With it, machines that aren't
+/// supplied by any network stop.
+#[derive(Resource, Default, Debug, PartialEq)]
+pub struct Power {
+    networks: Vec<PowerNetwork>,
+}
+
+impl Power {
+    pub fn networks(&self) -> &[PowerNetwork] {
+        &self.networks
+    }
+
+    pub fn network_of(&self, node: Entity) -> Option<&PowerNetwork> {
+        self.networks
+            .iter()
+            .find(|network| network.nodes.contains(&node))
+    }
+}
+
+/// Whether two power nodes are close enough to be connected
+pub fn is_in_reach(a: &TilePos, b: &TilePos) -> bool {
+    let a = Vec2::new(a.x as f32, a.y as f32);
+    let b = Vec2::new(b.x as f32, b.y as f32);
+    a.distance(b) <= WIRE_REACH
+}
+
+/// Groups nodes that are in reach of each other into networks, then shares
+/// each network's supply between the machines around its nodes.  A machine
+/// in reach of more than one network is supplied by the first.
+fn update_power(
+    mut power: ResMut<Power>,
+    nodes: Query<(Entity, &TilePos, &PowerNode)>,
+    mut consumers: Query<(Entity, &mut PowerConsumer)>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
+) {
+    let (tile_storage, map_size) = base.into_inner();
+
+    let mut unvisited: Vec<_> = nodes.iter().collect();
+    unvisited.sort_by_key(|(entity, ..)| *entity);
+    unvisited.reverse();
+
+    let mut networks = vec![];
+    let mut supplied = HashMap::new();
+    while let Some(first) = unvisited.pop() {
+        let mut members = vec![first];
+        let mut i = 0;
+        while i < members.len() {
+            let (_, pos, _) = members[i];
+            let (connected, rest) = unvisited
+                .into_iter()
+                .partition(|(_, other, _)| is_in_reach(pos, other));
+            unvisited = rest;
+            members.extend::<Vec<_>>(connected);
+            i += 1;
+        }
+
+        let mut network = PowerNetwork {
+            nodes: members.iter().map(|(entity, ..)| *entity).collect(),
+            supply: members
+                .iter()
+                .filter(|(.., node)| **node == PowerNode::Plant)
+                .count() as u32
+                * PLANT_OUTPUT,
+            demand: 0,
+        };
+
+        for (_, pos, _) in &members {
+            let (min_x, min_y) = (
+                pos.x.saturating_sub(SUPPLY_RADIUS),
+                pos.y.saturating_sub(SUPPLY_RADIUS),
+            );
+            let max_x = (pos.x + SUPPLY_RADIUS).min(map_size.x - 1);
+            let max_y = (pos.y + SUPPLY_RADIUS).min(map_size.y - 1);
+
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    if let Some(entity) = tile_storage.get(&TilePos { x, y })
+                        && let Ok((_, consumer)) = consumers.get(entity)
+                        && !supplied.contains_key(&entity)
+                    {
+                        supplied.insert(entity, networks.len());
+                        network.demand += consumer.demand;
+                    }
+                }
+            }
+        }
+
+        networks.push(network);
+    }
+
+    for (entity, mut consumer) in &mut consumers {
+        let satisfaction = supplied
+            .get(&entity)
+            .map_or(0.0, |network| networks[*network].satisfaction());
+        if consumer.satisfaction != satisfaction {
+            consumer.satisfaction = satisfaction;
+        }
+    }
+
+    power.set_if_neq(Power { networks });
+}
+
+#[derive(Component, Default)]
+#[relationship_target(relationship = PowerLabel, linked_spawn)]
+pub struct PowerLabels(Vec<Entity>);
+
+/// The text under a power node showing how much of its network's demand is
+/// met
+#[derive(Component)]
+#[relationship(relationship_target = PowerLabels)]
+pub struct PowerLabel(Entity);
+
+fn update_power_node_tiles(
+    mut commands: Commands,
+    new_nodes: Query<(Entity, &PowerNode), Without<TileTextureIndex>>,
+    tilemap_entity: Single<Entity, (With<BaseLayer>, With<ChunkedTileStorage>)>,
+) {
+    for (entity, node) in new_nodes {
+        commands.entity(entity).insert_if_new(TileBundle {
+            tilemap_id: TilemapId(*tilemap_entity),
+            texture_index: node.sprite().tile_texture_index(),
+            ..default()
+        });
+    }
+}
+
+fn spawn_power_labels(
+    mut commands: Commands,
+    new_nodes: Query<(Entity, &TilePos), Added<PowerNode>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (entity, tile_pos) in new_nodes {
+        let tile_center = base.center_in_world(tile_pos);
+        commands.spawn((
+            Name::new("Power Satisfaction"),
+            Text2d::default(),
+            TextFont::from_font_size(10.0),
+            Transform::from_translation(
+                (tile_center + Vec2::new(0.0, -base.tile_size.y / 3.0)).extend(2.0),
+            ),
+            PowerLabel(entity),
+        ));
+    }
+}
+
+fn update_power_labels(power: Res<Power>, labels: Query<(&PowerLabel, &mut Text2d)>) {
+    for (label, mut text) in labels {
+        let new_text = power
+            .network_of(label.0)
+            .map(|network| format!("{:.0}%", network.satisfaction() * 100.0))
+            .unwrap_or_default();
+        if text.0 != new_text {
+            text.0 = new_text;
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::input::egui_wants_any_input;
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        power::{Power, SUPPLY_RADIUS, is_in_reach},
+    },
+    helpers::TilemapQuery,
+};
+
+pub fn power_overlay_plugin(app: &mut App) {
+    app.init_resource::<PowerOverlay>().add_systems(
+        Update,
+        (
+            toggle_power_overlay
+                .run_if(input_just_pressed(KeyCode::KeyP))
+                .run_if(not(egui_wants_any_input)),
+            draw_power_overlay.run_if(|overlay: Res<PowerOverlay>| overlay.0),
+        )
+            .run_if(in_state(GameState::FactoryGame))
+            .run_if(resource_exists::<Power>),
+    );
+}
+
+/// Whether the areas that power networks supply are shown
+#[derive(Resource, Default)]
+struct PowerOverlay(bool);
+
+fn toggle_power_overlay(mut overlay: ResMut<PowerOverlay>) {
+    overlay.0 = !overlay.0;
+}
+
+/// Each network's supply area is outlined in a colour going from green, when
+/// all of its demand is met, to red, when none is, with wires between its
+/// nodes.
+fn draw_power_overlay(
+    mut gizmos: Gizmos,
+    power: Res<Power>,
+    nodes: Query<&TilePos>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    let tile_size = Vec2::new(base.grid_size.x, base.grid_size.y);
+    let area = tile_size * (SUPPLY_RADIUS * 2 + 1) as f32;
+
+    for network in power.networks() {
+        let satisfaction = network.satisfaction();
+        let color = Color::srgba(1.0 - satisfaction, satisfaction, 0.2, 0.8);
+        let positions: Vec<_> = nodes.iter_many(&network.nodes).collect();
+
+        for (i, pos) in positions.iter().enumerate() {
+            let center = base.center_in_world(pos);
+            gizmos.rect_2d(Isometry2d::from_translation(center), area, color);
+
+            for other in &positions[i + 1..] {
+                if is_in_reach(pos, other) {
+                    gizmos.line_2d(center, base.center_in_world(other), color);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,792 @@
+use std::{collections::HashMap, fmt::Display};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    factory_game::{
+        BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
+        conveyor::Conveyor,
+        helpers::{
+            CONVEYOR_DIRECTIONS, ConveyorDirection, ConveyorDirections, conveyor_directions,
+        },
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
+        operators::{Operand, operand_bundle},
+        payload_handler::{AddPayloadHandler, PayloadHandler},
+        payloads::{Payload, RequestPayloadTransferEvent, get_payload_transform},
+        signals::Disabled,
+        value::Value,
+    },
+    helpers::TilemapQuery,
+    sprite_sheet::GameSprite,
+};
+
+pub fn processor_plugin(app: &mut App) {
+    app.register_place_tile_event::<PlaceProcessorEvent>()
+        .add_payload_handler::<Processor>()
+        .add_systems(
+            Update,
+            (
+                update_processor_tiles.in_set(ConveyorSystems::TileUpdater),
+                (run_processors, send_processor_payloads)
+                    .chain()
+                    .in_set(ConveyorSystems::TransportLogic),
+                update_processor_transforms.in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+/// How many instructions a processor may execute each tick.  Instructions
+/// waiting on a side end the tick early.
+pub const CYCLES_PER_TICK: u32 = 8;
+
+/// Registers are named r0 to r3
+pub const REGISTERS: usize = 4;
+
+/// The longest program a processor can hold, not counting labels and
+/// comments
+pub const MAX_INSTRUCTIONS: usize = 32;
+
+/// What a new processor runs until it is given a program
+const DEFAULT_SOURCE: &str = "; Pass payloads from west to east\nMOV W, E\n";
+
+pub struct ProcessorTool;
+
+impl Tool for ProcessorTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (GameSprite::Processor, TileFlip::default())
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceProcessorEvent(TilePos::default()).cost()
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(PlaceProcessorEvent(*tile_pos));
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct PlaceProcessorEvent(pub TilePos);
+
+impl PlaceTileEvent for PlaceProcessorEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    fn cost(&self) -> u32 {
+        30
+    }
+
+    fn configure_for_map(&self, mut commands: EntityCommands, map_type: &TilemapType) {
+        commands.insert((processor_bundle(map_type), Name::new("Processor")));
+    }
+}
+
+/// Where an instruction takes a number from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Source {
+    Register(usize),
+    Constant(i64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Arithmetic {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
+/// Jumps are taken always, or depending on a register's value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum JumpCondition {
+    Always,
+    Zero(usize),
+    NotZero(usize),
+    Positive(usize),
+    Negative(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Instruction {
+    /// Takes the int payload waiting on a side into a register
+    In(usize, ConveyorDirection),
+    /// Sends a new int payload out of a side
+    Out(ConveyorDirection, Source),
+    /// Passes the payload waiting on one side out of another, whatever its
+    /// value
+    Mov(ConveyorDirection, ConveyorDirection),
+    Set(usize, Source),
+    /// Arithmetic wraps on overflow
+    Arithmetic(Arithmetic, usize, Source),
+    /// Jumps to an instruction index
+    Jump(JumpCondition, usize),
+    Nop,
+}
+
+/// A problem with a program's source, on a line counting from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+/// A program parsed from source like:
+///
+/// ```text
+/// loop:
+///     IN r0, W      ; wait for a payload from the west
+///     JZ r0, loop
+///     MUL r0, 2
+///     OUT E, r0
+///     JMP loop
+/// ```
+///
+/// Execution carries on from the top after the last instruction.
+#[derive(Debug, Clone, Default, PartialEq, Reflect)]
+pub struct Program {
+    instructions: Vec<Instruction>,
+    /// The source line of each instruction, counting from 1
+    lines: Vec<usize>,
+}
+
+impl Program {
+    pub fn parse(source: &str) -> Result<Program, ParseError> {
+        // Find the labels first, so that jumps can go forwards
+        let mut labels = HashMap::new();
+        let mut statements = vec![];
+        for (i, line) in source.lines().enumerate() {
+            let line_number = i + 1;
+            let error = |message: String| ParseError {
+                line: line_number,
+                message,
+            };
+
+            let mut statement = line.split(';').next().unwrap_or_default().trim();
+            if let Some((label, rest)) = statement.split_once(':') {
+                let label = label.trim();
+                if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    return Err(error(format!("\"{label}\" isn't a valid label")));
+                }
+                if labels
+                    .insert(label.to_lowercase(), statements.len())
+                    .is_some()
+                {
+                    return Err(error(format!("Label \"{label}\" is already used")));
+                }
+                statement = rest.trim();
+            }
+
+            if !statement.is_empty() {
+                statements.push((line_number, statement));
+            }
+        }
+
+        if statements.len() > MAX_INSTRUCTIONS {
+            return Err(ParseError {
+                line: statements[MAX_INSTRUCTIONS].0,
+                message: format!("Programs can have at most {MAX_INSTRUCTIONS} instructions"),
+            });
+        }
+
+        let mut program = Program::default();
+        for (line, statement) in statements {
+            let instruction = parse_instruction(statement, &labels)
+                .map_err(|message| ParseError { line, message })?;
+            program.instructions.push(instruction);
+            program.lines.push(line);
+        }
+        Ok(program)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// The source line of the instruction at `pc`
+    pub fn line(&self, pc: usize) -> Option<usize> {
+        self.lines.get(pc).copied()
+    }
+}
+
+fn parse_instruction(
+    statement: &str,
+    labels: &HashMap<String, usize>,
+) -> Result<Instruction, String> {
+    let (mnemonic, rest) = statement
+        .split_once(char::is_whitespace)
+        .unwrap_or((statement, ""));
+    let mnemonic = mnemonic.to_uppercase();
+    let args: Vec<&str> = if rest.trim().is_empty() {
+        vec![]
+    } else {
+        rest.split(',').map(str::trim).collect()
+    };
+
+    let expect_args = |count: usize| {
+        if args.len() == count {
+            Ok(())
+        } else {
+            Err(format!("{mnemonic} takes {count} operand(s)"))
+        }
+    };
+    let label = |name: &str| {
+        labels
+            .get(&name.to_lowercase())
+            .copied()
+            .ok_or_else(|| format!("No label called \"{name}\""))
+    };
+    let arithmetic = |op: Arithmetic| -> Result<Instruction, String> {
+        expect_args(2)?;
+        Ok(Instruction::Arithmetic(
+            op,
+            parse_register(args[0])?,
+            parse_source(args[1])?,
+        ))
+    };
+    let jump = |condition: fn(usize) -> JumpCondition| -> Result<Instruction, String> {
+        expect_args(2)?;
+        Ok(Instruction::Jump(
+            condition(parse_register(args[0])?),
+            label(args[1])?,
+        ))
+    };
+
+    match mnemonic.as_str() {
+        "IN" => {
+            expect_args(2)?;
+            Ok(Instruction::In(
+                parse_register(args[0])?,
+                parse_side(args[1])?,
+            ))
+        }
+        "OUT" => {
+            expect_args(2)?;
+            Ok(Instruction::Out(
+                parse_side(args[0])?,
+                parse_source(args[1])?,
+            ))
+        }
+        "MOV" => {
+            expect_args(2)?;
+            Ok(Instruction::Mov(parse_side(args[0])?, parse_side(args[1])?))
+        }
+        "SET" => {
+            expect_args(2)?;
+            Ok(Instruction::Set(
+                parse_register(args[0])?,
+                parse_source(args[1])?,
+            ))
+        }
+        "ADD" => arithmetic(Arithmetic::Add),
+        "SUB" => arithmetic(Arithmetic::Sub),
+        "MUL" => arithmetic(Arithmetic::Mul),
+        "DIV" => arithmetic(Arithmetic::Div),
+        "MOD" => arithmetic(Arithmetic::Mod),
+        "JMP" => {
+            expect_args(1)?;
+            Ok(Instruction::Jump(JumpCondition::Always, label(args[0])?))
+        }
+        "JZ" => jump(JumpCondition::Zero),
+        "JNZ" => jump(JumpCondition::NotZero),
+        "JGZ" => jump(JumpCondition::Positive),
+        "JLZ" => jump(JumpCondition::Negative),
+        "NOP" => {
+            expect_args(0)?;
+            Ok(Instruction::Nop)
+        }
+        _ => Err(format!("Unknown instruction \"{mnemonic}\"")),
+    }
+}
+
+fn parse_register(arg: &str) -> Result<usize, String> {
+    arg.strip_prefix(['r', 'R'])
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|n| *n < REGISTERS)
+        .ok_or_else(|| format!("\"{arg}\" isn't a register, r0 to r{}", REGISTERS - 1))
+}
+
+fn parse_source(arg: &str) -> Result<Source, String> {
+    if let Ok(constant) = arg.parse() {
+        return Ok(Source::Constant(constant));
+    }
+    parse_register(arg)
+        .map(Source::Register)
+        .map_err(|_| format!("\"{arg}\" isn't a register or a number"))
+}
+
+fn parse_side(arg: &str) -> Result<ConveyorDirection, String> {
+    match arg.to_uppercase().as_str() {
+        "N" | "NORTH" => Ok(ConveyorDirection::North),
+        "S" | "SOUTH" => Ok(ConveyorDirection::South),
+        "E" | "EAST" => Ok(ConveyorDirection::East),
+        "W" | "WEST" => Ok(ConveyorDirection::West),
+        "NE" | "NORTHEAST" => Ok(ConveyorDirection::NorthEast),
+        "NW" | "NORTHWEST" => Ok(ConveyorDirection::NorthWest),
+        "SE" | "SOUTHEAST" => Ok(ConveyorDirection::SouthEast),
+        "SW" | "SOUTHWEST" => Ok(ConveyorDirection::SouthWest),
+        _ => Err(format!(
+            "\"{arg}\" isn't a side, N, S, E, W or, on hex maps, NE, NW, SE or SW"
+        )),
+    }
+}
+
+/// Why a processor stopped running its program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Fault {
+    /// IN took a payload that wasn't an int
+    NotAnInt,
+    DivisionByZero,
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::NotAnInt => write!(f, "took a payload that isn't an int"),
+            Fault::DivisionByZero => write!(f, "division by zero"),
+        }
+    }
+}
+
+/// How a processor reads payloads it takes and makes the ones it sends
+pub trait PayloadIo {
+    /// Consumes `payload`, returning its value if it is an int
+    fn take(&mut self, payload: Entity) -> Option<i64>;
+
+    fn spawn(&mut self, value: i64) -> Entity;
+}
+
+/// A tile that runs a small program, taking payloads from any side and
+/// sending payloads out of any side.  Each side holds at most one payload
+/// coming in and one going out.
+#[derive(Component, Debug, Reflect)]
+pub struct Processor {
+    source: String,
+    program: Program,
+    pc: usize,
+    registers: [i64; REGISTERS],
+    fault: Option<Fault>,
+    /// Indexed by `ConveyorDirection::index`
+    inbox: [Option<Entity>; CONVEYOR_DIRECTIONS.len()],
+    outbox: [Option<Entity>; CONVEYOR_DIRECTIONS.len()],
+}
+
+impl Default for Processor {
+    fn default() -> Self {
+        let program = Program::parse(DEFAULT_SOURCE).expect("Default program should parse");
+        Processor::new(DEFAULT_SOURCE.to_string(), program)
+    }
+}
+
+impl PayloadHandler for Processor {
+    fn try_transfer(
+        &mut self,
+        _: &Conveyor,
+        request: &RequestPayloadTransferEvent,
+    ) -> Option<Entity> {
+        let slot = &mut self.inbox[request.direction.opposite().index()];
+        if slot.is_some() {
+            return None;
+        }
+        *slot = Some(request.payload);
+        Some(request.payload)
+    }
+
+    fn remove_payload(&mut self, payload: Entity) {
+        for slot in self.inbox.iter_mut().chain(self.outbox.iter_mut()) {
+            if *slot == Some(payload) {
+                *slot = None;
+            }
+        }
+    }
+
+    fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
+        self.inbox
+            .iter()
+            .chain(self.outbox.iter())
+            .flatten()
+            .copied()
+    }
+}
+
+impl Processor {
+    pub fn new(source: String, program: Program) -> Self {
+        Processor {
+            source,
+            program,
+            pc: 0,
+            registers: [0; REGISTERS],
+            fault: None,
+            inbox: Default::default(),
+            outbox: Default::default(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn registers(&self) -> &[i64; REGISTERS] {
+        &self.registers
+    }
+
+    pub fn fault(&self) -> Option<Fault> {
+        self.fault
+    }
+
+    /// The source line of the next instruction to run
+    pub fn current_line(&self) -> Option<usize> {
+        self.program.line(self.pc)
+    }
+
+    /// Replaces the program and starts it from the top.  Payloads already
+    /// waiting on the sides stay.
+    pub fn load(&mut self, source: String, program: Program) {
+        self.source = source;
+        self.program = program;
+        self.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.pc = 0;
+        self.registers = [0; REGISTERS];
+        self.fault = None;
+    }
+
+    /// Runs up to `CYCLES_PER_TICK` instructions, stopping early if one has
+    /// to wait for a side or faults.
+    pub fn run(&mut self, io: &mut impl PayloadIo) {
+        if self.fault.is_some() || self.program.is_empty() {
+            return;
+        }
+
+        for _ in 0..CYCLES_PER_TICK {
+            match self.step(io) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(fault) => {
+                    self.fault = Some(fault);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Runs the instruction at `pc`, returning false if it has to wait
+    fn step(&mut self, io: &mut impl PayloadIo) -> Result<bool, Fault> {
+        let mut next = self.pc + 1;
+
+        match self.program.instructions[self.pc] {
+            Instruction::In(register, side) => {
+                let Some(payload) = self.inbox[side.index()].take() else {
+                    return Ok(false);
+                };
+                self.registers[register] = io.take(payload).ok_or(Fault::NotAnInt)?;
+            }
+            Instruction::Out(side, source) => {
+                if self.outbox[side.index()].is_some() {
+                    return Ok(false);
+                }
+                self.outbox[side.index()] = Some(io.spawn(self.read(source)));
+            }
+            Instruction::Mov(from, to) => {
+                if self.inbox[from.index()].is_none() || self.outbox[to.index()].is_some() {
+                    return Ok(false);
+                }
+                self.outbox[to.index()] = self.inbox[from.index()].take();
+            }
+            Instruction::Set(register, source) => {
+                self.registers[register] = self.read(source);
+            }
+            Instruction::Arithmetic(op, register, source) => {
+                let (left, right) = (self.registers[register], self.read(source));
+                self.registers[register] = match op {
+                    Arithmetic::Add => left.wrapping_add(right),
+                    Arithmetic::Sub => left.wrapping_sub(right),
+                    Arithmetic::Mul => left.wrapping_mul(right),
+                    Arithmetic::Div => left.checked_div(right).ok_or(Fault::DivisionByZero)?,
+                    Arithmetic::Mod => left.checked_rem(right).ok_or(Fault::DivisionByZero)?,
+                };
+            }
+            Instruction::Jump(condition, target) => {
+                let jump = match condition {
+                    JumpCondition::Always => true,
+                    JumpCondition::Zero(register) => self.registers[register] == 0,
+                    JumpCondition::NotZero(register) => self.registers[register] != 0,
+                    JumpCondition::Positive(register) => self.registers[register] > 0,
+                    JumpCondition::Negative(register) => self.registers[register] < 0,
+                };
+                if jump {
+                    next = target;
+                }
+            }
+            Instruction::Nop => {}
+        }
+
+        self.pc = next % self.program.instructions.len();
+        Ok(true)
+    }
+
+    fn read(&self, source: Source) -> i64 {
+        match source {
+            Source::Register(register) => self.registers[register],
+            Source::Constant(constant) => constant,
+        }
+    }
+}
+
+pub fn processor_bundle(map_type: &TilemapType) -> impl Bundle {
+    let mut conveyor = Conveyor::new(ConveyorDirections::all(map_type));
+    conveyor.set_inputs(ConveyorDirections::all(map_type));
+
+    (Processor::default(), conveyor)
+}
+
+#[derive(SystemParam)]
+struct ProcessorIo<'w, 's> {
+    commands: Commands<'w, 's>,
+    operands: Query<'w, 's, &'static Operand>,
+}
+
+impl PayloadIo for ProcessorIo<'_, '_> {
+    fn take(&mut self, payload: Entity) -> Option<i64> {
+        let value = match self.operands.get(payload) {
+            Ok(Operand(Value::Int(value))) => Some(*value),
+            _ => None,
+        };
+        self.commands.entity(payload).despawn();
+        value
+    }
+
+    fn spawn(&mut self, value: i64) -> Entity {
+        self.commands
+            .spawn(operand_bundle(Operand(Value::Int(value))))
+            .id()
+    }
+}
+
+fn run_processors(processors: Query<&mut Processor, Without<Disabled>>, mut io: ProcessorIo) {
+    for mut processor in processors {
+        processor.run(&mut io);
+    }
+}
+
+fn send_processor_payloads(
+    processors: Query<(Entity, &Processor, &TilePos)>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<BaseLayer>>,
+    mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
+) {
+    let (tile_storage, map_size, map_type) = base.into_inner();
+
+    for (source, processor, tile_pos) in processors {
+        // Payloads sent out of sides the map doesn't have stay put
+        for &direction in conveyor_directions(map_type) {
+            let Some(payload) = processor.outbox[direction.index()] else {
+                continue;
+            };
+
+            let destination_pos = direction.offset(tile_pos, map_size);
+            if let Some(destination_pos) = destination_pos
+                && let Some(destination) = tile_storage.get(&destination_pos)
+            {
+                send_payloads.write(RequestPayloadTransferEvent {
+                    payload,
+                    source,
+                    destination,
+                    destination_pos,
+                    direction,
+                });
+            }
+        }
+    }
+}
+
+fn update_processor_tiles(
+    mut commands: Commands,
+    new_processors: Query<Entity, (With<Processor>, Without<TileTextureIndex>)>,
+    tilemap_entity: Single<Entity, (With<BaseLayer>, With<ChunkedTileStorage>)>,
+) {
+    for new_processor in new_processors {
+        commands.entity(new_processor).insert_if_new(TileBundle {
+            tilemap_id: TilemapId(*tilemap_entity),
+            texture_index: GameSprite::Processor.tile_texture_index(),
+            ..default()
+        });
+    }
+}
+
+/// Payloads coming in wait near the edge they came in by, and payloads
+/// going out near the edge they leave by.
+fn update_processor_transforms(
+    processors: Query<(&TilePos, &Processor)>,
+    mut payloads: Query<&mut Transform, With<Payload>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (tile_pos, processor) in processors {
+        let tile_center = base.center_in_world(tile_pos);
+
+        for direction in CONVEYOR_DIRECTIONS {
+            let slots = [
+                (
+                    processor.inbox[direction.index()],
+                    Some(direction),
+                    None,
+                    0.25,
+                ),
+                (
+                    processor.outbox[direction.index()],
+                    None,
+                    Some(direction),
+                    0.75,
+                ),
+            ];
+            for (payload, from, to, mu) in slots {
+                if let Some(payload) = payload
+                    && let Ok(mut transform) = payloads.get_mut(payload)
+                {
+                    *transform = get_payload_transform(tile_center, base.grid_size, from, to, mu);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod processor_test {
+    use super::*;
+
+    /// Payloads are fake entities whose index is their value
+    #[derive(Default)]
+    struct TestIo {
+        spawned: Vec<i64>,
+    }
+
+    impl PayloadIo for TestIo {
+        fn take(&mut self, payload: Entity) -> Option<i64> {
+            Some(payload.index() as i64)
+        }
+
+        fn spawn(&mut self, value: i64) -> Entity {
+            self.spawned.push(value);
+            Entity::from_raw(value as u32)
+        }
+    }
+
+    fn processor(source: &str) -> Processor {
+        Processor::new(source.to_string(), Program::parse(source).unwrap())
+    }
+
+    fn parse_error(source: &str) -> ParseError {
+        Program::parse(source).unwrap_err()
+    }
+
+    #[test]
+    fn parses_instructions_and_labels() {
+        let program = Program::parse(
+            "; doubles\n\
+             start: IN r0, w\n\
+             \n\
+             mul R0, 2\n\
+             JNZ r0, end\n\
+             JMP start\n\
+             end:\n\
+             OUT east, r0 ; done",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program.instructions,
+            vec![
+                Instruction::In(0, ConveyorDirection::West),
+                Instruction::Arithmetic(Arithmetic::Mul, 0, Source::Constant(2)),
+                Instruction::Jump(JumpCondition::NotZero(0), 4),
+                Instruction::Jump(JumpCondition::Always, 0),
+                Instruction::Out(ConveyorDirection::East, Source::Register(0)),
+            ]
+        );
+        assert_eq!(program.line(2), Some(5));
+    }
+
+    #[test]
+    fn reports_errors_by_line() {
+        assert_eq!(parse_error("NOP\nFOO r0").line, 2);
+        assert_eq!(parse_error("SET r4, 1").line, 1);
+        assert_eq!(parse_error("IN r0, up").line, 1);
+        assert_eq!(parse_error("JMP nowhere").line, 1);
+        assert_eq!(parse_error("a:\na:").line, 2);
+        assert_eq!(parse_error("ADD r0").line, 1);
+        assert_eq!(parse_error(&"NOP\n".repeat(MAX_INSTRUCTIONS + 1)).line, 33);
+    }
+
+    #[test]
+    fn runs_until_blocked() {
+        let mut processor = processor("IN r0, W\nADD r0, 5\nMUL r0, r0\nOUT E, r0");
+        let mut io = TestIo::default();
+
+        // Waits for input
+        processor.run(&mut io);
+        assert_eq!(processor.current_line(), Some(1));
+
+        processor.inbox[ConveyorDirection::West.index()] = Some(Entity::from_raw(3));
+        processor.run(&mut io);
+        assert_eq!(io.spawned, vec![64]);
+        assert!(processor.outbox[ConveyorDirection::East.index()].is_some());
+
+        // The next result waits for the first to leave
+        processor.inbox[ConveyorDirection::West.index()] = Some(Entity::from_raw(1));
+        processor.run(&mut io);
+        assert_eq!(io.spawned, vec![64]);
+        assert_eq!(processor.current_line(), Some(4));
+    }
+
+    #[test]
+    fn cycle_budget_limits_each_tick() {
+        let mut processor = processor("loop: ADD r0, 1\nJMP loop");
+        processor.run(&mut TestIo::default());
+        assert_eq!(processor.registers()[0], CYCLES_PER_TICK as i64 / 2);
+    }
+
+    #[test]
+    fn conditional_jumps() {
+        let mut processor = processor(
+            "SET r0, -2\n\
+             loop: ADD r0, 1\n\
+             JLZ r0, loop\n\
+             JZ r0, zero\n\
+             SET r1, 99\n\
+             zero: SET r2, 7\n\
+             end: JMP end",
+        );
+        processor.run(&mut TestIo::default());
+        processor.run(&mut TestIo::default());
+        assert_eq!(processor.registers(), &[0, 0, 7, 0]);
+    }
+
+    #[test]
+    fn division_by_zero_faults() {
+        let mut processor = processor("SET r0, 1\nDIV r0, r1\nSET r2, 1");
+        processor.run(&mut TestIo::default());
+        assert_eq!(processor.fault(), Some(Fault::DivisionByZero));
+        assert_eq!(processor.current_line(), Some(2));
+        assert_eq!(processor.registers()[2], 0);
+
+        processor.reset();
+        assert_eq!(processor.fault(), None);
+    }
+
+    #[test]
+    fn arithmetic_wraps() {
+        let mut processor = processor(&format!("SET r0, {}\nADD r0, 1\nend: JMP end", i64::MAX));
+        processor.run(&mut TestIo::default());
+        assert_eq!(processor.registers()[0], i64::MIN);
+    }
+}
@@ -0,0 +1,157 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        chunks::ChunkedTileStorage,
+        interaction::{HoveredTile, Tools},
+        processor::{CYCLES_PER_TICK, Processor, Program},
+    },
+};
+
+pub fn processor_panel_plugin(app: &mut App) {
+    app.init_resource::<ProcessorPanel>()
+        .add_systems(
+            Update,
+            select_processor
+                .run_if(input_just_pressed(MouseButton::Left))
+                .run_if(not(egui_wants_any_input))
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_processor_panel.run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_processor_panel);
+}
+
+const INSTRUCTIONS_HELP: &str = "\
+IN r, side      take an int payload into a register
+OUT side, x     send x out as a new payload
+MOV side, side  pass a payload straight through
+SET r, x
+ADD / SUB / MUL / DIV / MOD r, x
+JMP label
+JZ / JNZ / JGZ / JLZ r, label
+NOP
+
+Registers are r0 to r3, sides N, S, E and W (on hex maps
+E, W, NE, NW, SE and SW), and x is a register or a
+number.  Write labels as \"name:\" and comments after \";\".";
+
+/// Which processor is being programmed, and the source being edited, which
+/// is only loaded once it parses.
+#[derive(Resource, Default)]
+struct ProcessorPanel {
+    processor: Option<Entity>,
+    source: String,
+    error: Option<String>,
+}
+
+/// Clicking on a processor with no tool selected opens its panel
+fn select_processor(
+    tools: Res<Tools>,
+    hovered_tile: Single<&TilePos, With<HoveredTile>>,
+    tile_storage: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    processors: Query<&Processor>,
+    mut panel: ResMut<ProcessorPanel>,
+) {
+    if tools.current_tool().is_some() {
+        return;
+    }
+
+    let selected = tile_storage
+        .get(*hovered_tile)
+        .and_then(|entity| Some((entity, processors.get(entity).ok()?)));
+
+    panel.processor = selected.map(|(entity, _)| entity);
+    if let Some((_, processor)) = selected {
+        panel.source = processor.source().to_string();
+        panel.error = None;
+    }
+}
+
+fn close_processor_panel(mut panel: ResMut<ProcessorPanel>) {
+    panel.processor = None;
+}
+
+fn show_processor_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<ProcessorPanel>,
+    mut processors: Query<(&mut Processor, &TilePos)>,
+) -> Result {
+    let Some(entity) = panel.processor else {
+        return Ok(());
+    };
+    let Ok((mut processor, tile_pos)) = processors.get_mut(entity) else {
+        // The processor has been cleared away
+        panel.processor = None;
+        return Ok(());
+    };
+
+    let mut open = true;
+    egui::Window::new(format!("Processor at {}, {}", tile_pos.x, tile_pos.y))
+        .open(&mut open)
+        .resizable(false)
+        .show(contexts.ctx_mut()?, |ui| {
+            let panel = &mut *panel;
+            let editor = egui::TextEdit::multiline(&mut panel.source)
+                .code_editor()
+                .desired_rows(12)
+                .desired_width(280.0);
+            if ui.add(editor).changed() {
+                match Program::parse(&panel.source) {
+                    Ok(program) => {
+                        processor.load(panel.source.clone(), program);
+                        panel.error = None;
+                    }
+                    Err(error) => panel.error = Some(error.to_string()),
+                }
+            }
+
+            if let Some(error) = &panel.error {
+                ui.colored_label(egui::Color32::LIGHT_RED, error);
+            }
+
+            ui.separator();
+            match (processor.fault(), processor.current_line()) {
+                (Some(fault), Some(line)) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, format!("Line {line}: {fault}"));
+                }
+                (None, Some(line)) => {
+                    ui.label(format!("Running line {line}"));
+                }
+                _ => {
+                    ui.label("No program");
+                }
+            }
+            let registers = processor
+                .registers()
+                .iter()
+                .enumerate()
+                .map(|(i, value)| format!("r{i}: {value}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            ui.monospace(registers);
+
+            if ui.button("Reset").clicked() {
+                processor.reset();
+            }
+
+            egui::CollapsingHeader::new("Instructions").show(ui, |ui| {
+                ui.monospace(INSTRUCTIONS_HELP);
+                ui.label(format!(
+                    "Up to {CYCLES_PER_TICK} instructions run each tick."
+                ));
+            });
+        });
+
+    if !open {
+        panel.processor = None;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    factory_game::{
+        ConveyorSystems,
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool, Tools},
+        sink::{PayloadSunkEvent, Sink},
+        value::Value,
+    },
+    helpers::asset_path,
+    sprite_sheet::GameSprite,
+};
+
+pub fn research_plugin(app: &mut App) {
+    app.insert_resource(ResearchTree::load(asset_path(RESEARCH_PATH)))
+        .register_place_tile_event::<PlaceResearchSinkEvent>()
+        .add_systems(
+            Update,
+            (
+                record_research
+                    .run_if(resource_exists::<Research>)
+                    .after(ConveyorSystems::TransportLogic)
+                    .before(ConveyorSystems::PayloadTransforms),
+                lock_unresearched_tools.run_if(
+                    resource_exists::<Research>
+                        .and(resource_exists::<Tools>)
+                        .and(resource_changed::<Research>.or(resource_added::<Tools>)),
+                ),
+            ),
+        );
+}
+
+/// In the assets directory
+const RESEARCH_PATH: &str = "research.json";
+
+pub struct ResearchSinkTool;
+
+impl Tool for ResearchSinkTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (GameSprite::ResearchSink, TileFlip::default())
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceResearchSinkEvent(TilePos::default()).cost()
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(PlaceResearchSinkEvent(*tile_pos));
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct PlaceResearchSinkEvent(pub TilePos);
+
+impl PlaceTileEvent for PlaceResearchSinkEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    fn cost(&self) -> u32 {
+        10
+    }
+
+    fn configure_new_entity(&self, mut commands: EntityCommands) {
+        commands.insert((Sink::default(), ResearchSink, Name::new("Research Sink")));
+    }
+}
+
+/// Payloads delivered to a research sink count towards research
+#[derive(Component, Debug)]
+pub struct ResearchSink;
+
+/// Lasting improvements that research can give, besides unlocking tools
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Upgrade {
+    /// Generators emit payloads twice as often
+    FastGenerators,
+}
+
+impl Upgrade {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Upgrade::FastGenerators => "Generators are twice as fast",
+        }
+    }
+}
+
+/// Research is finished by delivering `quantity` payloads of `value` to
+/// research sinks, once the research it requires is finished.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ResearchNode {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub requires: Option<String>,
+    pub value: Value,
+    pub quantity: u32,
+    /// Slots of the tools that are locked until this is finished
+    #[serde(default)]
+    pub tools: Vec<u32>,
+    #[serde(default)]
+    pub upgrades: Vec<Upgrade>,
+}
+
+/// All the research there is, each node requiring at most one other
+#[derive(Resource, Deserialize, Default, Debug)]
+pub struct ResearchTree(Vec<ResearchNode>);
+
+impl ResearchTree {
+    fn load(path: impl AsRef<Path>) -> Self {
+        fs::read_to_string(path)
+            .inspect_err(|e| warn!("Can't read research: {e}"))
+            .ok()
+            .and_then(|json| {
+                serde_json::from_str(&json)
+                    .inspect_err(|e| warn!("Can't parse research: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Research that requires `parent`, or doesn't require anything if
+    /// `parent` is None
+    pub fn children(&self, parent: Option<&str>) -> impl Iterator<Item = &ResearchNode> {
+        self.0
+            .iter()
+            .filter(move |node| node.requires.as_deref() == parent)
+    }
+
+    fn get(&self, id: &str) -> Option<&ResearchNode> {
+        self.0.iter().find(|node| node.id == id)
+    }
+}
+
+/// Progress through the research tree.  There's no research in levels.
+#[derive(Resource, Default, Debug)]
+pub struct Research {
+    delivered: HashMap<String, u32>,
+    upgrades: HashSet<Upgrade>,
+}
+
+impl Research {
+    pub fn delivered(&self, node: &ResearchNode) -> u32 {
+        self.delivered.get(&node.id).copied().unwrap_or(0)
+    }
+
+    pub fn is_finished(&self, node: &ResearchNode) -> bool {
+        self.delivered(node) >= node.quantity
+    }
+
+    /// Whether the research that `node` requires is finished
+    pub fn is_available(&self, tree: &ResearchTree, node: &ResearchNode) -> bool {
+        node.requires
+            .as_ref()
+            .is_none_or(|id| tree.get(id).is_some_and(|node| self.is_finished(node)))
+    }
+
+    pub fn is_tool_unlocked(&self, tree: &ResearchTree, slot: u32) -> bool {
+        tree.0
+            .iter()
+            .filter(|node| node.tools.contains(&slot))
+            .all(|node| self.is_finished(node))
+    }
+
+    pub fn has_upgrade(&self, upgrade: Upgrade) -> bool {
+        self.upgrades.contains(&upgrade)
+    }
+
+    /// Counts `value` towards the first available research that wants it,
+    /// returning whether there was any.
+    pub fn deliver(&mut self, tree: &ResearchTree, value: &Value) -> bool {
+        let Some(node) = tree.0.iter().find(|node| {
+            node.value == *value && !self.is_finished(node) && self.is_available(tree, node)
+        }) else {
+            return false;
+        };
+
+        *self.delivered.entry(node.id.clone()).or_default() += 1;
+        if self.is_finished(node) {
+            self.upgrades.extend(&node.upgrades);
+        }
+        true
+    }
+}
+
+fn record_research(
+    mut sunk: EventReader<PayloadSunkEvent>,
+    research_sinks: Query<(), With<ResearchSink>>,
+    tree: Res<ResearchTree>,
+    mut research: ResMut<Research>,
+) {
+    for event in sunk.read() {
+        if research_sinks.contains(event.sink)
+            && let Some(value) = &event.value
+        {
+            research.deliver(&tree, value);
+        }
+    }
+}
+
+fn lock_unresearched_tools(
+    tree: Res<ResearchTree>,
+    research: Res<Research>,
+    mut tools: ResMut<Tools>,
+) {
+    tools.set_locked(|slot| !research.is_tool_unlocked(&tree, slot));
+}
+
+#[cfg(test)]
+mod research_test {
+    use super::*;
+
+    fn tree() -> ResearchTree {
+        serde_json::from_str(
+            r#"[
+                { "id": "a", "name": "A", "value": "1", "quantity": 2, "tools": [8] },
+                { "id": "b", "name": "B", "requires": "a", "value": "1", "quantity": 1,
+                  "tools": [9], "upgrades": ["fast_generators"] }
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn deliveries_unlock_in_order() {
+        let tree = tree();
+        let mut research = Research::default();
+
+        assert!(!research.is_tool_unlocked(&tree, 8));
+        assert!(research.is_tool_unlocked(&tree, 7));
+        assert!(!research.deliver(&tree, &Value::Int(2)));
+
+        assert!(research.deliver(&tree, &Value::Int(1)));
+        assert!(research.deliver(&tree, &Value::Int(1)));
+        assert!(research.is_tool_unlocked(&tree, 8));
+        assert!(!research.is_tool_unlocked(&tree, 9));
+
+        // B needs A finished first, and then takes the next delivery
+        assert!(research.deliver(&tree, &Value::Int(1)));
+        assert!(research.is_tool_unlocked(&tree, 9));
+        assert!(research.has_upgrade(Upgrade::FastGenerators));
+
+        assert!(!research.deliver(&tree, &Value::Int(1)));
+    }
+
+    #[test]
+    fn research_file_parses() {
+        let tree = ResearchTree::load(asset_path(RESEARCH_PATH));
+        assert!(tree.children(None).count() > 0);
+        for node in &tree.0 {
+            assert!(
+                node.requires
+                    .as_ref()
+                    .is_none_or(|id| tree.get(id).is_some())
+            );
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::research::{Research, ResearchTree},
+};
+
+pub fn research_panel_plugin(app: &mut App) {
+    app.init_resource::<ResearchPanel>()
+        .add_systems(
+            Update,
+            toggle_research_panel
+                .run_if(input_just_pressed(KeyCode::KeyR))
+                .run_if(not(egui_wants_any_input))
+                .run_if(in_state(GameState::FactoryGame))
+                .run_if(resource_exists::<Research>),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_research_panel
+                .run_if(in_state(GameState::FactoryGame))
+                .run_if(resource_exists::<Research>),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_research_panel);
+}
+
+#[derive(Resource, Default)]
+struct ResearchPanel {
+    open: bool,
+}
+
+fn toggle_research_panel(mut panel: ResMut<ResearchPanel>) {
+    panel.open = !panel.open;
+}
+
+fn close_research_panel(mut panel: ResMut<ResearchPanel>) {
+    panel.open = false;
+}
+
+fn show_research_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<ResearchPanel>,
+    tree: Res<ResearchTree>,
+    research: Res<Research>,
+) -> Result {
+    if !panel.open {
+        return Ok(());
+    }
+
+    egui::Window::new("Research (R)")
+        .open(&mut panel.open)
+        .show(contexts.ctx_mut()?, |ui| {
+            ui.label("Deliver values to a research sink");
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                show_research_nodes(ui, &tree, &research, None);
+            });
+        });
+
+    Ok(())
+}
+
+/// Shows the research requiring `parent`, each followed by what requires it
+fn show_research_nodes(
+    ui: &mut egui::Ui,
+    tree: &ResearchTree,
+    research: &Research,
+    parent: Option<&str>,
+) {
+    for node in tree.children(parent) {
+        let status = if research.is_finished(node) {
+            "done"
+        } else if research.is_available(tree, node) {
+            "in progress"
+        } else {
+            "locked"
+        };
+
+        egui::CollapsingHeader::new(format!("{} ({status})", node.name))
+            .id_salt(&node.id)
+            .default_open(true)
+            .show(ui, |ui| {
+                let delivered = research.delivered(node).min(node.quantity);
+                ui.add(
+                    egui::ProgressBar::new(delivered as f32 / node.quantity.max(1) as f32).text(
+                        format!(
+                            "{delivered}/{} x {}",
+                            node.quantity,
+                            node.value.source_text()
+                        ),
+                    ),
+                );
+
+                if !node.tools.is_empty() {
+                    let slots: Vec<_> = node.tools.iter().map(u32::to_string).collect();
+                    ui.label(format!("Unlocks tools {}", slots.join(", ")));
+                }
+                for upgrade in &node.upgrades {
+                    ui.label(upgrade.description());
+                }
+
+                show_research_nodes(ui, tree, research, Some(&node.id));
+            });
+    }
+}
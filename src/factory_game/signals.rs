@@ -0,0 +1,462 @@
+use std::{collections::HashSet, fmt::Display};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer, ConveyorSystems, MapConfig,
+        chunks::ChunkedTileStorage,
+        conveyor_belts::ConveyorBelt,
+        helpers::get_neighboring_positions,
+        interaction::{ClearTileEvent, Tool},
+        payloads::PayloadTransportLine,
+        sink::Sink,
+    },
+    helpers::TilemapQuery,
+    sprite_sheet::{GameSprite, SpriteSheet},
+};
+
+pub fn signals_plugin(app: &mut App) {
+    app.register_type::<SignalTile>()
+        .add_event::<PlaceSignalTileEvent>()
+        .add_observer(place_signal_tile)
+        .add_observer(clear_signal_tile)
+        .add_systems(
+            Update,
+            (
+                (update_signals, spawn_signal_labels)
+                    .in_set(ConveyorSystems::TileUpdater)
+                    .run_if(any_with_component::<SignalLayer>),
+                (
+                    update_signal_tile_colors,
+                    update_signal_labels,
+                    tint_disabled_tiles,
+                )
+                    .in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+/// The biggest constant a condition can compare against
+const MAX_CONSTANT: i64 = 9;
+
+/// Wires, sensors and conditions are drawn on their own layer, over the
+/// machines they read or control.
+#[derive(Component)]
+pub struct SignalLayer;
+
+pub fn make_signal_layer(
+    mut commands: Commands,
+    sprite_sheet: Res<SpriteSheet>,
+    config: Res<MapConfig>,
+) {
+    commands.spawn((
+        SignalLayer,
+        ChunkedTileStorage::default(),
+        super::make_layer(&config, sprite_sheet.image(), 1.0, "SignalLayer"),
+    ));
+}
+
+/// Connected signal tiles form a network, whose signal is the sum of what
+/// all its sensors read.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+#[require(Signal, SignalLabels)]
+pub enum SignalTile {
+    Wire,
+    /// Reads how many payloads are on the belt below it, or how many the
+    /// sink below it has sunk
+    Sensor,
+    /// Disables the generator, belt or distributor below it while the
+    /// condition isn't met
+    Condition(Condition),
+}
+
+impl SignalTile {
+    fn sprite(&self) -> GameSprite {
+        match self {
+            SignalTile::Wire => GameSprite::Wire,
+            SignalTile::Sensor => GameSprite::Sensor,
+            SignalTile::Condition(_) => GameSprite::Condition,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SignalTile::Wire => "Wire",
+            SignalTile::Sensor => "Sensor",
+            SignalTile::Condition(_) => "Condition",
+        }
+    }
+}
+
+/// The signal of the network a signal tile is on
+#[derive(Component, Debug, Default, Reflect)]
+pub struct Signal(pub i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum Comparison {
+    Greater,
+    Less,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn next(&self) -> Self {
+        match self {
+            Comparison::Greater => Comparison::Less,
+            Comparison::Less => Comparison::Equal,
+            Comparison::Equal => Comparison::NotEqual,
+            Comparison::NotEqual => Comparison::Greater,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub struct Condition {
+    pub comparison: Comparison,
+    pub constant: i64,
+}
+
+impl Default for Condition {
+    fn default() -> Self {
+        Condition {
+            comparison: Comparison::Greater,
+            constant: 0,
+        }
+    }
+}
+
+impl Condition {
+    pub fn is_met(&self, signal: i64) -> bool {
+        match self.comparison {
+            Comparison::Greater => signal > self.constant,
+            Comparison::Less => signal < self.constant,
+            Comparison::Equal => signal == self.constant,
+            Comparison::NotEqual => signal != self.constant,
+        }
+    }
+
+    /// Steps the constant, moving on to the next comparison after the
+    /// biggest constant
+    fn next(&self) -> Self {
+        if self.constant < MAX_CONSTANT {
+            Condition {
+                constant: self.constant + 1,
+                ..*self
+            }
+        } else {
+            Condition {
+                comparison: self.comparison.next(),
+                constant: 0,
+            }
+        }
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let comparison = match self.comparison {
+            Comparison::Greater => ">",
+            Comparison::Less => "<",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "!=",
+        };
+        write!(f, "{comparison}{}", self.constant)
+    }
+}
+
+pub struct SignalTool(SignalTile);
+
+impl SignalTool {
+    pub fn wire() -> Self {
+        SignalTool(SignalTile::Wire)
+    }
+
+    pub fn sensor() -> Self {
+        SignalTool(SignalTile::Sensor)
+    }
+
+    pub fn condition() -> Self {
+        SignalTool(SignalTile::Condition(Condition::default()))
+    }
+}
+
+impl Tool for SignalTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (self.0.sprite(), TileFlip::default())
+    }
+
+    fn next_variant(&mut self, _: &TilemapType) {
+        if let SignalTile::Condition(condition) = &mut self.0 {
+            *condition = condition.next();
+        }
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(PlaceSignalTileEvent(*tile_pos, self.0));
+    }
+}
+
+/// Places a tile on the signal layer.  Placing the same tile again removes
+/// it.
+#[derive(Event, Debug)]
+pub struct PlaceSignalTileEvent(pub TilePos, pub SignalTile);
+
+fn place_signal_tile(
+    trigger: Trigger<PlaceSignalTileEvent>,
+    mut commands: Commands,
+    layer: Single<(Entity, &mut ChunkedTileStorage, &TilemapSize), With<SignalLayer>>,
+    signal_tiles: Query<&SignalTile>,
+) {
+    let PlaceSignalTileEvent(tile_pos, tile) = *trigger.event();
+    let (layer_entity, mut storage, map_size) = layer.into_inner();
+
+    if !tile_pos.within_map_bounds(map_size) {
+        return;
+    }
+
+    if let Some(existing) = storage.remove(&tile_pos) {
+        commands.entity(existing).despawn();
+        if signal_tiles.get(existing) == Ok(&tile) {
+            return;
+        }
+    }
+
+    let entity = commands
+        .spawn((
+            StateScoped(GameState::FactoryGame),
+            Name::new(tile.name()),
+            tile,
+            TileBundle {
+                position: tile_pos,
+                tilemap_id: TilemapId(layer_entity),
+                texture_index: tile.sprite().tile_texture_index(),
+                ..default()
+            },
+        ))
+        .id();
+    storage.set(&tile_pos, entity);
+}
+
+/// Clearing a tile clears any signal tile on it too
+fn clear_signal_tile(
+    trigger: Trigger<ClearTileEvent>,
+    mut commands: Commands,
+    mut storage: Single<&mut ChunkedTileStorage, With<SignalLayer>>,
+) {
+    if let Some(entity) = storage.remove(&trigger.event().0) {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Machines with this don't do anything, a condition above them not being
+/// met
+#[derive(Component, Debug)]
+pub struct Disabled;
+
+/// Finds every network, works out its signal, and disables the machines
+/// below conditions that aren't met.
+fn update_signals(
+    mut commands: Commands,
+    layer: Single<(&ChunkedTileStorage, &TilemapSize, &TilemapType), With<SignalLayer>>,
+    base: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    mut signal_tiles: Query<(Entity, &TilePos, &SignalTile, &mut Signal)>,
+    belts: Query<&PayloadTransportLine, With<ConveyorBelt>>,
+    sinks: Query<&Sink>,
+    disabled: Query<Entity, With<Disabled>>,
+) {
+    let (storage, map_size, map_type) = layer.into_inner();
+
+    let reading = |tile_pos: &TilePos| -> i64 {
+        let Some(machine) = base.get(tile_pos) else {
+            return 0;
+        };
+        if let Ok(belt) = belts.get(machine) {
+            belt.count() as i64
+        } else if let Ok(sink) = sinks.get(machine) {
+            sink.tally() as i64
+        } else {
+            0
+        }
+    };
+
+    let mut visited = HashSet::new();
+    let mut to_disable = HashSet::new();
+    let starts: Vec<_> = signal_tiles.iter().map(|(e, pos, ..)| (e, *pos)).collect();
+
+    for (start, start_pos) in starts {
+        if !visited.insert(start) {
+            continue;
+        }
+
+        let network = find_network(start, start_pos, storage, map_size, map_type, &mut visited);
+
+        let signal: i64 = network
+            .iter()
+            .filter(|(entity, _)| {
+                signal_tiles
+                    .get(*entity)
+                    .is_ok_and(|(_, _, tile, _)| *tile == SignalTile::Sensor)
+            })
+            .map(|(_, pos)| reading(pos))
+            .sum();
+
+        for (entity, pos) in network {
+            let Ok((_, _, tile, mut tile_signal)) = signal_tiles.get_mut(entity) else {
+                continue;
+            };
+            if tile_signal.0 != signal {
+                tile_signal.0 = signal;
+            }
+            if let SignalTile::Condition(condition) = tile
+                && !condition.is_met(signal)
+                && let Some(machine) = base.get(&pos)
+            {
+                to_disable.insert(machine);
+            }
+        }
+    }
+
+    for entity in &disabled {
+        if !to_disable.remove(&entity) {
+            commands.entity(entity).remove::<Disabled>();
+        }
+    }
+    for entity in to_disable {
+        commands.entity(entity).insert(Disabled);
+    }
+}
+
+/// The signal tiles connected to `start`, marking them as visited
+fn find_network(
+    start: Entity,
+    start_pos: TilePos,
+    storage: &ChunkedTileStorage,
+    map_size: &TilemapSize,
+    map_type: &TilemapType,
+    visited: &mut HashSet<Entity>,
+) -> Vec<(Entity, TilePos)> {
+    let mut network = vec![];
+    let mut to_visit = vec![(start, start_pos)];
+
+    while let Some((entity, pos)) = to_visit.pop() {
+        network.push((entity, pos));
+        for neighbor in get_neighboring_positions(&pos, map_size, map_type).iter() {
+            if let Some(neighbor_entity) = storage.get(neighbor)
+                && visited.insert(neighbor_entity)
+            {
+                to_visit.push((neighbor_entity, *neighbor));
+            }
+        }
+    }
+
+    network
+}
+
+#[derive(Component, Default)]
+#[relationship_target(relationship = SignalLabel, linked_spawn)]
+pub struct SignalLabels(Vec<Entity>);
+
+/// Mark the text showing a sensor's signal or a condition so it is despawned
+/// along with the signal tile
+#[derive(Component)]
+#[relationship(relationship_target = SignalLabels)]
+pub struct SignalLabel(Entity);
+
+fn spawn_signal_labels(
+    mut commands: Commands,
+    new_tiles: Query<(Entity, &TilePos, &SignalTile), Added<SignalTile>>,
+    layer: Single<TilemapQuery, With<SignalLayer>>,
+) {
+    for (entity, tile_pos, tile) in new_tiles {
+        if *tile == SignalTile::Wire {
+            continue;
+        }
+
+        let tile_center = layer.center_in_world(tile_pos);
+        commands.spawn((
+            Name::new("Signal Label"),
+            Text2d::default(),
+            TextFont::from_font_size(10.0),
+            Transform::from_translation(
+                (tile_center + Vec2::new(0.0, layer.tile_size.y / 3.0)).extend(4.0),
+            ),
+            SignalLabel(entity),
+        ));
+    }
+}
+
+fn update_signal_labels(
+    signal_tiles: Query<(&SignalTile, &Signal)>,
+    labels: Query<(&SignalLabel, &mut Text2d)>,
+) {
+    for (label, mut text) in labels {
+        if let Ok((tile, signal)) = signal_tiles.get(label.0) {
+            let new_text = match tile {
+                SignalTile::Condition(condition) => format!("{condition}"),
+                _ => format!("{}", signal.0),
+            };
+            if text.0 != new_text {
+                text.0 = new_text;
+            }
+        }
+    }
+}
+
+/// Live wires are brighter, and conditions that aren't met are red
+fn update_signal_tile_colors(signal_tiles: Query<(&SignalTile, &Signal, &mut TileColor)>) {
+    for (tile, signal, mut color) in signal_tiles {
+        let new_color = match tile {
+            SignalTile::Condition(condition) if !condition.is_met(signal.0) => {
+                Color::srgb(1.0, 0.4, 0.4)
+            }
+            _ if signal.0 != 0 => Color::WHITE,
+            _ => Color::srgb(0.6, 0.6, 0.6),
+        };
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+    }
+}
+
+fn tint_disabled_tiles(
+    mut tiles: Query<&mut TileColor, Without<Sink>>,
+    disabled: Query<Entity, Added<Disabled>>,
+    mut enabled: RemovedComponents<Disabled>,
+) {
+    for entity in &disabled {
+        if let Ok(mut color) = tiles.get_mut(entity) {
+            color.0 = Color::srgb(0.4, 0.4, 0.4);
+        }
+    }
+    for entity in enabled.read() {
+        if let Ok(mut color) = tiles.get_mut(entity) {
+            color.0 = Color::WHITE;
+        }
+    }
+}
+
+#[cfg(test)]
+mod signal_test {
+    use super::*;
+
+    #[test]
+    fn conditions() {
+        let condition = Condition {
+            comparison: Comparison::Less,
+            constant: 3,
+        };
+        assert!(condition.is_met(2));
+        assert!(!condition.is_met(3));
+        assert_eq!(format!("{condition}"), "<3");
+
+        let last = Condition {
+            comparison: Comparison::NotEqual,
+            constant: MAX_CONSTANT,
+        };
+        assert_eq!(last.next(), Condition::default());
+    }
+}
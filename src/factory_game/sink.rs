@@ -5,11 +5,15 @@ use smallvec::SmallVec;
 use crate::{
     factory_game::{
         BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
         conveyor::Conveyor,
         helpers::{ConveyorDirection, ConveyorDirections},
         interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
+        operators::{ErrorPayload, Operand},
         payload_handler::{AddPayloadHandler, PayloadHandler},
         payloads::{Payload, RequestPayloadTransferEvent, get_payload_transform},
+        research::ResearchSink,
+        value::Value,
     },
     helpers::TilemapQuery,
     sprite_sheet::GameSprite,
@@ -18,45 +22,91 @@ use crate::{
 pub fn sink_plugin(app: &mut App) {
     app.register_place_tile_event::<PlaceSinkEvent>()
         .add_payload_handler::<Sink>()
+        .add_event::<PayloadSunkEvent>()
         .add_systems(
             Update,
             (
-                update_sink_tiles.in_set(ConveyorSystems::TileUpdater),
+                (update_sink_tiles, update_jammed_sink_tiles).in_set(ConveyorSystems::TileUpdater),
                 update_sinks.in_set(ConveyorSystems::TransportLogic),
                 update_sink_transforms.in_set(ConveyorSystems::PayloadTransforms),
             ),
         );
 }
 
-pub struct SinkTool;
+/// The variant decides whether the sink accepts error payloads.
+#[derive(Default)]
+pub struct SinkTool(bool);
 
 impl Tool for SinkTool {
     fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
-        (GameSprite::Sink, TileFlip::default())
+        (sink_sprite(self.0), TileFlip::default())
+    }
+
+    fn next_variant(&mut self, _: &TilemapType) {
+        self.0 = !self.0;
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceSinkEvent(TilePos::default(), self.0).cost()
     }
 
     fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
-        commands.trigger(PlaceSinkEvent(*tile_pos));
+        commands.trigger(PlaceSinkEvent(*tile_pos, self.0));
     }
 }
 
 #[derive(Event, Debug)]
-pub struct PlaceSinkEvent(TilePos);
+pub struct PlaceSinkEvent(pub TilePos, pub bool);
 
 impl PlaceTileEvent for PlaceSinkEvent {
     fn tile_pos(&self) -> TilePos {
         self.0
     }
 
+    fn cost(&self) -> u32 {
+        10
+    }
+
     fn configure_new_entity(&self, mut commands: EntityCommands) {
-        commands.insert((Sink::default(), Name::new("Sink")));
+        commands.insert((Sink::new(self.1), Name::new("Sink")));
+    }
+}
+
+fn sink_sprite(accepts_errors: bool) -> GameSprite {
+    if accepts_errors {
+        GameSprite::SinkAcceptsErrors
+    } else {
+        GameSprite::Sink
     }
 }
 
+/// A sink that doesn't accept errors jams when it is given one: the error
+/// payload stays put and nothing else is accepted until the sink is replaced.
 #[derive(Component, Reflect, Default)]
 #[require(Conveyor::new(ConveyorDirections::default()))]
-struct Sink {
+pub struct Sink {
     payloads: SmallVec<[(Entity, ConveyorDirection, f32); 4]>,
+    accepts_errors: bool,
+    jammed: bool,
+    /// How many payloads have been sunk
+    tally: u32,
+}
+
+impl Sink {
+    pub fn new(accepts_errors: bool) -> Self {
+        Sink {
+            accepts_errors,
+            ..default()
+        }
+    }
+
+    pub fn is_jammed(&self) -> bool {
+        self.jammed
+    }
+
+    pub fn tally(&self) -> u32 {
+        self.tally
+    }
 }
 
 impl PayloadHandler for Sink {
@@ -65,6 +115,9 @@ impl PayloadHandler for Sink {
         _: &Conveyor,
         request: &RequestPayloadTransferEvent,
     ) -> Option<Entity> {
+        if self.jammed {
+            return None;
+        }
         self.payloads
             .push((request.payload, request.direction.opposite(), 0.0));
         Some(request.payload)
@@ -79,13 +132,38 @@ impl PayloadHandler for Sink {
     }
 }
 
-fn update_sinks(mut commands: Commands, time: Res<Time>, sinks: Query<&mut Sink>) {
+/// Sent when a sink has finished with a payload and despawned it.  The value
+/// is None for error payloads.
+#[derive(Event, Debug)]
+pub struct PayloadSunkEvent {
+    pub sink: Entity,
+    pub value: Option<Value>,
+}
+
+fn update_sinks(
+    mut commands: Commands,
+    time: Res<Time>,
+    sinks: Query<(Entity, &mut Sink)>,
+    errors: Query<(), With<ErrorPayload>>,
+    operands: Query<&Operand>,
+    mut sunk: EventWriter<PayloadSunkEvent>,
+) {
     let t = time.delta_secs();
 
-    for mut sink in sinks {
+    for (sink_entity, mut sink) in sinks {
+        let sink = &mut *sink;
         for (entity, _, mu) in &mut sink.payloads {
             *mu += t;
-            if *mu >= 1.0 {
+            if !sink.accepts_errors && errors.contains(*entity) && *mu >= 0.5 {
+                // Hold the error in the middle of the sink
+                *mu = 0.5;
+                sink.jammed = true;
+            } else if *mu >= 1.0 {
+                sink.tally += 1;
+                sunk.write(PayloadSunkEvent {
+                    sink: sink_entity,
+                    value: operands.get(*entity).ok().map(|operand| operand.0.clone()),
+                });
                 commands.entity(*entity).despawn();
             }
         }
@@ -103,7 +181,7 @@ fn update_sink_transforms(
         for (entity, direction, mu) in &sink.payloads {
             if let Ok(mut transform) = payloads.get_mut(*entity) {
                 let payload_transform =
-                    get_payload_transform(tile_center, base.tile_size, Some(*direction), None, *mu);
+                    get_payload_transform(tile_center, base.grid_size, Some(*direction), None, *mu);
 
                 let scale_mu = 1.0 - ((*mu - 0.5) * 2.0).max(0.0);
 
@@ -115,14 +193,32 @@ fn update_sink_transforms(
 
 fn update_sink_tiles(
     mut commands: Commands,
-    new_sinks: Query<Entity, (With<Sink>, Without<TileTextureIndex>)>,
-    tilemap_entity: Single<Entity, (With<BaseLayer>, With<TileStorage>)>,
+    new_sinks: Query<(Entity, &Sink, Has<ResearchSink>), Without<TileTextureIndex>>,
+    tilemap_entity: Single<Entity, (With<BaseLayer>, With<ChunkedTileStorage>)>,
 ) {
-    for new_sink in new_sinks {
+    for (new_sink, sink, research) in new_sinks {
+        let sprite = if research {
+            GameSprite::ResearchSink
+        } else {
+            sink_sprite(sink.accepts_errors)
+        };
         commands.entity(new_sink).insert_if_new(TileBundle {
             tilemap_id: TilemapId(*tilemap_entity),
-            texture_index: GameSprite::Sink.tile_texture_index(),
+            texture_index: sprite.tile_texture_index(),
             ..default()
         });
     }
 }
+
+fn update_jammed_sink_tiles(sinks: Query<(&Sink, &mut TileColor)>) {
+    for (sink, mut color) in sinks {
+        let new_color = if sink.is_jammed() {
+            Color::srgb(1.0, 0.4, 0.4)
+        } else {
+            Color::WHITE
+        };
+        if color.0 != new_color {
+            color.0 = new_color;
+        }
+    }
+}
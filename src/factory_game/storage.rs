@@ -0,0 +1,303 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+
+use crate::{
+    factory_game::{
+        BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
+        conveyor::Conveyor,
+        drones::DronePort,
+        footprint::{Footprint, Port, PortKind},
+        helpers::ConveyorDirection,
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
+        payload_handler::{AddPayloadHandler, PayloadHandler},
+        payloads::{Payload, RequestPayloadTransferEvent},
+    },
+    helpers::{TilemapQuery, TilemapQueryItem},
+    sprite_sheet::{GameSprite, SpriteSheet},
+};
+
+pub fn storage_plugin(app: &mut App) {
+    app.register_place_tile_event::<PlaceStorageEvent>()
+        .add_payload_handler::<Storage>()
+        .add_systems(
+            Update,
+            (
+                update_storage_sprites.in_set(ConveyorSystems::TileUpdater),
+                update_storages.in_set(ConveyorSystems::TransportLogic),
+                update_storage_transforms.in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+pub const STORAGE_FOOTPRINT: Footprint = Footprint::new(
+    UVec2::new(2, 2),
+    &[
+        Port {
+            name: "in",
+            cell: UVec2::new(0, 0),
+            side: ConveyorDirection::West,
+            kind: PortKind::Input,
+        },
+        Port {
+            name: "out",
+            cell: UVec2::new(1, 1),
+            side: ConveyorDirection::East,
+            kind: PortKind::Output,
+        },
+    ],
+);
+
+pub struct StorageTool;
+
+impl Tool for StorageTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (GameSprite::Storage, TileFlip::default())
+    }
+
+    fn footprint_size(&self) -> UVec2 {
+        STORAGE_FOOTPRINT.size()
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceStorageEvent(TilePos::default()).cost()
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(PlaceStorageEvent(*tile_pos));
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct PlaceStorageEvent(pub TilePos);
+
+impl PlaceTileEvent for PlaceStorageEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    fn cost(&self) -> u32 {
+        25
+    }
+
+    fn footprint_size(&self) -> UVec2 {
+        STORAGE_FOOTPRINT.size()
+    }
+
+    fn configure_new_entity(&self, mut commands: EntityCommands) {
+        commands.insert((storage_bundle(self.0, 20), Name::new("Storage")));
+    }
+}
+
+/// A multi-tile buffer: payloads arriving at its input port queue up and
+/// leave, first in first out, from its output port.
+#[derive(Component, Debug, Reflect)]
+pub struct Storage {
+    anchor: TilePos,
+    capacity: usize,
+    payloads: VecDeque<Entity>,
+}
+
+impl PayloadHandler for Storage {
+    fn try_transfer(
+        &mut self,
+        _: &Conveyor,
+        request: &RequestPayloadTransferEvent,
+    ) -> Option<Entity> {
+        let port = STORAGE_FOOTPRINT.port_at(
+            &self.anchor,
+            &request.destination_pos,
+            request.direction.opposite(),
+            PortKind::Input,
+        );
+
+        if port.is_some() && self.payloads.len() < self.capacity {
+            self.payloads.push_back(request.payload);
+            return Some(request.payload);
+        }
+        None
+    }
+
+    fn remove_payload(&mut self, payload: Entity) {
+        self.payloads.retain(|p| *p != payload);
+    }
+
+    fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
+        self.payloads.iter().copied()
+    }
+
+    fn payload_to_pick_up(&self) -> Option<Entity> {
+        self.payloads.front().copied()
+    }
+}
+
+impl Storage {
+    pub fn new(anchor: TilePos, capacity: usize) -> Self {
+        Storage {
+            anchor,
+            capacity,
+            payloads: VecDeque::default(),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.payloads.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Adds a payload that didn't come in through the input port, returning
+    /// false if there is no room for it
+    pub fn push(&mut self, payload: Entity) -> bool {
+        if self.payloads.len() >= self.capacity {
+            return false;
+        }
+        self.payloads.push_back(payload);
+        true
+    }
+
+    /// Removes the first payload that `matches`, from anywhere in the queue
+    pub fn take(&mut self, matches: impl Fn(Entity) -> bool) -> Option<Entity> {
+        let index = self.payloads.iter().position(|payload| matches(*payload))?;
+        self.payloads.remove(index)
+    }
+
+    pub fn count_matching(&self, matches: impl Fn(Entity) -> bool) -> usize {
+        self.payloads
+            .iter()
+            .filter(|payload| matches(**payload))
+            .count()
+    }
+}
+
+pub fn storage_bundle(anchor: TilePos, capacity: usize) -> impl Bundle {
+    let mut conveyor = Conveyor::new(STORAGE_FOOTPRINT.port_sides(PortKind::Output));
+    conveyor.set_inputs(STORAGE_FOOTPRINT.port_sides(PortKind::Input));
+
+    (Storage::new(anchor, capacity), STORAGE_FOOTPRINT, conveyor)
+}
+
+fn update_storages(
+    storages: Query<(Entity, &Storage, &Footprint, &TilePos)>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
+    mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
+) {
+    let (tile_storage, map_size) = base.into_inner();
+
+    for (source, storage, footprint, anchor) in storages {
+        let Some(&payload) = storage.payloads.front() else {
+            continue;
+        };
+
+        let Some(port) = footprint.port("out") else {
+            continue;
+        };
+
+        let port_pos = footprint.port_pos(anchor, port);
+        let destination_pos = port.side.offset(&port_pos, map_size);
+        if let Some(destination_pos) = destination_pos
+            && let Some(destination) = tile_storage.get(&destination_pos)
+        {
+            send_payloads.write(RequestPayloadTransferEvent {
+                payload,
+                source,
+                destination,
+                destination_pos,
+                direction: port.side,
+            });
+        }
+    }
+}
+
+fn footprint_center(base: &TilemapQueryItem, anchor: &TilePos, footprint: &Footprint) -> Vec2 {
+    let size = footprint.size();
+    let far_corner = TilePos {
+        x: anchor.x + size.x - 1,
+        y: anchor.y + size.y - 1,
+    };
+    (base.center_in_world(anchor) + base.center_in_world(&far_corner)) / 2.0
+}
+
+#[derive(Component)]
+struct StorageCount;
+
+fn update_storage_sprites(
+    mut commands: Commands,
+    new_storages: Query<(Entity, &TilePos, &Footprint, Option<&DronePort>), Added<Storage>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+    sprite_sheet: Res<SpriteSheet>,
+) {
+    for (entity, anchor, footprint, drone_port) in new_storages {
+        let center = footprint_center(&base, anchor, footprint);
+        let tile_size = Vec2::from(base.tile_size);
+
+        let mut sprite =
+            sprite_sheet.sprite(drone_port.map_or(GameSprite::Storage, DronePort::sprite));
+        sprite.custom_size = Some(footprint.size().as_vec2() * tile_size);
+
+        commands
+            .entity(entity)
+            .insert((sprite, Transform::from_translation(center.extend(0.5))))
+            .with_children(|c| {
+                for port in footprint.ports() {
+                    let port_center = base.center_in_world(&footprint.port_pos(anchor, port));
+                    let edge = port_center + port_offset(port.side, base.grid_size);
+                    let angle = match port.kind {
+                        PortKind::Input => port.side.opposite().angle(),
+                        PortKind::Output => port.side.angle(),
+                    };
+
+                    c.spawn((
+                        Name::new(port.name),
+                        sprite_sheet.sprite(GameSprite::Arrow),
+                        Transform::from_translation((edge - center).extend(0.1))
+                            .with_rotation(Quat::from_rotation_z(angle))
+                            .with_scale(Vec3::splat(0.5)),
+                    ));
+                }
+
+                c.spawn((
+                    StorageCount,
+                    Text2d::default(),
+                    TextFont::from_font_size(12.0),
+                    Transform::from_xyz(0.0, -tile_size.y / 2.0, 0.2),
+                ));
+            });
+    }
+}
+
+/// Just inside the middle of a tile's edge
+fn port_offset(side: ConveyorDirection, grid_size: &TilemapGridSize) -> Vec2 {
+    let edge = side.offset_in_world(grid_size);
+    edge - edge.normalize() * 4.0
+}
+
+fn update_storage_transforms(
+    storages: Query<(&TilePos, &Footprint, &Storage, &Children)>,
+    mut counts: Query<&mut Text2d, With<StorageCount>>,
+    mut payloads: Query<&mut Transform, With<Payload>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (anchor, footprint, storage, children) in storages {
+        let center = footprint_center(&base, anchor, footprint);
+
+        for payload in storage.iter_payloads() {
+            if let Ok(mut transform) = payloads.get_mut(payload) {
+                *transform = Transform::from_translation(center.extend(3.0));
+            }
+        }
+
+        for child in children {
+            if let Ok(mut text) = counts.get_mut(*child) {
+                let new_text = format!("{}/{}", storage.count(), storage.capacity);
+                if text.0 != new_text {
+                    text.0 = new_text;
+                }
+            }
+        }
+    }
+}
@@ -1,22 +1,55 @@
 use std::time::Duration;
 
 use bevy::{prelude::*, state::app::StatesPlugin, time::TimeUpdateStrategy};
-use bevy_ecs_tilemap::tiles::{TilePos, TileStorage};
+use bevy_ecs_tilemap::{
+    map::{HexCoordSystem, TilemapSize, TilemapType},
+    tiles::{TilePos, TileStorage},
+};
+use bevy_rand::{plugin::EntropyPlugin, prelude::WyRand};
 
 use crate::{
     GameState,
     factory_game::{
         BaseLayer, MapConfig,
+        analysis::{ConveyorAnalysis, Warning},
         bridge::{BridgeConveyor, PlaceBridgeEvent},
+        campaign::{CurrentLevel, Level, place_level_tiles},
+        chunks::{CHUNK_SIZE, ChunkedTileStorage, UNBOUNDED_MAP_SIZE},
+        congestion::Congestion,
+        conveyor::Conveyor,
         conveyor_belts::{ConveyorBelt, PlaceConveyorBeltEvent},
-        generator::PlaceGeneratorEvent,
-        operators::Operand,
+        deadlock::{DeadlockKind, Deadlocks},
+        deposits::{Deposit, DepositLayer, Extracting},
+        drones::{DronePort, DroneQueue, PlaceDronePortEvent, WaitingFor},
+        economy::{Money, STARTING_MONEY},
+        generator::{Generator, PlaceGeneratorEvent, Sequence},
+        goals::{Goal, GoalCompleted, PlaceGoalSinkEvent},
+        graph_export::{GraphEdge, build_factory_graph},
+        inserter::PlaceInserterEvent,
+        interaction::{ClearTileEvent, Locked, RegisterPlaceTileEvent, Tool},
+        operators::{
+            CycleOverflowPolicyEvent, ErrorPayload, Operand, OperatorsTool, OverflowPolicy,
+            operand_bundle,
+        },
         payload_handler::PayloadHandler,
         payloads::PayloadTransportLine,
+        power::{PlacePowerNodeEvent, Power, PowerConsumer, PowerNode},
+        processor::{PlaceProcessorEvent, Processor, Program},
+        research::{PlaceResearchSinkEvent, Research, ResearchTree, Upgrade},
+        signals::{Comparison, Condition, Disabled, PlaceSignalTileEvent, SignalLayer, SignalTile},
+        sink::{PlaceSinkEvent, Sink},
+        storage::{PlaceStorageEvent, Storage},
+        throughput::{SeriesKind, Throughput},
+        tiled_map::{FACTORY_MAP_PATH, TiledMap},
+        unary_operators::{
+            PlaceUnaryOperatorEvent, UnaryOperator, UnaryOperatorTile, UnaryOperatorToolEvent,
+            UnaryOperatorsTool,
+        },
+        value::Value,
     },
 };
 
-use super::helpers::ConveyorDirection;
+use super::helpers::{ConveyorDirection, ConveyorDirections};
 
 fn setup() -> App {
     let mut app = App::new();
@@ -24,8 +57,10 @@ fn setup() -> App {
     app.add_plugins((
         MinimalPlugins,
         StatesPlugin,
+        EntropyPlugin::<WyRand>::with_seed(42u64.to_le_bytes()),
         crate::factory_game::factory_game_logic_plugin,
     ));
+    app.register_place_tile_event::<ClearTileEvent>();
     app.init_state::<GameState>()
         .insert_state(GameState::FactoryGame)
         .insert_resource(Time::<Virtual>::from_max_delta(Duration::from_secs(10)))
@@ -35,8 +70,9 @@ fn setup() -> App {
 
     app.world_mut().spawn((
         BaseLayer,
-        TileStorage::empty(map_config.size),
+        ChunkedTileStorage::default(),
         map_config.size,
+        map_config.map_type,
     ));
 
     app
@@ -64,7 +100,7 @@ fn generator_generates_payload() {
     let mut q = world.query::<&Operand>();
 
     assert_eq!(q.iter(world).len(), 1);
-    assert_eq!(q.single(world).unwrap().0, 1);
+    assert_eq!(q.single(world).unwrap().0, Value::Int(1));
 }
 
 #[test]
@@ -143,3 +179,1518 @@ fn generator_transfers_payload_to_bridge() {
 
     assert_eq!(bridge.iter_payloads().count(), 1);
 }
+
+#[test]
+fn inserter_picks_payload_from_storage_and_drops_it_on_conveyor() {
+    let mut app = setup();
+
+    // The inserter takes from a storage cell that isn't a port
+    let world = app.world_mut();
+    world.trigger(PlaceStorageEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceInserterEvent(
+        TilePos { x: 2, y: 0 },
+        ConveyorDirection::East,
+        2.0,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 3, y: 0 },
+        ConveyorDirection::East,
+    ));
+    app.update();
+
+    let world = app.world_mut();
+    let payload = world.spawn(operand_bundle(Operand(Value::Int(7)))).id();
+    let mut storage = world.query::<&mut Storage>();
+    assert!(storage.single_mut(world).unwrap().push(payload));
+
+    // Half a swing over, then the drop is transferred on the next update
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            250,
+        )));
+    for _ in 0..5 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut storage = world.query::<&Storage>();
+    assert_eq!(storage.single(world).unwrap().count(), 0);
+    let mut ptl = world.query_filtered::<&PayloadTransportLine, With<ConveyorBelt>>();
+    assert_eq!(
+        ptl.single(world)
+            .unwrap()
+            .iter_payloads()
+            .collect::<Vec<_>>(),
+        vec![payload]
+    );
+}
+
+#[test]
+fn storage_covers_its_footprint_and_is_cleared_from_any_tile() {
+    let mut app = setup();
+
+    app.world_mut()
+        .trigger(PlaceStorageEvent(TilePos { x: 4, y: 4 }));
+    app.update();
+
+    let storage_entity = {
+        let world = app.world_mut();
+        let mut storage = world.query_filtered::<Entity, With<Storage>>();
+        storage.single(world).unwrap()
+    };
+
+    let mut tile_storage = app
+        .world_mut()
+        .query_filtered::<&ChunkedTileStorage, With<BaseLayer>>();
+    let tiles = tile_storage.single(app.world()).unwrap();
+    for (x, y) in [(4, 4), (5, 4), (4, 5), (5, 5)] {
+        assert_eq!(tiles.get(&TilePos { x, y }), Some(storage_entity));
+    }
+    assert_eq!(tiles.get(&TilePos { x: 6, y: 4 }), None);
+
+    app.world_mut()
+        .trigger(ClearTileEvent(TilePos { x: 5, y: 5 }));
+    app.update();
+
+    let tiles = tile_storage.single(app.world()).unwrap();
+    for (x, y) in [(4, 4), (5, 4), (4, 5), (5, 5)] {
+        assert_eq!(tiles.get(&TilePos { x, y }), None);
+    }
+}
+
+#[test]
+fn storage_passes_payloads_from_input_port_to_output_port() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceStorageEvent(TilePos { x: 2, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 4, y: 1 },
+        ConveyorDirection::East,
+    ));
+    // Nothing comes out of the bottom right of the storage
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 4, y: 0 },
+        ConveyorDirection::East,
+    ));
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            500,
+        )));
+
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let belt_count = |app: &mut App, pos: TilePos| {
+        let mut q = app
+            .world_mut()
+            .query_filtered::<(&TilePos, &PayloadTransportLine), With<ConveyorBelt>>();
+        q.iter(app.world())
+            .find(|(p, _)| **p == pos)
+            .map(|(_, ptl)| ptl.count())
+            .unwrap()
+    };
+
+    assert!(belt_count(&mut app, TilePos { x: 4, y: 1 }) > 0);
+    assert_eq!(belt_count(&mut app, TilePos { x: 4, y: 0 }), 0);
+}
+
+#[test]
+fn storage_only_connects_through_its_port_cells() {
+    use ConveyorDirection::*;
+
+    let mut app = setup();
+
+    // The storage covers (2, 0) to (3, 1), taking payloads in at the west of
+    // (2, 0) and sending them out at the east of (3, 1)
+    let world = app.world_mut();
+    world.trigger(PlaceStorageEvent(TilePos { x: 2, y: 0 }));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 1 }));
+    world.trigger(PlaceConveyorBeltEvent(TilePos { x: 1, y: 1 }, East));
+    world.trigger(PlaceConveyorBeltEvent(TilePos { x: 4, y: 0 }, East));
+    world.trigger(PlaceConveyorBeltEvent(TilePos { x: 4, y: 1 }, East));
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            500,
+        )));
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut storage = world.query::<&Storage>();
+    assert_eq!(storage.single(world).unwrap().count(), 0);
+
+    let mut belts =
+        world.query_filtered::<(&TilePos, &Conveyor, &PayloadTransportLine), With<ConveyorBelt>>();
+    let mut belt = |pos| {
+        belts
+            .iter(world)
+            .find(|(tile_pos, ..)| **tile_pos == pos)
+            .map(|(_, conveyor, line)| (conveyor.inputs(), line.count()))
+            .unwrap()
+    };
+    // Only the belt by the output port is fed, and the belt pointing at the
+    // side of a cell without a port is stuck
+    assert_eq!(
+        belt(TilePos { x: 4, y: 1 }).0,
+        ConveyorDirections::new(West)
+    );
+    assert_eq!(
+        belt(TilePos { x: 4, y: 0 }).0,
+        ConveyorDirections::default()
+    );
+    assert!(belt(TilePos { x: 1, y: 1 }).1 > 0);
+
+    assert!(warnings(&app).contains(&(TilePos { x: 1, y: 1 }, Warning::Blocked)));
+}
+
+#[test]
+fn constant_operator_takes_belt_direction_and_steps_its_constant() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 2, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 3, y: 0 },
+        ConveyorDirection::East,
+    ));
+    app.update();
+
+    // The first use replaces the belt, the second steps K from 1 to 2
+    let tool = UnaryOperatorToolEvent(
+        TilePos { x: 2, y: 0 },
+        UnaryOperator::AddConstant(1),
+        ConveyorDirection::North,
+    );
+    app.world_mut().trigger(tool);
+    app.update();
+    let tool = UnaryOperatorToolEvent(
+        TilePos { x: 2, y: 0 },
+        UnaryOperator::AddConstant(1),
+        ConveyorDirection::North,
+    );
+    app.world_mut().trigger(tool);
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            500,
+        )));
+
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut q = world.query::<&Operand>();
+    let values: Vec<_> = q.iter(world).map(|o| o.0.clone()).collect();
+
+    assert!(values.contains(&Value::Int(3)));
+    assert!(
+        values
+            .iter()
+            .all(|v| *v == Value::Int(1) || *v == Value::Int(3))
+    );
+}
+
+#[test]
+fn unary_operator_tool_places_facing_its_way_unless_over_a_belt() {
+    let mut app = setup();
+
+    let mut tool = UnaryOperatorsTool::unary();
+    for _ in 0..3 {
+        tool.next_variant(&TilemapType::Square);
+    }
+    let world = app.world_mut();
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 5, y: 5 },
+        ConveyorDirection::South,
+    ));
+    world.flush();
+    tool.execute(world.commands(), &TilePos { x: 5, y: 5 });
+    tool.execute(world.commands(), &TilePos { x: 7, y: 5 });
+    world.flush();
+
+    let outputs: Vec<_> = world
+        .query_filtered::<(&TilePos, &Conveyor), With<UnaryOperatorTile>>()
+        .iter(world)
+        .map(|(tile_pos, conveyor)| (tile_pos.x, conveyor.output()))
+        .collect();
+    assert_eq!(outputs.len(), 2);
+    assert!(outputs.contains(&(5, ConveyorDirection::South)));
+    assert!(outputs.contains(&(7, ConveyorDirection::North)));
+
+    // One more turn brings it back round, onto the next operator
+    tool.next_variant(&TilemapType::Square);
+    assert_eq!(
+        tool.get_sprite_flip().1,
+        ConveyorDirection::East.tile_flip()
+    );
+}
+
+#[test]
+fn values_that_become_errors_lose_their_swatch() {
+    let mut app = setup();
+
+    // "+1" can't add to a colour
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceUnaryOperatorEvent(
+        TilePos { x: 1, y: 0 },
+        UnaryOperator::Increment,
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 2, y: 0 },
+        ConveyorDirection::East,
+    ));
+    app.update();
+
+    let world = app.world_mut();
+    let mut generator = world.query::<&mut Generator>();
+    generator.single_mut(world).unwrap().sequence = Sequence::Constant(Value::Colour([255, 0, 0]));
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            250,
+        )));
+    for _ in 0..12 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let errors: Vec<_> = world
+        .query_filtered::<Has<Sprite>, With<ErrorPayload>>()
+        .iter(world)
+        .collect();
+    assert!(!errors.is_empty());
+    assert!(errors.iter().all(|has_swatch| !has_swatch));
+}
+
+/// Generator -> belt -> "+K" with a K big enough to overflow -> belt -> sink,
+/// placed without running it
+fn place_overflowing_factory(policy: OverflowPolicy, sink_accepts_errors: bool) -> App {
+    let mut app = setup();
+    app.insert_resource(policy);
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceUnaryOperatorEvent(
+        TilePos { x: 2, y: 0 },
+        UnaryOperator::AddConstant(i64::MAX),
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 3, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: 4, y: 0 }, sink_accepts_errors));
+
+    app
+}
+
+fn overflowing_factory(policy: OverflowPolicy, sink_accepts_errors: bool) -> App {
+    run_overflowing_factory(place_overflowing_factory(policy, sink_accepts_errors))
+}
+
+fn run_overflowing_factory(mut app: App) -> App {
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            500,
+        )));
+
+    for _ in 0..30 {
+        app.update();
+    }
+
+    app
+}
+
+fn sink_is_jammed(app: &mut App) -> bool {
+    let mut q = app.world_mut().query::<&Sink>();
+    q.single(app.world()).unwrap().is_jammed()
+}
+
+fn error_count(app: &mut App) -> usize {
+    let mut q = app
+        .world_mut()
+        .query_filtered::<Entity, With<ErrorPayload>>();
+    q.iter(app.world()).count()
+}
+
+#[test]
+fn overflow_makes_error_payload_that_jams_sink() {
+    let mut app = overflowing_factory(OverflowPolicy::Error, false);
+
+    assert!(sink_is_jammed(&mut app));
+    // The one in the sink plus the ones backed up behind it
+    assert!(error_count(&mut app) > 1);
+}
+
+#[test]
+fn sink_can_accept_errors() {
+    let mut app = overflowing_factory(OverflowPolicy::Error, true);
+
+    assert!(!sink_is_jammed(&mut app));
+}
+
+#[test]
+fn wrapping_overflow_doesnt_make_errors() {
+    let mut app = overflowing_factory(OverflowPolicy::Wrapping, false);
+
+    assert!(!sink_is_jammed(&mut app));
+    assert_eq!(error_count(&mut app), 0);
+
+    let world = app.world_mut();
+    let mut q = world.query::<&Operand>();
+    assert!(q.iter(world).any(|o| o.0 == Value::Int(i64::MIN)));
+}
+
+#[test]
+fn operator_overflow_policy_overrides_the_factorys() {
+    let mut app = place_overflowing_factory(OverflowPolicy::Error, false);
+    // Spawn the placed tiles, then step the "+K" from following the factory
+    // to wrapping
+    let world = app.world_mut();
+    world.flush();
+    world.trigger(CycleOverflowPolicyEvent(TilePos { x: 2, y: 0 }));
+    let mut app = run_overflowing_factory(app);
+
+    assert!(!sink_is_jammed(&mut app));
+    assert_eq!(error_count(&mut app), 0);
+
+    let world = app.world_mut();
+    let mut q = world.query::<&Operand>();
+    assert!(q.iter(world).any(|o| o.0 == Value::Int(i64::MIN)));
+}
+
+#[test]
+fn dead_end_belts_heat_up_and_flowing_ones_stay_cool() {
+    let mut app = setup();
+
+    // The top line runs into a sink and the bottom one into nothing
+    let world = app.world_mut();
+    for y in [0, 2] {
+        world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y }));
+        for x in 1..3 {
+            world.trigger(PlaceConveyorBeltEvent(
+                TilePos { x, y },
+                ConveyorDirection::East,
+            ));
+        }
+    }
+    world.trigger(PlaceSinkEvent(TilePos { x: 3, y: 0 }, false));
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            500,
+        )));
+
+    for _ in 0..30 {
+        app.update();
+    }
+
+    let mut q = app.world_mut().query::<(&TilePos, &Congestion)>();
+    let mut congestion = |pos| {
+        q.iter(app.world())
+            .find(|(tile_pos, _)| **tile_pos == pos)
+            .map(|(_, congestion)| (congestion.stalled_for(), congestion.heat()))
+            .unwrap()
+    };
+
+    let (flowing_stall, flowing_heat) = congestion(TilePos { x: 2, y: 0 });
+    let (dead_end_stall, dead_end_heat) = congestion(TilePos { x: 2, y: 2 });
+    assert_eq!(flowing_stall, 0.0);
+    assert!(dead_end_stall > 5.0);
+    assert!(dead_end_heat > 0.5);
+    assert!(flowing_heat < 0.3);
+}
+
+#[test]
+fn throughput_counts_generated_carried_and_sunk_payloads() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: 2, y: 0 }, false));
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            250,
+        )));
+
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let throughput = app.world().resource::<Throughput>();
+    let per_minute = |kind| {
+        throughput
+            .series()
+            .find(|(_, series)| series.kind() == kind)
+            .map(|(_, series)| series.per_minute())
+            .unwrap()
+    };
+    let generated = per_minute(SeriesKind::Generator);
+    let sunk = per_minute(SeriesKind::Sink);
+
+    // A payload a second, with the last few still on their way
+    assert!((8..=10).contains(&generated));
+    assert!(per_minute(SeriesKind::Belt) <= generated);
+    assert!(sunk > 0 && sunk <= per_minute(SeriesKind::Belt));
+
+    let (_, sink) = throughput
+        .series()
+        .find(|(_, series)| series.kind() == SeriesKind::Sink)
+        .unwrap();
+    assert_eq!(sink.values().get("1"), Some(&sunk));
+}
+
+fn warnings(app: &App) -> Vec<(TilePos, Warning)> {
+    app.world().resource::<ConveyorAnalysis>().warnings()
+}
+
+#[test]
+fn analysis_finds_loops_dead_ends_and_unfed_machines() {
+    use ConveyorDirection::*;
+
+    let mut app = setup();
+    let belts = [
+        // A loop
+        ((0, 0), East),
+        ((1, 0), North),
+        ((1, 1), West),
+        ((0, 1), South),
+        // Two belts pointing at each other
+        ((5, 2), East),
+        ((6, 2), West),
+        // From a generator to nowhere, and from one to a sink
+        ((11, 0), East),
+        ((11, 5), East),
+        // Only feeding the operator's left operand
+        ((19, 1), East),
+    ];
+
+    let world = app.world_mut();
+    for ((x, y), direction) in belts {
+        world.trigger(PlaceConveyorBeltEvent(TilePos { x, y }, direction));
+    }
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 10, y: 0 }));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 10, y: 5 }));
+    world.trigger(PlaceSinkEvent(TilePos { x: 12, y: 5 }, false));
+    OperatorsTool::plus(&TilemapType::Square).execute(world.commands(), &TilePos { x: 20, y: 1 });
+    world.flush();
+
+    app.update();
+    app.update();
+
+    let at = |x, y, warning| (TilePos { x, y }, warning);
+    assert_eq!(
+        warnings(&app),
+        vec![
+            at(0, 0, Warning::Cycle),
+            at(0, 1, Warning::Cycle),
+            at(1, 0, Warning::Cycle),
+            at(1, 1, Warning::Cycle),
+            at(5, 2, Warning::Blocked),
+            at(6, 2, Warning::Blocked),
+            at(10, 0, Warning::NoPathToSink),
+            at(11, 0, Warning::DeadEnd),
+            at(20, 1, Warning::OneOperand),
+        ]
+    );
+
+    // Fixing things clears their warnings, though breaking the loop and
+    // clearing one of the facing belts leaves dead ends
+    let world = app.world_mut();
+    world.trigger(ClearTileEvent(TilePos { x: 1, y: 1 }));
+    world.trigger(ClearTileEvent(TilePos { x: 6, y: 2 }));
+    world.trigger(PlaceSinkEvent(TilePos { x: 12, y: 0 }, false));
+    world.trigger(PlaceConveyorBeltEvent(TilePos { x: 21, y: 1 }, West));
+
+    app.update();
+    app.update();
+
+    assert_eq!(
+        warnings(&app),
+        vec![at(1, 0, Warning::DeadEnd), at(5, 2, Warning::DeadEnd)]
+    );
+}
+
+fn deadlocks(app: &App) -> Vec<(DeadlockKind, TilePos, usize, String)> {
+    app.world()
+        .resource::<Deadlocks>()
+        .iter()
+        .map(|deadlock| {
+            (
+                deadlock.kind,
+                deadlock.cause,
+                deadlock.tiles.len(),
+                deadlock.explanation.clone(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn deadlocks_are_traced_to_full_loops_starved_operators_and_dead_ends() {
+    use ConveyorDirection::*;
+
+    let mut app = setup();
+    let belts = [
+        // A generator filling a line that's closed into a loop once full
+        ((1, 0), East),
+        ((2, 0), East),
+        ((3, 0), North),
+        ((3, 1), West),
+        // Only feeding the operator's left operand
+        ((19, 1), East),
+        // From a generator to nowhere
+        ((11, 5), East),
+    ];
+
+    let world = app.world_mut();
+    for ((x, y), direction) in belts {
+        world.trigger(PlaceConveyorBeltEvent(TilePos { x, y }, direction));
+    }
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 18, y: 1 }));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 10, y: 5 }));
+    OperatorsTool::plus(&TilemapType::Square).execute(world.commands(), &TilePos { x: 20, y: 1 });
+    world.flush();
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            500,
+        )));
+
+    for _ in 0..60 {
+        app.update();
+    }
+    app.world_mut()
+        .trigger(PlaceConveyorBeltEvent(TilePos { x: 2, y: 1 }, South));
+    for _ in 0..60 {
+        app.update();
+    }
+
+    let at = |x, y| TilePos { x, y };
+    assert_eq!(
+        deadlocks(&app),
+        vec![
+            (
+                DeadlockKind::Cycle,
+                at(2, 0),
+                // The loop, and the generator and belt feeding it
+                6,
+                "The loop of 4 tiles through Conveyor Belt at 2, 0 is full, so none of them can \
+                 move"
+                    .to_string()
+            ),
+            (
+                DeadlockKind::Blocked,
+                at(11, 5),
+                2,
+                "Conveyor Belt at 11, 5 has nowhere to send its payloads".to_string()
+            ),
+            (
+                DeadlockKind::StarvedInput,
+                at(20, 1),
+                3,
+                "Plus at 20, 1 holds its left operand, but nothing feeds its right side"
+                    .to_string()
+            ),
+        ]
+    );
+
+    // A sink frees the dead end, while an empty belt doesn't help the
+    // operator
+    let world = app.world_mut();
+    world.trigger(PlaceSinkEvent(at(12, 5), false));
+    world.trigger(PlaceConveyorBeltEvent(at(21, 1), West));
+
+    for _ in 0..4 {
+        app.update();
+    }
+
+    let deadlocks = deadlocks(&app);
+    assert_eq!(deadlocks.len(), 2);
+    assert_eq!(
+        deadlocks[1].3,
+        "Plus at 20, 1 holds its left operand, but the Conveyor Belt at 21, 1 feeding its right \
+         side hasn't delivered anything for over 10s"
+    );
+}
+
+#[test]
+fn exported_graph_collapses_belt_runs() {
+    use ConveyorDirection::*;
+
+    let mut app = setup();
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    for x in 1..4 {
+        world.trigger(PlaceConveyorBeltEvent(TilePos { x, y: 0 }, East));
+    }
+    world.trigger(PlaceSinkEvent(TilePos { x: 4, y: 0 }, false));
+    // A loop of belts with nothing else on it
+    for ((x, y), direction) in [
+        ((10, 0), East),
+        ((11, 0), North),
+        ((11, 1), West),
+        ((10, 1), South),
+    ] {
+        world.trigger(PlaceConveyorBeltEvent(TilePos { x, y }, direction));
+    }
+
+    app.update();
+    app.update();
+
+    let graph = app
+        .world_mut()
+        .run_system_cached(build_factory_graph)
+        .unwrap();
+
+    let nodes: Vec<_> = graph
+        .nodes
+        .iter()
+        .map(|node| (node.kind.as_str(), node.x, node.y))
+        .collect();
+    assert_eq!(
+        nodes,
+        vec![
+            ("Generator", 0, 0),
+            ("Sink", 4, 0),
+            ("Conveyor Belt", 10, 0)
+        ]
+    );
+    assert_eq!(
+        graph.edges,
+        vec![
+            GraphEdge {
+                from: 0,
+                to: 1,
+                belts: 3
+            },
+            GraphEdge {
+                from: 2,
+                to: 2,
+                belts: 3
+            },
+        ]
+    );
+    assert!(graph.to_dot().contains("n0 -> n1 [label=\"3 belts\"];"));
+    assert!(graph.to_json().unwrap().contains("\"kind\": \"Generator\""));
+}
+
+#[test]
+fn configured_generator_emits_its_sequence() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 2, y: 0 },
+        ConveyorDirection::East,
+    ));
+    app.update();
+
+    let world = app.world_mut();
+    let mut generator = world.query::<&mut Generator>();
+    let mut generator = generator.single_mut(world).unwrap();
+    generator.sequence = Sequence::counter();
+    generator.time_between_generations = 0.5;
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            250,
+        )));
+
+    for _ in 0..12 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut q = world.query::<&Operand>();
+    let mut values: Vec<_> = q.iter(world).map(|o| o.0.clone()).collect();
+    values.sort_by_key(|v| match v {
+        Value::Int(v) => *v,
+        _ => i64::MAX,
+    });
+
+    assert!(values.len() > 2);
+    for (i, value) in values.iter().enumerate() {
+        assert_eq!(*value, Value::Int(i as i64));
+    }
+}
+
+#[test]
+fn goal_completed_when_all_goals_met() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceGoalSinkEvent(
+        TilePos { x: 2, y: 0 },
+        Goal::new(Value::Int(1), 3, None),
+    ));
+    // A second goal that can't be met yet
+    world.trigger(PlaceGoalSinkEvent(
+        TilePos { x: 5, y: 5 },
+        Goal::new(Value::Int(2), 1, None),
+    ));
+
+    app.world_mut()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            250,
+        )));
+
+    let completions = |app: &mut App| {
+        app.world_mut()
+            .resource_mut::<Events<GoalCompleted>>()
+            .drain()
+            .count()
+    };
+
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let delivered = |app: &mut App| {
+        let mut q = app.world_mut().query::<(&TilePos, &Goal)>();
+        q.iter(app.world())
+            .find(|(pos, _)| **pos == TilePos { x: 2, y: 0 })
+            .map(|(_, goal)| (goal.delivered(), goal.is_completed()))
+            .unwrap()
+    };
+
+    assert!(delivered(&mut app).0 >= 3);
+    assert!(delivered(&mut app).1);
+    assert_eq!(completions(&mut app), 0);
+
+    // Clearing the unmet goal leaves only met goals
+    app.world_mut()
+        .trigger(ClearTileEvent(TilePos { x: 5, y: 5 }));
+    app.update();
+    app.update();
+
+    assert_eq!(completions(&mut app), 1);
+}
+
+#[test]
+fn goal_sink_takes_error_payloads_as_rejects() {
+    let mut app = place_overflowing_factory(OverflowPolicy::Error, false);
+    app.world_mut().trigger(PlaceGoalSinkEvent(
+        TilePos { x: 4, y: 0 },
+        Goal::new(Value::Int(1), 3, None),
+    ));
+    let mut app = run_overflowing_factory(app);
+
+    assert!(!sink_is_jammed(&mut app));
+
+    let world = app.world_mut();
+    let goal = world.query::<&Goal>().single(world).unwrap();
+    assert_eq!(goal.delivered(), 0);
+    assert!(goal.rejects() > 1);
+}
+
+#[test]
+fn level_tiles_are_locked() {
+    let mut app = setup();
+
+    let level = Level::parse(
+        "test",
+        r#"{
+            "name": "Test",
+            "size": [8, 4],
+            "tools": [2],
+            "tiles": [
+                { "type": "generator", "pos": [0, 0], "values": ["7"], "seconds_between": 0.5 },
+                { "type": "goal", "pos": [2, 0], "target": "7", "quantity": 2 }
+            ],
+            "scoring": { "max_tiles": 1, "min_throughput": 0.5 }
+        }"#,
+    )
+    .unwrap();
+
+    let world = app.world_mut();
+    world.insert_resource(CurrentLevel(level));
+    world.run_system_cached(place_level_tiles).unwrap();
+
+    // The player can neither clear nor replace level tiles
+    world.trigger(ClearTileEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceSinkEvent(TilePos { x: 2, y: 0 }, false));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    assert_eq!(world.query::<&Locked>().iter(world).count(), 2);
+
+    let generator = world.query::<&Generator>().single(world).unwrap();
+    assert_eq!(generator.sequence, Sequence::Constant(Value::Int(7)));
+    assert_eq!(generator.time_between_generations, 0.5);
+
+    let goal = world.query::<&Goal>().single(world).unwrap();
+    assert!(goal.is_completed());
+}
+
+#[test]
+fn placing_costs_money_and_sinks_pay() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.insert_resource(Money::default());
+    let balance = |world: &World| world.resource::<Money>().balance();
+
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: 2, y: 0 }, false));
+    world.flush();
+    assert_eq!(balance(world), STARTING_MONEY - 20 - 1 - 10);
+
+    // Clearing refunds half
+    world.trigger(PlaceSinkEvent(TilePos { x: 5, y: 5 }, false));
+    world.flush();
+    world.trigger(ClearTileEvent(TilePos { x: 5, y: 5 }));
+    world.flush();
+    assert_eq!(balance(world), STARTING_MONEY - 20 - 1 - 10 - 5);
+
+    // Re-placing the same kind of tile only costs the difference
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.flush();
+    assert_eq!(balance(world), STARTING_MONEY - 20 - 1 - 10 - 5);
+    world.trigger(PlacePowerNodeEvent(TilePos { x: 5, y: 5 }, PowerNode::Pole));
+    world.flush();
+    world.trigger(PlacePowerNodeEvent(
+        TilePos { x: 5, y: 5 },
+        PowerNode::Plant,
+    ));
+    world.flush();
+    assert_eq!(balance(world), STARTING_MONEY - 20 - 1 - 10 - 5 - 40);
+    world.trigger(ClearTileEvent(TilePos { x: 5, y: 5 }));
+    world.flush();
+    assert_eq!(balance(world), STARTING_MONEY - 20 - 1 - 10 - 5 - 20);
+
+    // Storage costs too much to place
+    for x in 0..7 {
+        world.trigger(PlaceGeneratorEvent(TilePos { x, y: 10 }));
+    }
+    world.flush();
+    let before = balance(world);
+    world.trigger(PlaceStorageEvent(TilePos { x: 20, y: 20 }));
+    world.flush();
+    assert_eq!(balance(world), before);
+    assert_eq!(world.query::<&Storage>().iter(world).count(), 0);
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..20 {
+        app.update();
+    }
+
+    // The sink is paid 1 for each payload of 1
+    let world = app.world_mut();
+    let sunk = world.query::<&Sink>().single(world).unwrap().tally();
+    assert_eq!(sunk, 2);
+    assert_eq!(balance(world), before + sunk as u64);
+}
+
+#[test]
+fn research_sink_finishes_research() {
+    let mut app = setup();
+
+    let tree: ResearchTree = serde_json::from_str(
+        r#"[{ "id": "a", "name": "A", "value": "1", "quantity": 3, "tools": [8],
+              "upgrades": ["fast_generators"] }]"#,
+    )
+    .unwrap();
+
+    let world = app.world_mut();
+    world.insert_resource(tree);
+    world.insert_resource(Research::default());
+
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceResearchSinkEvent(TilePos { x: 2, y: 0 }));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..30 {
+        app.update();
+    }
+
+    let world = app.world();
+    let research = world.resource::<Research>();
+    assert!(research.is_tool_unlocked(world.resource::<ResearchTree>(), 8));
+    assert!(research.has_upgrade(Upgrade::FastGenerators));
+}
+
+#[test]
+fn condition_stops_generator_once_sink_has_enough() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    let map_config = MapConfig::default();
+    world.spawn((
+        SignalLayer,
+        ChunkedTileStorage::default(),
+        map_config.size,
+        map_config.map_type,
+    ));
+
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    for x in 1..3 {
+        world.trigger(PlaceConveyorBeltEvent(
+            TilePos { x, y: 0 },
+            ConveyorDirection::East,
+        ));
+    }
+    world.trigger(PlaceSinkEvent(TilePos { x: 3, y: 0 }, false));
+
+    // The generator only runs while the sink has sunk fewer than 3
+    world.trigger(PlaceSignalTileEvent(
+        TilePos { x: 0, y: 0 },
+        SignalTile::Condition(Condition {
+            comparison: Comparison::Less,
+            constant: 3,
+        }),
+    ));
+    for x in 0..4 {
+        world.trigger(PlaceSignalTileEvent(TilePos { x, y: 1 }, SignalTile::Wire));
+    }
+    world.trigger(PlaceSignalTileEvent(
+        TilePos { x: 3, y: 0 },
+        SignalTile::Sensor,
+    ));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..80 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let tally = world.query::<&Sink>().single(world).unwrap().tally();
+    // Payloads already on the belts still arrive
+    assert!((3..6).contains(&tally), "tally {tally}");
+
+    let mut generators = world.query_filtered::<(), (With<Generator>, With<Disabled>)>();
+    assert_eq!(generators.iter(world).count(), 1);
+}
+
+#[test]
+fn processor_runs_its_program_on_payloads() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceProcessorEvent(TilePos { x: 2, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 3, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.flush();
+
+    let source = "loop: IN r0, W\nMUL r0, 10\nOUT E, r0\nJMP loop";
+    let mut processors = world.query::<&mut Processor>();
+    processors
+        .single_mut(world)
+        .unwrap()
+        .load(source.to_string(), Program::parse(source).unwrap());
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut belts = world.query::<(&TilePos, &PayloadTransportLine)>();
+    let payloads: Vec<_> = belts
+        .iter(world)
+        .find(|(pos, _)| **pos == TilePos { x: 3, y: 0 })
+        .unwrap()
+        .1
+        .iter_payloads()
+        .collect();
+    let mut values: Vec<_> = payloads
+        .iter()
+        .map(|payload| world.get::<Operand>(*payload).unwrap().0.clone())
+        .collect();
+    values.sort_by_key(|value| match value {
+        Value::Int(value) => *value,
+        _ => panic!("Expected an int, got {value:?}"),
+    });
+
+    assert!(!values.is_empty());
+    assert_eq!(values[0], Value::Int(10));
+    assert!(values.iter().all(|value| match value {
+        Value::Int(value) => value % 10 == 0,
+        _ => false,
+    }));
+}
+
+#[test]
+fn drones_fetch_requested_payloads_from_providers() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceDronePortEvent(
+        TilePos { x: 2, y: 0 },
+        DronePort::Provider,
+    ));
+    world.trigger(PlaceDronePortEvent(
+        TilePos { x: 10, y: 5 },
+        DronePort::Requester(Value::Int(1)),
+    ));
+    // Nothing provides 2s
+    world.trigger(PlaceDronePortEvent(
+        TilePos { x: 10, y: 0 },
+        DronePort::Requester(Value::Int(2)),
+    ));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let mut ports = world.query::<(&TilePos, &Storage)>();
+    let mut stored = |pos: TilePos| {
+        ports
+            .iter(world)
+            .find(|(p, _)| **p == pos)
+            .map(|(_, storage)| storage.count())
+            .unwrap()
+    };
+    assert!(stored(TilePos { x: 10, y: 5 }) > 0);
+    assert_eq!(stored(TilePos { x: 10, y: 0 }), 0);
+
+    let queue = world.resource::<DroneQueue>();
+    let request = queue
+        .requests()
+        .iter()
+        .find(|request| request.tile_pos == TilePos { x: 10, y: 0 })
+        .unwrap();
+    assert_eq!(request.value, Value::Int(2));
+    assert_eq!(request.waiting_for, WaitingFor::Provider);
+}
+
+#[test]
+fn machines_only_run_when_powered() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.insert_resource(Power::default());
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 0 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: 2, y: 0 }, false));
+    // A pole on its own has nothing to carry
+    world.trigger(PlacePowerNodeEvent(TilePos { x: 0, y: 2 }, PowerNode::Pole));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let tally = |app: &mut App| {
+        let world = app.world_mut();
+        world.query::<&Sink>().single(world).unwrap().tally()
+    };
+    assert_eq!(tally(&mut app), 0);
+
+    // The plant is in reach of the pole, which supplies the generator and
+    // belt
+    app.world_mut().trigger(PlacePowerNodeEvent(
+        TilePos { x: 5, y: 2 },
+        PowerNode::Plant,
+    ));
+    for _ in 0..20 {
+        app.update();
+    }
+    assert!(tally(&mut app) > 0);
+
+    let world = app.world_mut();
+    let networks = world.resource::<Power>().networks();
+    assert_eq!(networks.len(), 1);
+    assert_eq!(networks[0].demand, 6);
+    assert_eq!(networks[0].satisfaction(), 1.0);
+
+    let mut consumers = world.query::<&PowerConsumer>();
+    assert!(
+        consumers
+            .iter(world)
+            .all(|consumer| consumer.speed() == 1.0)
+    );
+}
+
+/// Generator -> belt -> sink, powered by a plant, with `extra_generators`
+/// more around a pole in reach of it.  Returns how many payloads were sunk in
+/// 20 seconds and how fast the first generator ran.
+fn partly_powered_factory(extra_generators: usize) -> (u32, f32) {
+    partly_powered_line::<Generator>(extra_generators, |world, tile_pos| {
+        world.trigger(PlaceConveyorBeltEvent(tile_pos, ConveyorDirection::East));
+    })
+}
+
+/// Like `partly_powered_factory`, with whatever `place_middle` places at 1, 0
+/// instead of the belt.  The speed returned is of the `T` at the start of the
+/// line.
+fn partly_powered_line<T: Component>(
+    extra_generators: usize,
+    place_middle: impl FnOnce(&mut World, TilePos),
+) -> (u32, f32) {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.insert_resource(Power::default());
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    place_middle(world, TilePos { x: 1, y: 0 });
+    world.trigger(PlaceSinkEvent(TilePos { x: 2, y: 0 }, false));
+    world.trigger(PlacePowerNodeEvent(
+        TilePos { x: 2, y: 2 },
+        PowerNode::Plant,
+    ));
+    world.trigger(PlacePowerNodeEvent(TilePos { x: 2, y: 7 }, PowerNode::Pole));
+    let around_pole = (0..5)
+        .flat_map(|x| (5..10).map(move |y| TilePos { x, y }))
+        .filter(|pos| *pos != TilePos { x: 2, y: 7 });
+    for tile_pos in around_pole.take(extra_generators) {
+        world.trigger(PlaceGeneratorEvent(tile_pos));
+    }
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..80 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let tally = world.query::<&Sink>().single(world).unwrap().tally();
+    let speed = world
+        .query_filtered::<(&TilePos, &PowerConsumer), With<T>>()
+        .iter(world)
+        .find(|(tile_pos, _)| tile_pos.x <= 1 && tile_pos.y == 0)
+        .map(|(_, consumer)| consumer.speed())
+        .unwrap();
+    (tally, speed)
+}
+
+#[test]
+fn half_supplied_machines_run_at_half_speed() {
+    // 19 more generators need 95, so with the first generator and the belt
+    // the plant's 50 meets about half of the 101 demanded
+    let (full_tally, full_speed) = partly_powered_factory(0);
+    let (half_tally, half_speed) = partly_powered_factory(19);
+
+    assert_eq!(full_speed, 1.0);
+    assert!((half_speed - 0.5).abs() < 0.01);
+    assert!(full_tally > 10);
+    assert!(half_tally.abs_diff(full_tally / 2) <= 1);
+}
+
+#[test]
+fn half_supplied_unary_operators_run_at_half_speed() {
+    let unary_operator_line = |extra_generators| {
+        partly_powered_line::<UnaryOperatorTile>(extra_generators, |world, tile_pos| {
+            world.trigger(PlaceUnaryOperatorEvent(
+                tile_pos,
+                UnaryOperator::Increment,
+                ConveyorDirection::East,
+            ));
+        })
+    };
+    // With the "+1" in place of the belt the plant's 50 meets about half of
+    // the 102 demanded
+    let (full_tally, full_speed) = unary_operator_line(0);
+    let (half_tally, half_speed) = unary_operator_line(19);
+
+    assert_eq!(full_speed, 1.0);
+    assert!((half_speed - 0.5).abs() < 0.02);
+    assert!(full_tally > 10);
+    assert!(half_tally.abs_diff(full_tally / 2) <= 1);
+}
+
+#[test]
+fn generators_only_go_on_deposits_and_emit_what_they_hold() {
+    let mut app = setup();
+
+    // The deposit layer covers 10, 10 to 13, 13 of the base layer
+    let world = app.world_mut();
+    let deposit = world.spawn(Deposit::Sand).id();
+    let grass = world.spawn_empty().id();
+    let mut storage = TileStorage::empty(TilemapSize { x: 4, y: 4 });
+    storage.set(&TilePos { x: 0, y: 0 }, deposit);
+    storage.set(&TilePos { x: 1, y: 0 }, grass);
+    world.spawn((
+        DepositLayer {
+            origin: TilePos { x: 10, y: 10 },
+        },
+        storage,
+    ));
+
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 11, y: 10 }));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 12, y: 10 }));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 0, y: 0 }));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 10, y: 10 }));
+    app.update();
+
+    let world = app.world_mut();
+    let generators: Vec<_> = world
+        .query::<(&TilePos, &Generator, &Extracting)>()
+        .iter(world)
+        .map(|(pos, generator, extracting)| (*pos, generator.sequence.clone(), extracting.0))
+        .collect();
+    assert_eq!(
+        generators,
+        [(
+            TilePos { x: 10, y: 10 },
+            Deposit::Sand.sequence(),
+            Deposit::Sand
+        )]
+    );
+}
+
+#[test]
+fn nothing_is_placed_on_blocked_map_tiles() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    world.insert_resource(TiledMap::load(FACTORY_MAP_PATH).unwrap());
+
+    // There's a bush at 1, 14
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 1, y: 14 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 2, y: 14 },
+        ConveyorDirection::East,
+    ));
+    // The storage would cover the bush
+    world.trigger(PlaceStorageEvent(TilePos { x: 0, y: 13 }));
+    app.update();
+
+    let world = app.world_mut();
+    let belts: Vec<_> = world
+        .query_filtered::<&TilePos, With<ConveyorBelt>>()
+        .iter(world)
+        .copied()
+        .collect();
+    assert_eq!(belts, [TilePos { x: 2, y: 14 }]);
+    assert_eq!(world.query::<&Storage>().iter(world).count(), 0);
+}
+
+#[test]
+fn payloads_cross_chunks_far_out_on_an_unbounded_map() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    let mut base = world
+        .query_filtered::<&mut TilemapSize, With<BaseLayer>>()
+        .single_mut(world)
+        .unwrap();
+    *base = UNBOUNDED_MAP_SIZE;
+
+    // The generator is at the end of one chunk, and the rest are in the next
+    let x = CHUNK_SIZE * 1000 - 1;
+    let y = UNBOUNDED_MAP_SIZE.y - 1;
+    world.trigger(PlaceGeneratorEvent(TilePos { x, y }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: x + 1, y },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: x + 2, y }, false));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..20 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    assert!(world.query::<&Sink>().single(world).unwrap().tally() > 0);
+    let chunks = world
+        .query_filtered::<&ChunkedTileStorage, With<BaseLayer>>()
+        .single(world)
+        .unwrap()
+        .chunks()
+        .count();
+    assert_eq!(chunks, 2);
+}
+
+#[test]
+fn payloads_cross_a_hex_map_and_its_bridges() {
+    let mut app = setup();
+
+    let world = app.world_mut();
+    *world
+        .query_filtered::<&mut TilemapType, With<BaseLayer>>()
+        .single_mut(world)
+        .unwrap() = TilemapType::Hexagon(HexCoordSystem::Row);
+
+    // One line goes North East through the bridge and the other East
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 5, y: 5 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 5, y: 6 },
+        ConveyorDirection::NorthEast,
+    ));
+    world.trigger(PlaceBridgeEvent(TilePos { x: 5, y: 7 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 5, y: 8 },
+        ConveyorDirection::NorthEast,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: 5, y: 9 }, false));
+
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 3, y: 7 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 4, y: 7 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 6, y: 7 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: 7, y: 7 }, false));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let tallies: Vec<_> = world
+        .query::<(&TilePos, &Sink)>()
+        .iter(world)
+        .map(|(pos, sink)| (*pos, sink.tally()))
+        .collect();
+    assert_eq!(tallies.len(), 2);
+    assert!(tallies.iter().all(|(_, tally)| *tally > 0), "{tallies:?}");
+}
+
+#[test]
+fn operators_placed_on_a_hex_map_face_one_of_its_directions() {
+    let mut app = setup();
+
+    let hex = TilemapType::Hexagon(HexCoordSystem::Row);
+    let world = app.world_mut();
+    *world
+        .query_filtered::<&mut TilemapType, With<BaseLayer>>()
+        .single_mut(world)
+        .unwrap() = hex;
+
+    // The left operand comes in from the North East and the right from the
+    // South East, and the sum goes East into the sink
+    OperatorsTool::plus(&hex).execute(world.commands(), &TilePos { x: 5, y: 5 });
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 5, y: 7 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 5, y: 6 },
+        ConveyorDirection::SouthWest,
+    ));
+    world.trigger(PlaceGeneratorEvent(TilePos { x: 7, y: 3 }));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 6, y: 4 },
+        ConveyorDirection::NorthWest,
+    ));
+    world.trigger(PlaceConveyorBeltEvent(
+        TilePos { x: 6, y: 5 },
+        ConveyorDirection::East,
+    ));
+    world.trigger(PlaceSinkEvent(TilePos { x: 7, y: 5 }, false));
+
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+        250,
+    )));
+    for _ in 0..40 {
+        app.update();
+    }
+
+    let world = app.world_mut();
+    let output = world
+        .query::<(&TilePos, &Conveyor)>()
+        .iter(world)
+        .find(|(tile_pos, _)| **tile_pos == TilePos { x: 5, y: 5 })
+        .map(|(_, conveyor)| conveyor.output())
+        .unwrap();
+    assert_eq!(output, ConveyorDirection::East);
+    assert!(world.query::<&Sink>().single(world).unwrap().tally() > 0);
+    assert!(
+        world
+            .query::<&Operand>()
+            .iter(world)
+            .any(|operand| operand.0 == Value::Int(2))
+    );
+}
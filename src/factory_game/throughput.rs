@@ -0,0 +1,200 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{
+    GameState,
+    factory_game::{
+        ConveyorSystems, conveyor_belts::ConveyorBelt, generator::PayloadGeneratedEvent,
+        payloads::PayloadTransferredEvent, sink::PayloadSunkEvent,
+    },
+};
+
+pub fn throughput_plugin(app: &mut App) {
+    app.init_resource::<Throughput>()
+        .add_systems(
+            Update,
+            record_throughput
+                .after(ConveyorSystems::TransportLogic)
+                .before(ConveyorSystems::PayloadTransforms),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), clear_throughput);
+}
+
+/// Seconds of history kept for each tile, which is also the window its rate
+/// is counted over
+pub const HISTORY: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesKind {
+    Generator,
+    Sink,
+    Belt,
+}
+
+/// What a tile has generated, sunk or passed on in each of the last
+/// `HISTORY` seconds, newest last
+#[derive(Debug)]
+pub struct Series {
+    kind: SeriesKind,
+    seconds: VecDeque<Second>,
+}
+
+#[derive(Debug, Default)]
+struct Second {
+    count: u32,
+    /// Only kept for generators and sinks.  Error payloads are counted as
+    /// "error".
+    values: BTreeMap<String, u32>,
+}
+
+impl Series {
+    fn new(kind: SeriesKind) -> Self {
+        Series {
+            kind,
+            seconds: VecDeque::from([Second::default()]),
+        }
+    }
+
+    pub fn kind(&self) -> SeriesKind {
+        self.kind
+    }
+
+    /// Payloads over the last minute
+    pub fn per_minute(&self) -> u32 {
+        self.seconds.iter().map(|second| second.count).sum()
+    }
+
+    /// Payloads in each second, oldest first
+    pub fn counts(&self) -> impl Iterator<Item = u32> {
+        self.seconds.iter().map(|second| second.count)
+    }
+
+    /// How many of each value a generator has made or a sink has taken over
+    /// the last minute
+    pub fn values(&self) -> BTreeMap<&str, u32> {
+        let mut values = BTreeMap::new();
+        for (value, count) in self.seconds.iter().flat_map(|second| &second.values) {
+            *values.entry(value.as_str()).or_default() += count;
+        }
+        values
+    }
+
+    fn record(&mut self, value: Option<String>) {
+        let second = self.seconds.back_mut().unwrap();
+        second.count += 1;
+        if let Some(value) = value {
+            *second.values.entry(value).or_default() += 1;
+        }
+    }
+
+    fn next_second(&mut self) {
+        self.seconds.push_back(Second::default());
+        if self.seconds.len() > HISTORY {
+            self.seconds.pop_front();
+        }
+    }
+}
+
+/// Rolling payload counts for every generator, sink and belt that has
+/// handled a payload recently
+#[derive(Resource, Debug, Default)]
+pub struct Throughput {
+    series: HashMap<Entity, Series>,
+    /// The whole second of elapsed time being counted
+    second: u64,
+}
+
+impl Throughput {
+    pub fn series(&self) -> impl Iterator<Item = (Entity, &Series)> {
+        self.series.iter().map(|(entity, series)| (*entity, series))
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&Series> {
+        self.series.get(&entity)
+    }
+
+    fn record(&mut self, entity: Entity, kind: SeriesKind, value: Option<String>) {
+        self.series
+            .entry(entity)
+            .or_insert_with(|| Series::new(kind))
+            .record(value);
+    }
+
+    /// Moves every series on to `second`, forgetting tiles that have been
+    /// idle for the whole history
+    fn advance_to(&mut self, second: u64) {
+        let elapsed = second.saturating_sub(self.second).min(HISTORY as u64);
+        self.second = second;
+        for _ in 0..elapsed {
+            self.series.values_mut().for_each(Series::next_second);
+        }
+        if elapsed > 0 {
+            self.series.retain(|_, series| series.per_minute() > 0);
+        }
+    }
+}
+
+fn record_throughput(
+    time: Res<Time>,
+    mut throughput: ResMut<Throughput>,
+    mut generated: EventReader<PayloadGeneratedEvent>,
+    mut sunk: EventReader<PayloadSunkEvent>,
+    mut transferred: EventReader<PayloadTransferredEvent>,
+    belts: Query<(), With<ConveyorBelt>>,
+) {
+    throughput.advance_to(time.elapsed_secs() as u64);
+
+    for e in generated.read() {
+        throughput.record(
+            e.generator,
+            SeriesKind::Generator,
+            Some(e.value.source_text()),
+        );
+    }
+    for e in sunk.read() {
+        let value = e
+            .value
+            .as_ref()
+            .map_or("error".to_string(), |value| value.source_text());
+        throughput.record(e.sink, SeriesKind::Sink, Some(value));
+    }
+    for e in transferred.read() {
+        if belts.contains(e.source) {
+            throughput.record(e.source, SeriesKind::Belt, None);
+        }
+    }
+}
+
+fn clear_throughput(mut throughput: ResMut<Throughput>) {
+    *throughput = Throughput::default();
+}
+
+#[cfg(test)]
+mod throughput_test {
+    use super::*;
+
+    #[test]
+    fn counts_roll_over_the_last_minute() {
+        let mut throughput = Throughput::default();
+        let sink = Entity::from_raw(1);
+
+        throughput.record(sink, SeriesKind::Sink, Some("1".to_string()));
+        throughput.advance_to(1);
+        throughput.record(sink, SeriesKind::Sink, Some("1".to_string()));
+        throughput.record(sink, SeriesKind::Sink, Some("error".to_string()));
+
+        let series = throughput.get(sink).unwrap();
+        assert_eq!(series.per_minute(), 3);
+        assert_eq!(series.counts().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(series.values(), BTreeMap::from([("1", 2), ("error", 1)]));
+
+        throughput.advance_to(HISTORY as u64);
+        assert_eq!(throughput.get(sink).unwrap().per_minute(), 2);
+        assert_eq!(throughput.get(sink).unwrap().counts().count(), HISTORY);
+
+        // Idle for a whole minute, so forgotten
+        throughput.advance_to(HISTORY as u64 * 2);
+        assert!(throughput.get(sink).is_none());
+    }
+}
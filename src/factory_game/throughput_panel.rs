@@ -0,0 +1,176 @@
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::throughput::{HISTORY, Series, SeriesKind, Throughput},
+};
+
+pub fn throughput_panel_plugin(app: &mut App) {
+    app.init_resource::<ThroughputPanel>()
+        .add_systems(
+            Update,
+            toggle_throughput_panel
+                .run_if(input_just_pressed(KeyCode::KeyM))
+                .run_if(not(egui_wants_any_input))
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_throughput_panel.run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_throughput_panel);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum SortBy {
+    Tile,
+    Kind,
+    #[default]
+    PerMinute,
+}
+
+/// Whether the throughput dashboard is open, and how its table is sorted
+#[derive(Resource, Default)]
+struct ThroughputPanel {
+    open: bool,
+    sort_by: SortBy,
+    ascending: bool,
+}
+
+fn toggle_throughput_panel(mut panel: ResMut<ThroughputPanel>) {
+    panel.open = !panel.open;
+}
+
+fn close_throughput_panel(mut panel: ResMut<ThroughputPanel>) {
+    panel.open = false;
+}
+
+fn show_throughput_panel(
+    mut contexts: EguiContexts,
+    mut panel: ResMut<ThroughputPanel>,
+    throughput: Res<Throughput>,
+    tiles: Query<(&TilePos, Option<&Name>)>,
+) -> Result {
+    if !panel.open {
+        return Ok(());
+    }
+
+    let panel = &mut *panel;
+    let mut rows: Vec<_> = throughput
+        .series()
+        .filter_map(|(entity, series)| Some((tiles.get(entity).ok()?, series)))
+        .collect();
+    rows.sort_by(|((a_pos, a_name), a), ((b_pos, b_name), b)| {
+        let order = match panel.sort_by {
+            SortBy::Tile => (a_pos.x, a_pos.y).cmp(&(b_pos.x, b_pos.y)),
+            SortBy::Kind => kind_text(a.kind())
+                .cmp(kind_text(b.kind()))
+                .then_with(|| a_name.cmp(b_name)),
+            SortBy::PerMinute => a.per_minute().cmp(&b.per_minute()),
+        };
+        if panel.ascending {
+            order
+        } else {
+            order.reverse()
+        }
+    });
+
+    egui::Window::new("Throughput (M)")
+        .open(&mut panel.open)
+        .show(contexts.ctx_mut()?, |ui| {
+            for kind in [SeriesKind::Generator, SeriesKind::Sink, SeriesKind::Belt] {
+                let total: u32 = rows
+                    .iter()
+                    .filter(|(_, series)| series.kind() == kind)
+                    .map(|(_, series)| series.per_minute())
+                    .sum();
+                ui.label(format!("{}: {total}/min", kind_text(kind)));
+            }
+            ui.separator();
+
+            if rows.is_empty() {
+                ui.label("Nothing has moved in the last minute");
+                return;
+            }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("throughput").striped(true).show(ui, |ui| {
+                    for (label, sort_by) in [
+                        ("Tile", SortBy::Tile),
+                        ("Kind", SortBy::Kind),
+                        ("Per minute", SortBy::PerMinute),
+                    ] {
+                        let arrow = match (panel.sort_by == sort_by, panel.ascending) {
+                            (false, _) => "",
+                            (true, true) => " ⏶",
+                            (true, false) => " ⏷",
+                        };
+                        if ui.button(format!("{label}{arrow}")).clicked() {
+                            panel.ascending = panel.sort_by == sort_by && !panel.ascending;
+                            panel.sort_by = sort_by;
+                        }
+                    }
+                    ui.label(format!("Last {HISTORY}s"));
+                    ui.label("Values");
+                    ui.end_row();
+
+                    for ((tile_pos, name), series) in &rows {
+                        ui.label(format!(
+                            "{} at {}, {}",
+                            name.map_or("Tile", |name| name.as_str()),
+                            tile_pos.x,
+                            tile_pos.y
+                        ));
+                        ui.label(kind_text(series.kind()));
+                        ui.label(series.per_minute().to_string());
+                        sparkline(ui, series);
+                        ui.label(
+                            series
+                                .values()
+                                .iter()
+                                .map(|(value, count)| format!("{value} × {count}"))
+                                .collect::<Vec<_>>()
+                                .join(", "),
+                        );
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+
+    Ok(())
+}
+
+fn kind_text(kind: SeriesKind) -> &'static str {
+    match kind {
+        SeriesKind::Generator => "Generated",
+        SeriesKind::Sink => "Sunk",
+        SeriesKind::Belt => "Carried",
+    }
+}
+
+/// Payloads per second over the history, scaled to the busiest second
+fn sparkline(ui: &mut egui::Ui, series: &Series) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(120.0, 18.0), egui::Sense::hover());
+    let max = series.counts().max().unwrap_or(0).max(1) as f32;
+    let step = rect.width() / (HISTORY - 1) as f32;
+    // Right aligned, so the newest second is always at the same place
+    let start = rect.right() - step * (series.counts().count() - 1) as f32;
+
+    let points = series
+        .counts()
+        .enumerate()
+        .map(|(i, count)| {
+            egui::pos2(
+                start + step * i as f32,
+                rect.bottom() - rect.height() * count as f32 / max,
+            )
+        })
+        .collect();
+    ui.painter().add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.0_f32, ui.visuals().text_color()),
+    ));
+}
@@ -0,0 +1,254 @@
+use bevy::{ecs::query::QueryData, input::common_conditions::input_just_pressed, prelude::*};
+use bevy_ecs_tilemap::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::egui_wants_any_input};
+
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        bridge::BridgeConveyor,
+        chunks::ChunkedTileStorage,
+        congestion::Congestion,
+        conveyor::Conveyor,
+        distributor::Distributor,
+        generator::Generator,
+        helpers::{ConveyorDirection, ConveyorDirections},
+        interaction::{HoveredTile, Tools},
+        operators::{ErrorPayload, Operand, OperatorTile},
+        payloads::{Payload, PayloadTransportLine},
+        sink::Sink,
+        storage::Storage,
+        throughput::Throughput,
+        unary_operators::UnaryOperatorTile,
+    },
+};
+
+pub fn tile_inspector_plugin(app: &mut App) {
+    app.init_resource::<TileInspector>()
+        .add_systems(
+            Update,
+            (
+                toggle_tile_inspector.run_if(input_just_pressed(KeyCode::KeyI)),
+                pin_inspected_tile.run_if(input_just_pressed(MouseButton::Left)),
+            )
+                .run_if(not(egui_wants_any_input))
+                .run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            show_tile_inspector.run_if(in_state(GameState::FactoryGame)),
+        )
+        .add_systems(OnExit(GameState::FactoryGame), close_tile_inspector);
+}
+
+/// Whether the inspector is open, and the tile it is pinned to.  With no tile
+/// pinned it follows the hovered tile.
+#[derive(Resource, Default)]
+struct TileInspector {
+    open: bool,
+    pinned: Option<TilePos>,
+}
+
+fn toggle_tile_inspector(mut inspector: ResMut<TileInspector>) {
+    inspector.open = !inspector.open;
+    inspector.pinned = None;
+}
+
+fn close_tile_inspector(mut inspector: ResMut<TileInspector>) {
+    inspector.open = false;
+    inspector.pinned = None;
+}
+
+/// Clicking with no tool selected pins the inspector to the clicked tile, or
+/// unpins it if that tile is already pinned
+fn pin_inspected_tile(
+    tools: Res<Tools>,
+    hovered_tile: Single<&TilePos, With<HoveredTile>>,
+    mut inspector: ResMut<TileInspector>,
+) {
+    if !inspector.open || tools.current_tool().is_some() {
+        return;
+    }
+
+    let tile_pos = **hovered_tile;
+    inspector.pinned = (inspector.pinned != Some(tile_pos)).then_some(tile_pos);
+}
+
+#[derive(QueryData)]
+struct InspectedTile {
+    name: Option<&'static Name>,
+    conveyor: Option<&'static Conveyor>,
+    belt: Option<&'static PayloadTransportLine>,
+    generator: Option<&'static Generator>,
+    distributor: Option<&'static Distributor>,
+    operator: Option<&'static OperatorTile>,
+    unary_operator: Option<&'static UnaryOperatorTile>,
+    bridge: Option<&'static BridgeConveyor>,
+    sink: Option<&'static Sink>,
+    storage: Option<&'static Storage>,
+    congestion: Option<&'static Congestion>,
+}
+
+#[expect(clippy::too_many_arguments)]
+fn show_tile_inspector(
+    mut contexts: EguiContexts,
+    mut inspector: ResMut<TileInspector>,
+    hovered_tile: Single<&TilePos, With<HoveredTile>>,
+    tile_storage: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    tiles: Query<InspectedTile>,
+    payloads: Query<(Option<&Operand>, Has<ErrorPayload>), With<Payload>>,
+    time: Res<Time>,
+    throughput: Res<Throughput>,
+) -> Result {
+    if !inspector.open {
+        return Ok(());
+    }
+
+    let inspector = &mut *inspector;
+    let tile_pos = inspector.pinned.unwrap_or(**hovered_tile);
+    let entity = tile_storage.get(&tile_pos);
+    let tile = entity.and_then(|entity| tiles.get(entity).ok());
+    let per_minute = entity
+        .and_then(|entity| throughput.get(entity))
+        .map_or(0, |series| series.per_minute());
+
+    egui::Window::new("Tile inspector (I)")
+        .open(&mut inspector.open)
+        .resizable(false)
+        .show(contexts.ctx_mut()?, |ui| {
+            ui.label(format!(
+                "{}, {}{}",
+                tile_pos.x,
+                tile_pos.y,
+                if inspector.pinned.is_some() {
+                    " (pinned, click it again to follow the cursor)"
+                } else {
+                    " (click a tile to pin it)"
+                }
+            ));
+            let Some(tile) = tile else {
+                ui.label("Empty");
+                return;
+            };
+
+            ui.heading(tile.name.map_or("Unnamed tile", Name::as_str));
+
+            if let Some(conveyor) = tile.conveyor {
+                ui.label(format!("Inputs: {}", directions_text(conveyor.inputs())));
+                ui.label(format!("Outputs: {}", directions_text(conveyor.outputs())));
+            }
+
+            let line_ui = |ui: &mut egui::Ui, label: &str, line: &PayloadTransportLine| {
+                let destination = line
+                    .destination()
+                    .map_or(String::new(), |dir| format!(" to {dir:?}"));
+                ui.label(format!("{label}{destination}: {} payloads", line.count()));
+                for (payload, mu) in line.payloads() {
+                    ui.label(format!(
+                        "    mu {mu:.2}: {}",
+                        payload_text(&payloads, payload)
+                    ));
+                }
+            };
+
+            if let Some(line) = tile.belt {
+                line_ui(ui, "Belt", line);
+            }
+
+            if let Some(generator) = tile.generator {
+                let due = (generator.next_generate_time() - time.elapsed_secs()).max(0.0);
+                ui.label(format!(
+                    "Next payload in {due:.1}s, every {:.1}s",
+                    generator.time_between_generations
+                ));
+                ui.label(format!("Next output: {:?}", generator.next_output()));
+                for (_, line) in generator.outputs() {
+                    line_ui(ui, "Output", line);
+                }
+            }
+
+            if let Some(distributor) = tile.distributor {
+                ui.label(format!("Next output: {:?}", distributor.next_output()));
+                line_ui(ui, "Input", distributor.input());
+                for (_, line) in distributor.outputs() {
+                    line_ui(ui, "Output", line);
+                }
+            }
+
+            if let Some(operator) = tile.operator {
+                for (side, operand) in ["Left", "Right"].into_iter().zip(operator.operands()) {
+                    ui.label(match operand {
+                        Some((payload, from)) => format!(
+                            "{side} operand from {from:?}: {}",
+                            payload_text(&payloads, payload)
+                        ),
+                        None => format!("{side} operand: waiting"),
+                    });
+                }
+                line_ui(ui, "Result", operator.payload_transport_line());
+            }
+
+            if let Some(operator) = tile.unary_operator {
+                line_ui(ui, "Belt", operator.payload_transport_line());
+            }
+
+            if let Some(bridge) = tile.bridge {
+                for line in bridge.lines() {
+                    line_ui(ui, "Line", line);
+                }
+            }
+
+            if let Some(sink) = tile.sink {
+                ui.label(format!("Sunk: {}", sink.tally()));
+                if sink.is_jammed() {
+                    ui.label("Jammed by an error payload");
+                }
+            }
+
+            if let Some(congestion) = tile.congestion {
+                ui.label(format!(
+                    "Stalled for {:.1}s, {:.0}% full, heat {:.2}",
+                    congestion.stalled_for(),
+                    congestion.fill() * 100.0,
+                    congestion.heat()
+                ));
+            }
+
+            if tile.generator.is_some() || tile.sink.is_some() || tile.belt.is_some() {
+                ui.label(format!("{per_minute} payloads in the last minute"));
+            }
+
+            if let Some(storage) = tile.storage {
+                ui.label(format!(
+                    "Holding {}/{}",
+                    storage.count(),
+                    storage.capacity()
+                ));
+            }
+        });
+
+    Ok(())
+}
+
+fn directions_text(directions: ConveyorDirections) -> String {
+    let directions: Vec<_> = directions
+        .iter()
+        .map(|dir: ConveyorDirection| format!("{dir:?}"))
+        .collect();
+    if directions.is_empty() {
+        "none".to_string()
+    } else {
+        directions.join(", ")
+    }
+}
+
+fn payload_text(
+    payloads: &Query<(Option<&Operand>, Has<ErrorPayload>), With<Payload>>,
+    payload: Entity,
+) -> String {
+    match payloads.get(payload) {
+        Ok((Some(Operand(value)), _)) => value.source_text(),
+        Ok((None, true)) => "error".to_string(),
+        _ => "unknown".to_string(),
+    }
+}
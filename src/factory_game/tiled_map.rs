@@ -0,0 +1,206 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use tiled::{Loader, PropertyValue};
+
+use crate::{
+    GameState,
+    factory_game::{MapConfig, play_level},
+    helpers::asset_path,
+};
+
+pub fn tiled_map_plugin(app: &mut App) {
+    app.add_systems(
+        OnEnter(GameState::FactoryGame),
+        make_terrain_layers
+            .run_if(resource_exists::<TiledMap>)
+            .before(super::make_base_layer),
+    );
+}
+
+/// In the assets directory
+pub const FACTORY_MAP_PATH: &str = "factory-map1.xml";
+
+/// Tile properties that stop anything being placed on a tile
+const BLOCKING_PROPERTIES: [&str; 2] = ["blocked", "water"];
+
+/// Terrain to build a factory on, drawn beneath the base layer.  Only tiles
+/// from the map's first tileset are drawn.
+#[derive(Resource, Debug)]
+pub struct TiledMap {
+    size: TilemapSize,
+    tile_size: TilemapTileSize,
+    /// The tileset image, relative to the assets directory
+    image: PathBuf,
+    /// Texture indices of each tile layer's tiles, bottom layer first
+    layers: Vec<Vec<Option<u32>>>,
+    blocked: Vec<bool>,
+}
+
+impl TiledMap {
+    /// Loads the map at `path` in the assets directory
+    pub fn load(path: impl AsRef<Path>) -> Option<Self> {
+        let assets_dir = asset_path("");
+        let map = Loader::new()
+            .load_tmx_map(assets_dir.join(path))
+            .inspect_err(|e| warn!("Can't load map: {e}"))
+            .ok()?;
+
+        let Some(image) = map.tilesets().first().and_then(|tileset| {
+            tileset
+                .image
+                .as_ref()?
+                .source
+                .strip_prefix(&assets_dir)
+                .ok()
+                .map(Path::to_path_buf)
+        }) else {
+            warn!("Map has no tileset image in {}", assets_dir.display());
+            return None;
+        };
+
+        let size = TilemapSize {
+            x: map.width,
+            y: map.height,
+        };
+        let mut layers = vec![];
+        let mut blocked = vec![false; size.count()];
+
+        for layer in map.layers().filter_map(|layer| layer.as_tile_layer()) {
+            let mut indices = vec![None; size.count()];
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    // Tiled's rows go down the screen, but tile positions go up
+                    let Some(tile) = layer.get_tile(x as i32, (size.y - 1 - y) as i32) else {
+                        continue;
+                    };
+                    if tile.tileset_index() != 0 {
+                        continue;
+                    }
+
+                    let index = TilePos { x, y }.to_index(&size);
+                    indices[index] = Some(tile.id());
+                    blocked[index] |= tile.get_tile().is_some_and(|tile| {
+                        BLOCKING_PROPERTIES.iter().any(|name| {
+                            tile.properties.get(*name) == Some(&PropertyValue::BoolValue(true))
+                        })
+                    });
+                }
+            }
+            layers.push(indices);
+        }
+
+        Some(TiledMap {
+            size,
+            tile_size: TilemapTileSize {
+                x: map.tile_width as f32,
+                y: map.tile_height as f32,
+            },
+            image,
+            layers,
+            blocked,
+        })
+    }
+
+    pub fn size(&self) -> TilemapSize {
+        self.size
+    }
+
+    /// Whether nothing can be placed at `tile_pos`
+    pub fn is_blocked(&self, tile_pos: &TilePos) -> bool {
+        tile_pos.within_map_bounds(&self.size) && self.blocked[tile_pos.to_index(&self.size)]
+    }
+}
+
+/// Starts the sandbox on the Tiled map at `path`, sized to fit it.  The map
+/// takes the place of generated deposits, so generators can go anywhere.
+pub fn play_tiled_map(commands: &mut Commands, path: impl AsRef<Path>) {
+    let Some(map) = TiledMap::load(path) else {
+        return;
+    };
+
+    play_level(commands, None);
+    commands.insert_resource(MapConfig {
+        size: map.size(),
+        ..default()
+    });
+    commands.insert_resource(map);
+}
+
+/// One of the Tiled map's layers
+#[derive(Component)]
+pub struct TerrainLayer;
+
+fn make_terrain_layers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<MapConfig>,
+    map: Res<TiledMap>,
+) {
+    let texture = asset_server.load(map.image.clone());
+
+    // Map tiles may be bigger or smaller than ours, so the layers are scaled
+    // to line up with the base layer
+    let scale = config.grid_size.x / map.tile_size.x;
+    let map_config = MapConfig {
+        size: map.size,
+        tile_size: map.tile_size,
+        grid_size: map.tile_size.into(),
+        ..default()
+    };
+
+    for (i, indices) in map.layers.iter().enumerate() {
+        let mut layer = commands.spawn((
+            TerrainLayer,
+            super::make_layer(&map_config, texture.clone(), 0.0, "TerrainLayer"),
+        ));
+        layer.insert(
+            Transform::from_xyz(0.0, 0.0, -3.0 + 0.1 * i as f32).with_scale(Vec3::splat(scale)),
+        );
+
+        let layer_entity = layer.id();
+        let mut storage = TileStorage::empty(map.size);
+        for (index, texture_index) in indices.iter().enumerate() {
+            let Some(texture_index) = texture_index else {
+                continue;
+            };
+            let position = TilePos {
+                x: index as u32 % map.size.x,
+                y: index as u32 / map.size.x,
+            };
+            let tile = commands
+                .spawn((
+                    StateScoped(GameState::FactoryGame),
+                    TileBundle {
+                        position,
+                        tilemap_id: TilemapId(layer_entity),
+                        texture_index: TileTextureIndex(*texture_index),
+                        ..default()
+                    },
+                ))
+                .id();
+            storage.set(&position, tile);
+        }
+        commands.entity(layer_entity).insert(storage);
+    }
+}
+
+#[cfg(test)]
+mod tiled_map_test {
+    use super::*;
+
+    #[test]
+    fn factory_map_loads_with_obstacles() {
+        let map = TiledMap::load(FACTORY_MAP_PATH).unwrap();
+
+        assert_eq!(map.size(), TilemapSize { x: 24, y: 16 });
+        assert_eq!(map.layers.len(), 2);
+        assert_eq!(map.image, Path::new("kenny.nl/towerDefense_tilesheet.png"));
+
+        // The bush in the top left corner, and the grass beside it
+        assert!(map.is_blocked(&TilePos { x: 1, y: 14 }));
+        assert!(!map.is_blocked(&TilePos { x: 2, y: 14 }));
+        assert!(!map.is_blocked(&TilePos { x: 100, y: 100 }));
+    }
+}
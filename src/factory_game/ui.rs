@@ -1,12 +1,31 @@
 use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
 
-use crate::{GameState, factory_game::interaction::Tools, sprite_sheet::SpriteSheet};
+use crate::{
+    GameState,
+    factory_game::{
+        BaseLayer,
+        chunks::ChunkedTileStorage,
+        economy::Money,
+        interaction::{HoveredTile, Tools},
+        operators::{OperatorTile, OverflowPolicy},
+        unary_operators::UnaryOperatorTile,
+    },
+    sprite_sheet::SpriteSheet,
+};
 
 pub fn ui_plugin(app: &mut App) {
     app.add_systems(OnEnter(GameState::FactoryGame), create_ui)
         .add_systems(
             Update,
-            update_tools.run_if(resource_exists_and_changed::<Tools>),
+            (
+                update_tools.run_if(
+                    resource_exists::<Tools>
+                        .and(resource_changed::<Tools>.or(resource_exists_and_changed::<Money>)),
+                ),
+                update_overflow_policy.run_if(resource_exists::<OverflowPolicy>),
+                update_money.run_if(resource_exists_and_changed::<Money>),
+            ),
         );
 }
 
@@ -27,23 +46,93 @@ fn create_ui(mut commands: Commands) {
         BackgroundColor(Color::BLACK),
         BorderColor(Color::WHITE),
     ));
+
+    commands.spawn((
+        StateScoped(GameState::FactoryGame),
+        Node {
+            margin: UiRect::all(Val::Px(10.0)),
+            align_self: AlignSelf::Start,
+            justify_self: JustifySelf::Start,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        children![
+            (
+                OverflowPolicyText,
+                Text::default(),
+                TextFont::from_font_size(14.0),
+            ),
+            (MoneyText, Text::default(), TextFont::from_font_size(14.0)),
+        ],
+    ));
+}
+
+#[derive(Component)]
+struct MoneyText;
+
+fn update_money(money: Res<Money>, mut text: Single<&mut Text, With<MoneyText>>) {
+    text.0 = format!("Money: ${}", money.balance());
+}
+
+#[derive(Component)]
+struct OverflowPolicyText;
+
+fn update_overflow_policy(
+    overflow_policy: Res<OverflowPolicy>,
+    hovered_tile: Option<Single<&TilePos, With<HoveredTile>>>,
+    tile_storage: Option<Single<&ChunkedTileStorage, With<BaseLayer>>>,
+    operators: Query<&OperatorTile>,
+    unary_operators: Query<&UnaryOperatorTile>,
+    texts: Query<&mut Text, With<OverflowPolicyText>>,
+) {
+    let mut new_text = format!("Overflow: {:?} (O to change)", *overflow_policy);
+    // The override of the operator under the cursor, if it has one
+    if let Some(hovered_tile) = hovered_tile
+        && let Some(tile_storage) = tile_storage
+        && let Some(entity) = tile_storage.get(&hovered_tile)
+        && let Some(policy) = operators
+            .get(entity)
+            .map(OperatorTile::overflow_policy)
+            .or_else(|_| {
+                unary_operators
+                    .get(entity)
+                    .map(UnaryOperatorTile::overflow_policy)
+            })
+            .ok()
+            .flatten()
+    {
+        new_text += &format!("\nThis operator: {policy:?} (shift-O to change)");
+    }
+    for mut text in texts {
+        if text.0 != new_text {
+            text.0 = new_text.clone();
+        }
+    }
 }
 
 #[derive(Component)]
 struct ToolsPanel;
 
 #[derive(Component)]
-struct ToolPanelSlot(u32);
+struct ToolPanelSlot {
+    slot: u32,
+    cost: u32,
+}
 
 fn update_tools(
     mut commands: Commands,
     tools: Res<Tools>,
+    money: Option<Res<Money>>,
     sprite_sheet: Res<SpriteSheet>,
     panel: Single<Entity, With<ToolsPanel>>,
-    tool_panel_slots: Query<(&mut BackgroundColor, &mut Node, &ToolPanelSlot)>,
+    tool_panel_slots: Query<(&mut BackgroundColor, &mut Node, &ToolPanelSlot, &Children)>,
+    mut images: Query<&mut ImageNode>,
 ) {
     let inactive_color = Color::srgb(0.4, 0.4, 0.4);
     let active_color = Color::srgb(0.4, 0.1, 0.1);
+    let unavailable_color = Color::srgb(0.15, 0.15, 0.15);
+
+    let can_afford = |cost| money.as_ref().is_none_or(|money| money.can_afford(cost));
 
     let is_active_slot = |slot| {
         tools
@@ -52,14 +141,45 @@ fn update_tools(
             .unwrap_or(false)
     };
 
+    let is_locked_slot = |slot| {
+        tools
+            .tools()
+            .iter()
+            .any(|entry| entry.slot() == slot && entry.is_locked())
+    };
+
+    // Locked tools are greyed out, and tools that can't be afforded dimmed
+    let slot_color = |slot: &ToolPanelSlot| {
+        if is_active_slot(slot.slot) {
+            active_color
+        } else if is_locked_slot(slot.slot) || !can_afford(slot.cost) {
+            unavailable_color
+        } else {
+            inactive_color
+        }
+    };
+    let image_color = |slot: &ToolPanelSlot| {
+        if is_locked_slot(slot.slot) {
+            Color::srgba(1.0, 1.0, 1.0, 0.25)
+        } else {
+            Color::WHITE
+        }
+    };
+
     if tools.is_added() {
         let tool_selectors: Vec<_> = tools
             .tools()
             .iter()
             .map(|t| {
+                let slot = ToolPanelSlot {
+                    slot: t.slot(),
+                    cost: t.tool().cost(),
+                };
+                let (background, tint) = (slot_color(&slot), image_color(&slot));
+
                 commands
                     .spawn((
-                        ToolPanelSlot(t.slot()),
+                        slot,
                         Node {
                             margin: UiRect::all(Val::Px(2.0)),
                             padding: UiRect::all(Val::Px(10.0)),
@@ -67,7 +187,7 @@ fn update_tools(
                             min_height: Val::Px(32.0),
                             ..default()
                         },
-                        BackgroundColor(inactive_color),
+                        BackgroundColor(background),
                     ))
                     .with_children(|c| {
                         let (sprite, _) = t.tool().get_sprite_flip();
@@ -76,7 +196,8 @@ fn update_tools(
                             ImageNode::from_atlas_image(
                                 sprite_sheet.image(),
                                 sprite_sheet.texture_atlas(sprite),
-                            ),
+                            )
+                            .with_color(tint),
                             Node {
                                 width: Val::Px(32.0),
                                 height: Val::Px(32.0),
@@ -92,6 +213,18 @@ fn update_tools(
                                 ..default()
                             },
                         ));
+                        if money.is_some() && t.tool().cost() > 0 {
+                            c.spawn((
+                                Text::new(format!("${}", t.tool().cost())),
+                                TextFont::from_font_size(10.0),
+                                Node {
+                                    position_type: PositionType::Absolute,
+                                    top: Val::Px(0.0),
+                                    right: Val::Px(2.0),
+                                    ..default()
+                                },
+                            ));
+                        }
                     })
                     .id()
             })
@@ -104,13 +237,17 @@ fn update_tools(
         return;
     }
 
-    for (mut bg, mut node, slot) in tool_panel_slots {
-        if is_active_slot(slot.0) {
-            bg.0 = active_color;
-            node.top = Val::Px(-2.0);
+    for (mut bg, mut node, slot, children) in tool_panel_slots {
+        bg.0 = slot_color(slot);
+        node.top = if is_active_slot(slot.slot) {
+            Val::Px(-2.0)
         } else {
-            bg.0 = inactive_color;
-            node.top = Val::Auto;
+            Val::Auto
+        };
+
+        let mut children = images.iter_many_mut(children);
+        while let Some(mut image) = children.fetch_next() {
+            image.color = image_color(slot);
         }
     }
 }
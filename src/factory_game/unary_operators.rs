@@ -0,0 +1,513 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
+use smallvec::SmallVec;
+
+use crate::{
+    factory_game::{
+        BaseLayer, ConveyorSystems,
+        chunks::ChunkedTileStorage,
+        conveyor::Conveyor,
+        helpers::{ConveyorDirection, ConveyorDirections},
+        interaction::{PlaceTileEvent, RegisterPlaceTileEvent, Tool},
+        operators::{CycleOverflowPolicyEvent, Operand, OverflowPolicy, error_payload_bundle},
+        payload_handler::{AddPayloadHandler, PayloadHandler},
+        payloads::{Payload, PayloadTransportLine, RequestPayloadTransferEvent},
+        power::PowerConsumer,
+        value::Value,
+    },
+    helpers::TilemapQuery,
+    sprite_sheet::GameSprite,
+};
+
+pub fn unary_operators_plugin(app: &mut App) {
+    app.register_place_tile_event::<PlaceUnaryOperatorEvent>()
+        .add_payload_handler::<UnaryOperatorTile>()
+        .register_type::<UnaryOperator>()
+        .add_observer(on_unary_operator_tool)
+        .add_observer(cycle_unary_operator_overflow_policy)
+        .add_systems(
+            Update,
+            (
+                update_unary_operator_tiles.in_set(ConveyorSystems::TileUpdater),
+                (apply_unary_operators, update_unary_operator_payloads)
+                    .chain()
+                    .in_set(ConveyorSystems::TransportLogic),
+                update_unary_operator_transforms.in_set(ConveyorSystems::PayloadTransforms),
+            ),
+        );
+}
+
+/// The largest constant that can be dialled into a "+K" or "×K" tile before
+/// it wraps back round to 1.
+const MAX_CONSTANT: i64 = 10;
+
+/// Operators that take a single operand.  The constant operators carry their
+/// constant, K, with them.  Negate also flips bools and inverts colours; the
+/// rest only work on ints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum UnaryOperator {
+    Increment,
+    Double,
+    Square,
+    Negate,
+    AddConstant(i64),
+    MultiplyConstant(i64),
+}
+
+impl UnaryOperator {
+    fn sprite(&self) -> GameSprite {
+        match self {
+            UnaryOperator::Increment => GameSprite::UnaryIncrement,
+            UnaryOperator::Double => GameSprite::UnaryDouble,
+            UnaryOperator::Square => GameSprite::UnarySquare,
+            UnaryOperator::Negate => GameSprite::UnaryNegate,
+            UnaryOperator::AddConstant(_) => GameSprite::UnaryAddConstant,
+            UnaryOperator::MultiplyConstant(_) => GameSprite::UnaryMultiplyConstant,
+        }
+    }
+
+    pub fn constant(&self) -> Option<i64> {
+        match self {
+            UnaryOperator::AddConstant(k) | UnaryOperator::MultiplyConstant(k) => Some(*k),
+            _ => None,
+        }
+    }
+
+    /// The fixed operators cycle between each other, the constant operators
+    /// step their constant.
+    fn next_variant(&self) -> Self {
+        let next_constant = |k: i64| k.rem_euclid(MAX_CONSTANT) + 1;
+
+        match self {
+            UnaryOperator::Increment => UnaryOperator::Double,
+            UnaryOperator::Double => UnaryOperator::Square,
+            UnaryOperator::Square => UnaryOperator::Negate,
+            UnaryOperator::Negate => UnaryOperator::Increment,
+            UnaryOperator::AddConstant(k) => UnaryOperator::AddConstant(next_constant(*k)),
+            UnaryOperator::MultiplyConstant(k) => {
+                UnaryOperator::MultiplyConstant(next_constant(*k))
+            }
+        }
+    }
+
+    /// Results that don't fit in an operand are handled by the overflow
+    /// policy, as with the binary operators.  Returns None for an error.
+    pub fn apply(&self, operand: &Operand, policy: OverflowPolicy) -> Option<Operand> {
+        let result = match (self, &operand.0) {
+            (UnaryOperator::Negate, Value::Bool(value)) => Some(Value::Bool(!value)),
+            (UnaryOperator::Negate, Value::Colour(value)) => {
+                Some(Value::Colour(value.map(|channel| u8::MAX - channel)))
+            }
+            (_, Value::Int(value)) => self.apply_int(*value, policy).map(Value::Int),
+            _ => None,
+        };
+        result.map(Operand)
+    }
+
+    fn apply_int(&self, value: i64, policy: OverflowPolicy) -> Option<i64> {
+        let add = |k: i64| {
+            policy.resolve(
+                value.checked_add(k),
+                || value.wrapping_add(k),
+                || value.saturating_add(k),
+            )
+        };
+        let multiply = |k: i64| {
+            policy.resolve(
+                value.checked_mul(k),
+                || value.wrapping_mul(k),
+                || value.saturating_mul(k),
+            )
+        };
+
+        match self {
+            UnaryOperator::Increment => add(1),
+            UnaryOperator::Double => multiply(2),
+            UnaryOperator::Square => multiply(value),
+            UnaryOperator::Negate => policy.resolve(
+                value.checked_neg(),
+                || value.wrapping_neg(),
+                || value.saturating_neg(),
+            ),
+            UnaryOperator::AddConstant(k) => add(*k),
+            UnaryOperator::MultiplyConstant(k) => multiply(*k),
+        }
+    }
+}
+
+/// Places single input operators.  A unary operator placed over a belt takes
+/// over the belt's direction, otherwise it points the tool's way.  Using the
+/// tool on a constant operator of the same kind steps its constant instead of
+/// replacing it.
+pub struct UnaryOperatorsTool {
+    operator: UnaryOperator,
+    direction: ConveyorDirection,
+}
+
+/// Power a unary operator needs to run at full speed
+const POWER: u32 = 2;
+
+/// The way the tool points at first, which is on every map
+const FIRST_DIRECTION: ConveyorDirection = ConveyorDirection::East;
+
+impl UnaryOperatorsTool {
+    fn new(operator: UnaryOperator) -> Self {
+        Self {
+            operator,
+            direction: FIRST_DIRECTION,
+        }
+    }
+
+    pub fn unary() -> Self {
+        Self::new(UnaryOperator::Increment)
+    }
+
+    pub fn add_constant() -> Self {
+        Self::new(UnaryOperator::AddConstant(1))
+    }
+
+    pub fn multiply_constant() -> Self {
+        Self::new(UnaryOperator::MultiplyConstant(2))
+    }
+}
+
+impl Tool for UnaryOperatorsTool {
+    fn get_sprite_flip(&self) -> (GameSprite, TileFlip) {
+        (self.operator.sprite(), self.direction.tile_flip())
+    }
+
+    /// Turns the tool, and after a full turn moves on to the next operator
+    fn next_variant(&mut self, map_type: &TilemapType) {
+        self.direction = self.direction.next(map_type);
+        if self.direction == FIRST_DIRECTION {
+            self.operator = self.operator.next_variant();
+        }
+    }
+
+    fn cost(&self) -> u32 {
+        PlaceUnaryOperatorEvent(TilePos::default(), self.operator, self.direction).cost()
+    }
+
+    fn execute(&self, mut commands: Commands, tile_pos: &TilePos) {
+        commands.trigger(UnaryOperatorToolEvent(
+            *tile_pos,
+            self.operator,
+            self.direction,
+        ));
+    }
+}
+
+/// Sent when the unary operator tool is used on a tile, before deciding
+/// whether to place a new operator or edit the one that is already there.
+#[derive(Event, Debug)]
+pub struct UnaryOperatorToolEvent(pub TilePos, pub UnaryOperator, pub ConveyorDirection);
+
+fn on_unary_operator_tool(
+    trigger: Trigger<UnaryOperatorToolEvent>,
+    mut commands: Commands,
+    tile_storage: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    mut unary_operators: Query<&mut UnaryOperatorTile>,
+    conveyors: Query<&Conveyor>,
+) {
+    let UnaryOperatorToolEvent(tile_pos, operator, tool_direction) = *trigger.event();
+    let existing = tile_storage.get(&tile_pos);
+
+    if let Some(entity) = existing
+        && let Ok(mut tile) = unary_operators.get_mut(entity)
+        && operator.constant().is_some()
+        && std::mem::discriminant(&tile.operator) == std::mem::discriminant(&operator)
+    {
+        tile.operator = tile.operator.next_variant();
+        return;
+    }
+
+    let direction = existing
+        .and_then(|entity| conveyors.get(entity).ok())
+        .filter(|conveyor| !conveyor.outputs().is_multiple())
+        .and_then(|conveyor| conveyor.single_or_no_output())
+        .unwrap_or(tool_direction);
+
+    commands.trigger(PlaceUnaryOperatorEvent(tile_pos, operator, direction));
+}
+
+#[derive(Event, Debug)]
+pub struct PlaceUnaryOperatorEvent(pub TilePos, pub UnaryOperator, pub ConveyorDirection);
+
+impl PlaceTileEvent for PlaceUnaryOperatorEvent {
+    fn tile_pos(&self) -> TilePos {
+        self.0
+    }
+
+    fn cost(&self) -> u32 {
+        10
+    }
+
+    fn configure_new_entity(&self, mut commands: EntityCommands) {
+        commands.insert((
+            Name::new(format!("{:?}", self.1)),
+            unary_operator_bundle(self.1, self.2),
+        ));
+    }
+}
+
+/// A belt-like tile that applies its operator to every payload passing
+/// straight through it.
+#[derive(Component, Debug, Reflect)]
+#[require(ConstantLabels, PowerConsumer::new(POWER))]
+pub struct UnaryOperatorTile {
+    operator: UnaryOperator,
+    /// Overrides the factory's `OverflowPolicy`
+    overflow_policy: Option<OverflowPolicy>,
+    payload_transport_line: PayloadTransportLine,
+    /// Payloads that have arrived but not been operated on yet
+    pending: SmallVec<[Entity; 2]>,
+}
+
+#[derive(Component, Default)]
+#[relationship_target(relationship = ConstantLabel, linked_spawn)]
+pub struct ConstantLabels(Vec<Entity>);
+
+/// Mark the text showing a constant operator's constant so it is despawned
+/// along with the operator
+#[derive(Component)]
+#[relationship(relationship_target = ConstantLabels)]
+pub struct ConstantLabel(Entity);
+
+impl PayloadHandler for UnaryOperatorTile {
+    fn try_transfer(
+        &mut self,
+        _: &Conveyor,
+        request: &RequestPayloadTransferEvent,
+    ) -> Option<Entity> {
+        if request.direction != self.payload_transport_line.output_direction() {
+            return None;
+        }
+
+        let payload = self
+            .payload_transport_line
+            .try_transfer_onto(request.direction.opposite(), || request.payload)?;
+        self.pending.push(payload);
+        Some(payload)
+    }
+
+    fn remove_payload(&mut self, payload: Entity) {
+        self.payload_transport_line.remove_payload(payload);
+        self.pending.retain(|p| *p != payload);
+    }
+
+    fn iter_payloads(&self) -> impl Iterator<Item = Entity> {
+        self.payload_transport_line.iter_payloads()
+    }
+
+    fn transport_lines(&self) -> impl Iterator<Item = &PayloadTransportLine> {
+        std::iter::once(&self.payload_transport_line)
+    }
+}
+
+impl UnaryOperatorTile {
+    pub fn new(operator: UnaryOperator, direction: ConveyorDirection) -> Self {
+        UnaryOperatorTile {
+            operator,
+            overflow_policy: None,
+            payload_transport_line: PayloadTransportLine::new(direction, 2),
+            pending: SmallVec::default(),
+        }
+    }
+
+    pub fn payload_transport_line(&self) -> &PayloadTransportLine {
+        &self.payload_transport_line
+    }
+
+    pub fn overflow_policy(&self) -> Option<OverflowPolicy> {
+        self.overflow_policy
+    }
+}
+
+fn cycle_unary_operator_overflow_policy(
+    trigger: Trigger<CycleOverflowPolicyEvent>,
+    tile_storage: Single<&ChunkedTileStorage, With<BaseLayer>>,
+    mut unary_operators: Query<&mut UnaryOperatorTile>,
+) {
+    if let Some(entity) = tile_storage.get(&trigger.event().0)
+        && let Ok(mut tile) = unary_operators.get_mut(entity)
+    {
+        tile.overflow_policy = OverflowPolicy::next_override(tile.overflow_policy);
+    }
+}
+
+pub fn unary_operator_bundle(operator: UnaryOperator, direction: ConveyorDirection) -> impl Bundle {
+    let mut conveyor = Conveyor::from(direction);
+    conveyor.set_inputs(ConveyorDirections::new(direction.opposite()));
+
+    (UnaryOperatorTile::new(operator, direction), conveyor)
+}
+
+/// Error payloads have no `Operand`, so they pass through untouched.  Values
+/// that become errors lose their colour swatch too, so they look like errors.
+fn apply_unary_operators(
+    mut commands: Commands,
+    unary_operators: Query<&mut UnaryOperatorTile>,
+    mut operands: Query<&mut Operand>,
+    overflow_policy: Res<OverflowPolicy>,
+) {
+    for mut tile in unary_operators {
+        let operator = tile.operator;
+        let policy = tile.overflow_policy.unwrap_or(*overflow_policy);
+
+        for payload in tile.pending.drain(..) {
+            if let Ok(mut operand) = operands.get_mut(payload) {
+                match operator.apply(&operand, policy) {
+                    Some(result) => *operand = result,
+                    None => {
+                        commands
+                            .entity(payload)
+                            .remove::<(Operand, Sprite)>()
+                            .insert(error_payload_bundle());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn update_unary_operator_payloads(
+    unary_operators: Query<(Entity, &mut UnaryOperatorTile, &TilePos, &PowerConsumer)>,
+    time: Res<Time>,
+    base: Single<(&ChunkedTileStorage, &TilemapSize), With<BaseLayer>>,
+    mut send_payloads: EventWriter<RequestPayloadTransferEvent>,
+) {
+    let (tile_storage, map_size) = base.into_inner();
+    let t = time.delta_secs();
+
+    for (entity, mut tile, tile_pos, power) in unary_operators {
+        tile.payload_transport_line.update(
+            entity,
+            tile_pos,
+            t * power.speed(),
+            tile_storage,
+            map_size,
+            &mut send_payloads,
+        );
+    }
+}
+
+fn update_unary_operator_tiles(
+    mut commands: Commands,
+    new_operators: Query<
+        (Entity, &TilePos, &UnaryOperatorTile, &Conveyor),
+        Without<TileTextureIndex>,
+    >,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (entity, tile_pos, tile, conveyor) in new_operators {
+        if tile.operator.constant().is_some() {
+            let tile_center = base.center_in_world(tile_pos);
+            commands.spawn((
+                Name::new("Constant"),
+                Text2d::default(),
+                TextFont::from_font_size(10.0),
+                Transform::from_translation(
+                    (tile_center + Vec2::new(0.0, -base.tile_size.y / 3.0)).extend(2.0),
+                ),
+                ConstantLabel(entity),
+            ));
+        }
+
+        commands.entity(entity).insert_if_new(TileBundle {
+            tilemap_id: TilemapId(base.entity),
+            texture_index: tile.operator.sprite().tile_texture_index(),
+            flip: conveyor.output().tile_flip(),
+            ..default()
+        });
+    }
+}
+
+fn update_unary_operator_transforms(
+    unary_operators: Query<(&TilePos, &UnaryOperatorTile)>,
+    mut labels: Query<(&ConstantLabel, &mut Text2d)>,
+    mut payloads: Query<&mut Transform, With<Payload>>,
+    base: Single<TilemapQuery, With<BaseLayer>>,
+) {
+    for (tile_pos, tile) in unary_operators {
+        tile.payload_transport_line
+            .update_payload_transforms(tile_pos, &mut payloads, &base);
+    }
+
+    for (label, mut text) in &mut labels {
+        if let Ok((_, tile)) = unary_operators.get(label.0)
+            && let Some(k) = tile.operator.constant()
+        {
+            let new_text = format!("K={k}");
+            if text.0 != new_text {
+                text.0 = new_text;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod unary_operator_test {
+    use super::*;
+    use UnaryOperator::*;
+
+    fn apply(operator: UnaryOperator, value: Value) -> Option<Value> {
+        operator
+            .apply(&Operand(value), OverflowPolicy::Error)
+            .map(|o| o.0)
+    }
+
+    fn apply_int(operator: UnaryOperator, value: i64) -> i64 {
+        match apply(operator, Value::Int(value)) {
+            Some(Value::Int(result)) => result,
+            other => panic!("Expected an int, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn fixed_operators() {
+        assert_eq!(apply_int(Increment, 7), 8);
+        assert_eq!(apply_int(Double, 7), 14);
+        assert_eq!(apply_int(Square, 7), 49);
+        assert_eq!(apply_int(Negate, 7), -7);
+    }
+
+    #[test]
+    fn constant_operators() {
+        assert_eq!(apply_int(AddConstant(5), 7), 12);
+        assert_eq!(apply_int(MultiplyConstant(3), 7), 21);
+    }
+
+    #[test]
+    fn negate_other_types() {
+        assert_eq!(apply(Negate, Value::Bool(true)), Some(Value::Bool(false)));
+        assert_eq!(
+            apply(Negate, Value::Colour([255, 0, 55])),
+            Some(Value::Colour([0, 255, 200]))
+        );
+        assert_eq!(apply(Negate, Value::Text("abc".to_string())), None);
+        assert_eq!(apply(Increment, Value::Bool(true)), None);
+    }
+
+    #[test]
+    fn out_of_range_results() {
+        let max = Operand(Value::Int(i64::MAX));
+
+        assert!(Square.apply(&max, OverflowPolicy::Error).is_none());
+        assert_eq!(
+            Increment.apply(&max, OverflowPolicy::Wrapping),
+            Some(Operand(Value::Int(i64::MIN)))
+        );
+        assert_eq!(Double.apply(&max, OverflowPolicy::Saturating), Some(max));
+    }
+
+    #[test]
+    fn variants() {
+        assert_eq!(Square.next_variant(), Negate);
+        assert_eq!(Negate.next_variant(), Increment);
+        assert_eq!(AddConstant(3).next_variant(), AddConstant(4));
+        assert_eq!(
+            MultiplyConstant(MAX_CONSTANT).next_variant(),
+            MultiplyConstant(1)
+        );
+    }
+}
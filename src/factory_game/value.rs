@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer, de::Error};
+
+/// The longest text a payload can carry
+pub const MAX_TEXT_LEN: usize = 8;
+
+/// The data carried by a payload.  Operators only combine values of the same
+/// type; anything else gives an error payload.
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub enum Value {
+    Int(i64),
+    Bool(bool),
+    /// Red, green and blue channels
+    Colour([u8; 3]),
+    /// At most `MAX_TEXT_LEN` characters
+    Text(String),
+}
+
+impl Value {
+    /// Reads a value as typed by the player: an int, `true` or `false`, a
+    /// colour as `#rrggbb`, or else a short text, optionally quoted.
+    pub fn parse(source: &str) -> Option<Value> {
+        let source = source.trim();
+
+        if let Ok(value) = source.parse::<i64>() {
+            return Some(Value::Int(value));
+        }
+        if let Ok(value) = source.parse::<bool>() {
+            return Some(Value::Bool(value));
+        }
+        if let Some(hex) = source.strip_prefix('#')
+            && hex.len() == 6
+            && let Ok(rgb) = u32::from_str_radix(hex, 16)
+        {
+            let [_, r, g, b] = rgb.to_be_bytes();
+            return Some(Value::Colour([r, g, b]));
+        }
+
+        let text = source
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .unwrap_or(source);
+        (!text.is_empty() && text.chars().count() <= MAX_TEXT_LEN)
+            .then(|| Value::Text(text.to_string()))
+    }
+
+    /// The inverse of `parse`
+    pub fn source_text(&self) -> String {
+        match self {
+            Value::Int(value) => format!("{value}"),
+            Value::Bool(value) => format!("{value}"),
+            Value::Colour([r, g, b]) => format!("#{r:02x}{g:02x}{b:02x}"),
+            Value::Text(text) => format!("\"{text}\""),
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Bool(_) => "bool",
+            Value::Colour(_) => "colour",
+            Value::Text(_) => "text",
+        }
+    }
+
+    /// What is written on the payload.  Colours are drawn as a swatch instead.
+    pub fn payload_text(&self) -> String {
+        match self {
+            Value::Int(value) => format!("{value}"),
+            Value::Bool(true) => "T".to_string(),
+            Value::Bool(false) => "F".to_string(),
+            Value::Colour(_) => String::new(),
+            Value::Text(text) => format!("\"{text}\""),
+        }
+    }
+
+    pub fn text_color(&self) -> Color {
+        match self {
+            Value::Int(_) => Color::srgb(1.0, 0.4, 0.4),
+            Value::Bool(true) => Color::srgb(0.4, 1.0, 0.4),
+            Value::Bool(false) => Color::srgb(0.6, 0.6, 0.6),
+            Value::Colour(_) => Color::NONE,
+            Value::Text(_) => Color::WHITE,
+        }
+    }
+
+    /// The swatch drawn behind the payload text, only visible for colours.
+    pub fn swatch(&self) -> Sprite {
+        match self {
+            Value::Colour([r, g, b]) => {
+                Sprite::from_color(Color::srgb_u8(*r, *g, *b), Vec2::splat(14.0))
+            }
+            _ => Sprite::from_color(Color::NONE, Vec2::ZERO),
+        }
+    }
+}
+
+/// Values in data files are written as they would be typed in
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Value::parse(&source).ok_or_else(|| D::Error::custom(format!("invalid value {source:?}")))
+    }
+}
+
+/// Appends `right` to `left`, or None if the result would be too long.  Use
+/// `truncate_text` to keep as much as fits.
+pub fn concat_text(left: &str, right: &str) -> Option<String> {
+    let joined = format!("{left}{right}");
+    (joined.chars().count() <= MAX_TEXT_LEN).then_some(joined)
+}
+
+pub fn truncate_text(left: &str, right: &str) -> String {
+    left.chars()
+        .chain(right.chars())
+        .take(MAX_TEXT_LEN)
+        .collect()
+}
+
+/// Apply `f` to each channel of two colours.
+pub fn zip_channels(left: &[u8; 3], right: &[u8; 3], f: impl Fn(u8, u8) -> u8) -> [u8; 3] {
+    [0, 1, 2].map(|i| f(left[i], right[i]))
+}
+
+#[cfg(test)]
+mod value_test {
+    use super::*;
+
+    #[test]
+    fn text_length_is_limited() {
+        assert_eq!(concat_text("abcd", "efgh").as_deref(), Some("abcdefgh"));
+        assert_eq!(concat_text("abcd", "efghi"), None);
+        assert_eq!(truncate_text("abcd", "efghi"), "abcdefgh");
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Value::parse(" -12 "), Some(Value::Int(-12)));
+        assert_eq!(Value::parse("true"), Some(Value::Bool(true)));
+        assert_eq!(Value::parse("#ff8000"), Some(Value::Colour([255, 128, 0])));
+        assert_eq!(Value::parse("cat"), Some(Value::Text("cat".to_string())));
+        assert_eq!(Value::parse("\"12\""), Some(Value::Text("12".to_string())));
+        assert_eq!(Value::parse("much too long"), None);
+        assert_eq!(Value::parse(""), None);
+
+        for value in [
+            Value::Int(3),
+            Value::Bool(false),
+            Value::Colour([1, 2, 3]),
+            Value::Text("true".to_string()),
+        ] {
+            assert_eq!(Value::parse(&value.source_text()), Some(value));
+        }
+    }
+
+    #[test]
+    fn payload_text() {
+        assert_eq!(Value::Int(-4).payload_text(), "-4");
+        assert_eq!(Value::Bool(true).payload_text(), "T");
+        assert_eq!(Value::Text("hi".to_string()).payload_text(), "\"hi\"");
+    }
+}
@@ -1,4 +1,11 @@
-use bevy::{ecs::query::QueryData, math::bounding::Aabb2d, prelude::*};
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    asset::io::file::FileAssetReader, ecs::query::QueryData, math::bounding::Aabb2d, prelude::*,
+};
 use bevy_ecs_tilemap::prelude::*;
 use bevy_pancam::PanCam;
 
@@ -99,3 +106,31 @@ pub fn set_camera_limits_from_tilemaps(
     pan_cam.max_x = combined_bounds.max.x;
     pan_cam.max_y = combined_bounds.max.y;
 }
+
+/// A path in the assets directory, for files read straight from disk rather
+/// than through the `AssetServer`.  It's found the same way the asset server
+/// finds it, so it doesn't depend on the working directory.
+pub fn asset_path(path: impl AsRef<Path>) -> PathBuf {
+    FileAssetReader::get_base_path().join("assets").join(path)
+}
+
+/// A path in the player's own data directory, for saves and exports.  That's
+/// `$XDG_DATA_HOME/dafm` or `~/.local/share/dafm` on Linux, and the platform's
+/// equivalent elsewhere, falling back to beside the executable.
+pub fn user_data_path(path: impl AsRef<Path>) -> PathBuf {
+    let var = |name| env::var_os(name).filter(|value| !value.is_empty());
+    let data_dir = if cfg!(windows) {
+        var("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        var("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        var("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| var("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+
+    data_dir
+        .unwrap_or_else(FileAssetReader::get_base_path)
+        .join("dafm")
+        .join(path)
+}
@@ -15,7 +15,7 @@ mod helpers;
 mod main_menu;
 mod space_shooter;
 mod sprite_sheet;
-// mod terrain;
+mod terrain;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, States)]
 enum GameState {
@@ -26,39 +26,42 @@ enum GameState {
 }
 
 fn main() {
-    App::new()
-        .add_plugins(
-            DefaultPlugins
-                .set(ImagePlugin::default_nearest())
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        present_mode: PresentMode::Immediate,
-                        ..default()
-                    }),
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(ImagePlugin::default_nearest())
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    present_mode: PresentMode::Immediate,
                     ..default()
                 }),
-        )
-        .add_plugins(bevy_mod_debugdump::CommandLineArgs)
-        .init_resource::<SpriteSheet>()
-        .add_plugins(EnhancedInputPlugin)
-        .init_state::<GameState>()
-        .insert_state(GameState::MainMenu)
-        .enable_state_scoped_entities::<GameState>()
-        .add_plugins(PhysicsPlugins::default())
-        // .add_plugins(PhysicsDebugPlugin::default())
-        .add_plugins(EguiPlugin::default())
-        .add_plugins(WorldInspectorPlugin::new().run_if(resource_exists::<ShowWorldInspector>))
-        .add_systems(Update, toggle_world_inspector)
-        // .add_plugins(ResourceInspectorPlugin::<PlayerMoveConfig>::default())
-        .add_plugins(PanCamPlugin)
-        .add_plugins(TilemapPlugin)
-        // .add_plugins(terrain::TerrainPlugin)
-        .add_plugins(EntropyPlugin::<WyRand>::default())
-        .add_plugins(main_menu::main_menu_plugin)
-        .add_plugins(space_shooter::space_shooter_plugin)
-        .add_plugins(factory_game::factory_game_plugin)
-        .add_systems(OnEnter(GameState::MainMenu), setup_camera)
-        .run();
+                ..default()
+            }),
+    )
+    .add_plugins(bevy_mod_debugdump::CommandLineArgs)
+    .init_resource::<SpriteSheet>()
+    .add_plugins(EnhancedInputPlugin)
+    .init_state::<GameState>()
+    .insert_state(GameState::MainMenu)
+    .enable_state_scoped_entities::<GameState>()
+    .add_plugins(PhysicsPlugins::default())
+    // .add_plugins(PhysicsDebugPlugin::default())
+    .add_plugins(EguiPlugin::default())
+    .add_plugins(WorldInspectorPlugin::new().run_if(resource_exists::<ShowWorldInspector>))
+    .add_systems(Update, toggle_world_inspector)
+    // .add_plugins(ResourceInspectorPlugin::<PlayerMoveConfig>::default())
+    .add_plugins(PanCamPlugin)
+    .add_plugins(TilemapPlugin)
+    .add_plugins(EntropyPlugin::<WyRand>::default())
+    .add_plugins(main_menu::main_menu_plugin)
+    .add_plugins(space_shooter::space_shooter_plugin)
+    .add_plugins(factory_game::factory_game_plugin)
+    .add_systems(OnEnter(GameState::MainMenu), setup_camera);
+
+    #[cfg(feature = "terrain")]
+    app.add_plugins(terrain::terrain_plugin);
+
+    app.run();
 }
 
 #[derive(Resource)]
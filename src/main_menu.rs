@@ -1,14 +1,26 @@
 use bevy::prelude::*;
 use bevy_egui::input::egui_wants_any_keyboard_input;
 
-use crate::{GameState, toggle_world_inspector};
+use crate::{
+    GameState,
+    factory_game::{
+        BestScores, FACTORY_MAP_PATH, Levels, play_hex_sandbox, play_level, play_tiled_map,
+    },
+    toggle_world_inspector,
+};
 
 pub fn main_menu_plugin(app: &mut App) {
-    app.add_systems(OnEnter(GameState::MainMenu), on_enter)
+    app.init_resource::<MenuPage>()
+        .add_systems(OnEnter(GameState::MainMenu), on_enter)
         .add_systems(
             Update,
             (
-                check_main_menu_keys.run_if(in_state(GameState::MainMenu)),
+                (
+                    check_main_menu_keys,
+                    update_menu_text.run_if(resource_changed::<MenuPage>),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::MainMenu)),
                 check_for_exit.run_if(not(in_state(GameState::MainMenu))),
             )
                 .run_if(not(egui_wants_any_keyboard_input))
@@ -16,22 +28,101 @@ pub fn main_menu_plugin(app: &mut App) {
         );
 }
 
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+enum MenuPage {
+    #[default]
+    Main,
+    LevelSelect,
+}
+
+#[derive(Component)]
+struct MenuText;
+
 fn on_enter(mut commands: Commands) {
+    commands.insert_resource(MenuPage::Main);
     commands.spawn((
         StateScoped(GameState::MainMenu),
-        Text2d::new("1: Shooter\n2: Conveyor"),
+        MenuText,
+        Text2d::default(),
         TextLayout::new_with_justify(JustifyText::Center),
     ));
 }
 
-fn check_main_menu_keys(mut commands: Commands, keys: Res<ButtonInput<KeyCode>>) {
-    match keys.get_just_released().next() {
-        Some(KeyCode::Digit1) => commands.set_state(GameState::SpaceShooter),
-        Some(KeyCode::Digit2) => commands.set_state(GameState::FactoryGame),
-        _ => (),
+fn update_menu_text(
+    page: Res<MenuPage>,
+    levels: Res<Levels>,
+    best_scores: Res<BestScores>,
+    mut text: Single<&mut Text2d, With<MenuText>>,
+) {
+    text.0 = match *page {
+        MenuPage::Main => {
+            "1: Shooter\n2: Conveyor\n3: Campaign\n4: Conveyor on a map\n5: Hex conveyor"
+                .to_string()
+        }
+        MenuPage::LevelSelect => {
+            let mut text = "Campaign\n".to_string();
+            for (i, level) in levels.iter().enumerate().take(9) {
+                text += &format!("\n{}: {}", i + 1, level.name);
+                if let Some(best) = best_scores.get(level) {
+                    text += &format!(
+                        " - {}/3 stars, {} tiles, {:.1}/s",
+                        level.scoring.stars(best),
+                        best.tiles,
+                        best.throughput
+                    );
+                }
+            }
+            text + "\n\nEsc: Back"
+        }
+    };
+}
+
+fn check_main_menu_keys(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut page: ResMut<MenuPage>,
+    levels: Res<Levels>,
+) {
+    let Some(key) = keys.get_just_released().next() else {
+        return;
+    };
+
+    match *page {
+        MenuPage::Main => match key {
+            KeyCode::Digit1 => commands.set_state(GameState::SpaceShooter),
+            KeyCode::Digit2 => play_level(&mut commands, None),
+            KeyCode::Digit3 => *page = MenuPage::LevelSelect,
+            KeyCode::Digit4 => play_tiled_map(&mut commands, FACTORY_MAP_PATH),
+            KeyCode::Digit5 => play_hex_sandbox(&mut commands),
+            _ => (),
+        },
+        MenuPage::LevelSelect => {
+            if *key == KeyCode::Escape {
+                *page = MenuPage::Main;
+            } else if let Some(level) = level_number(key).and_then(|i| levels.get(i)) {
+                play_level(&mut commands, Some(level));
+            }
+        }
     }
 }
 
+/// Levels are chosen with the digit keys, 1 being the first
+fn level_number(key: &KeyCode) -> Option<usize> {
+    [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ]
+    .iter()
+    .position(|digit| digit == key)
+}
+
 fn check_for_exit(mut commands: Commands, mut keys: ResMut<ButtonInput<KeyCode>>) {
     if keys.clear_just_pressed(KeyCode::Escape) {
         commands.set_state(GameState::MainMenu);
@@ -51,9 +51,12 @@ pub enum GameSprite {
     ConveyorInSOutE,
     ConveyorInNSOutE,
     ConveyorInNSWOutE,
+    /// Straight belt for hex maps
+    ConveyorInSWOutNE,
     BlankSquare,
     Delete,
     Arrow,
+    ArrowNorthEast,
     Generator,
     Sink,
     Distributor,
@@ -63,6 +66,32 @@ pub enum GameSprite {
     BridgeBoth,
     OperatorPlus,
     OperatorMultiply,
+    InserterBase,
+    InserterArm,
+    Storage,
+    OperatorSubtract,
+    OperatorDivide,
+    OperatorModulo,
+    OperatorMin,
+    OperatorMax,
+    OperatorPower,
+    UnaryIncrement,
+    UnaryDouble,
+    UnarySquare,
+    UnaryAddConstant,
+    UnaryMultiplyConstant,
+    SinkAcceptsErrors,
+    UnaryNegate,
+    ResearchSink,
+    Wire,
+    Sensor,
+    Condition,
+    Processor,
+    ProviderPort,
+    RequesterPort,
+    Drone,
+    PowerPlant,
+    PowerPole,
 }
 
 impl GameSprite {
@@ -81,9 +110,11 @@ impl GameSprite {
             ConveyorInSOutE => 13,
             ConveyorInNSOutE => 14,
             ConveyorInNSWOutE => 15,
+            ConveyorInSWOutNE => 7,
             BlankSquare => 20,
             Delete => 21,
             Arrow => 22,
+            ArrowNorthEast => 8,
             Generator => 30,
             Sink => 31,
             Distributor => 32,
@@ -93,6 +124,32 @@ impl GameSprite {
             BridgeBoth => 35,
             OperatorPlus => 36,
             OperatorMultiply => 37,
+            InserterBase => 43,
+            InserterArm => 44,
+            Storage => 45,
+            OperatorSubtract => 50,
+            OperatorDivide => 51,
+            OperatorModulo => 52,
+            OperatorMin => 53,
+            OperatorMax => 54,
+            OperatorPower => 55,
+            UnaryIncrement => 56,
+            UnaryDouble => 57,
+            UnarySquare => 58,
+            UnaryAddConstant => 59,
+            UnaryMultiplyConstant => 60,
+            SinkAcceptsErrors => 61,
+            UnaryNegate => 62,
+            ResearchSink => 63,
+            Wire => 64,
+            Sensor => 65,
+            Condition => 66,
+            Processor => 67,
+            ProviderPort => 68,
+            RequesterPort => 69,
+            Drone => 70,
+            PowerPlant => 71,
+            PowerPole => 72,
         }
     }
 
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use bevy::{log::info, math::ops::ln};
+use bevy::{
+    log::{debug, info},
+    math::ops::ln,
+};
 use bevy_ecs_tilemap::{
     helpers::square_grid::neighbors::{Neighbors, SquareDirection},
     map::TilemapSize,
@@ -68,6 +71,43 @@ impl Generator {
             .collect()
     }
 
+    /// Steps until every tile is collapsed, starting again whenever the
+    /// options for a tile run out
+    pub fn generate(&mut self) {
+        loop {
+            while !self.step() {}
+
+            if self
+                .get()
+                .iter()
+                .all(|t| t.state != TileState::Collapsed(0))
+            {
+                return;
+            }
+
+            info!("Map generation failed, trying again");
+            self.reset();
+        }
+    }
+
+    /// The names of the Wang colours at each corner of the tile at `pos`,
+    /// once it has collapsed
+    pub fn corner_colors(&self, pos: &TilePos) -> Option<[&str; 4]> {
+        let MapEntry::Collapsed { class, .. } = self.grid[pos.to_index(&self.size)] else {
+            return None;
+        };
+
+        // Corner sets only use the odd entries of the Wang ID
+        let id = &self.tile_set.classes.classes[class.0].wang_id.0;
+        let name = |color: u8| {
+            usize::from(color)
+                .checked_sub(1)
+                .and_then(|index| self.tile_set.color_names.get(index))
+                .map_or("", String::as_str)
+        };
+        Some([name(id[1]), name(id[3]), name(id[5]), name(id[7])])
+    }
+
     pub fn step(&mut self) -> bool {
         let Some(tile) = self.pick_tile_to_update() else {
             // No tiles to update: we're done!
@@ -282,6 +322,7 @@ fn calculate_entropy(options: &[TileClass], tile_set: &TileSet) -> f32 {
 }
 
 struct TileSet {
+    color_names: Vec<String>,
     combos: TileCombos,
     classes: TileClasses,
     tiles: Vec<TileOption>,
@@ -416,9 +457,9 @@ impl TileClasses {
         }
 
         let mut classes: Vec<TileClassData> = classes.into_values().collect();
-        classes.sort_by(|a, b| a.wang_id.0.cmp(&b.wang_id.0));
+        classes.sort_by_key(|class| class.wang_id.0);
 
-        println!("{classes:?}");
+        debug!("{classes:?}");
 
         TileClasses { classes }
     }
@@ -475,7 +516,7 @@ impl TileSet {
             }
         }
 
-        tiles.sort_by(|a, b| a.index.cmp(&b.index));
+        tiles.sort_by_key(|tile| tile.index);
 
         let mut horizontal = Vec::new();
         let mut vertical = Vec::new();
@@ -494,7 +535,7 @@ impl TileSet {
             }
         }
 
-        println!(
+        info!(
             "{} tiles, {} classes {} horizontal combos, {} vertical combos",
             tiles.len(),
             classes.len(),
@@ -507,7 +548,14 @@ impl TileSet {
             vertical: Combos::new(&vertical),
         };
 
+        let color_names = tileset.wang_sets[0]
+            .wang_colors
+            .iter()
+            .map(|color| color.name.clone())
+            .collect();
+
         TileSet {
+            color_names,
             combos,
             classes,
             tiles,
@@ -2,6 +2,7 @@ use super::mapgen;
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
 
+#[allow(dead_code)]
 pub struct MapGenPlugin;
 
 #[derive(Event, PartialEq)]
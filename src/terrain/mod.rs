@@ -1,20 +1,29 @@
-// Experiments with terrain generation / wave form collapse etc.
+// Experiments with terrain generation / wave form collapse etc.  The
+// generator is also used for the factory game's resource deposits, while the
+// experiments themselves are only built with the `terrain` feature.
+#[cfg(feature = "terrain")]
 use bevy::prelude::*;
+#[cfg(feature = "terrain")]
 use bevy_ecs_tilemap::prelude::*;
 
+#[cfg(feature = "terrain")]
+#[allow(dead_code)]
 mod map;
-mod mapgen;
+pub mod mapgen;
+#[cfg(feature = "terrain")]
 mod mapgen_viz;
 
-use mapgen_viz::MapGenPlugin;
-
+#[cfg(feature = "terrain")]
 use crate::helpers::set_camera_limits_from_tilemaps;
 
+#[cfg(feature = "terrain")]
+#[allow(dead_code)]
 pub struct TerrainPlugin;
 
+#[cfg(feature = "terrain")]
 pub fn terrain_plugin(app: &mut App) {
     assert!(app.is_plugin_added::<TilemapPlugin>());
-    app.add_plugins(MapGenPlugin)
+    app.add_plugins(mapgen_viz::mapgen_plugin)
         // .add_plugins(map::MapPlugin)
         .add_systems(PostStartup, set_camera_limits_from_tilemaps);
 }